@@ -0,0 +1,402 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use minisign::{PublicKey, SecretKey, SignatureBox};
+use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::UnixStream;
+use tokio::sync::Mutex;
+
+use crate::cli::AgentArgs;
+use crate::common::{bind_private_socket, get_store_dir, get_verification_key_path, sha256_file};
+use crate::interactive::GetPassword;
+use crate::sign::{load_signing_keys, make_named_signature, make_signature};
+
+/// The environment variable that other commands read to find the agent's socket.
+pub const AGENT_SOCKET_ENV: &str = "CHECKED_AGENT_SOCK";
+
+/// A request to the agent, sent as a line of JSON.
+#[derive(Serialize, Deserialize, Debug)]
+enum AgentRequest {
+    /// Ask which key the agent holds.
+    Key,
+    /// Sign the file at this absolute path.
+    Sign(PathBuf),
+}
+
+/// A response from the agent, sent as a line of JSON.
+#[derive(Serialize, Deserialize, Debug)]
+enum AgentResponse {
+    /// The name of the key that the agent holds, and its base64 encoded verification key.
+    Key {
+        name: String,
+        verification_key: String,
+    },
+    Signature(String),
+    Error(String),
+}
+
+struct AgentState {
+    name: String,
+    sk: SecretKey,
+    vk: Option<PublicKey>,
+    /// The base64 encoded verification key for [AgentState::sk].
+    verification_key: String,
+    confirm: bool,
+    // Only ask for one confirmation at a time.
+    confirm_lock: Mutex<()>,
+}
+
+/// Unlock a signing key once and sign files for other `checked` commands until the key times out.
+///
+/// The agent listens on a Unix socket that only the current user can connect to. Commands that
+/// sign look for the agent before prompting for a password, and use it if it holds the key that
+/// they were asked to sign with. They check the agent's signatures with their own copy of the
+/// verification key before using them.
+///
+/// With [AgentArgs::confirm], the agent signs a private copy of the file, so that the content that
+/// is signed is the content whose digest was shown when asking for confirmation.
+pub async fn agent(mut agent_args: AgentArgs) -> anyhow::Result<()> {
+    let store_dir = get_store_dir(agent_args.config_dir.clone())?;
+    let password = agent_args.get_password()?;
+    let (sk, vk) = load_signing_keys(&store_dir, &agent_args.name, password)?;
+    let verification_key = PublicKey::from_secret_key(&sk)
+        .context("Could not get the verification key for the signing key")?
+        .to_base64();
+
+    let socket = agent_args
+        .socket
+        .clone()
+        .unwrap_or_else(|| agent_socket_path(&store_dir));
+    let listener = bind_private_socket(&socket)?;
+
+    println!(
+        "Agent holding '{}' for {} seconds, listening on {}",
+        agent_args.name,
+        agent_args.ttl,
+        socket.display()
+    );
+    if agent_args.socket.is_some() {
+        println!(
+            "Set {}={} for other commands to find the agent",
+            AGENT_SOCKET_ENV,
+            socket.display()
+        );
+    }
+
+    let state = Arc::new(AgentState {
+        name: agent_args.name.clone(),
+        sk,
+        vk,
+        verification_key,
+        confirm: agent_args.confirm,
+        confirm_lock: Mutex::new(()),
+    });
+
+    let timeout = tokio::time::sleep(Duration::from_secs(agent_args.ttl));
+    tokio::pin!(timeout);
+
+    loop {
+        tokio::select! {
+            _ = &mut timeout => break,
+            accepted = listener.accept() => {
+                let (stream, _) = accepted?;
+                let state = state.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, state).await {
+                        eprintln!("Agent connection failed: {:?}", e);
+                    }
+                });
+            }
+        }
+    }
+
+    // Dropping the state when the connections finish forgets the key.
+    std::fs::remove_file(&socket).ok();
+    println!("Key '{}' timed out, the agent has stopped", agent_args.name);
+
+    Ok(())
+}
+
+/// Where commands look for the agent, unless the socket is set with [AGENT_SOCKET_ENV].
+pub(crate) fn agent_socket_path(store_dir: &Path) -> PathBuf {
+    match std::env::var_os(AGENT_SOCKET_ENV) {
+        Some(socket) if !socket.is_empty() => PathBuf::from(socket),
+        _ => store_dir.join("agent.sock"),
+    }
+}
+
+async fn handle_connection(stream: UnixStream, state: Arc<AgentState>) -> anyhow::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        let response = match serde_json::from_str::<AgentRequest>(&line) {
+            Ok(AgentRequest::Key) => AgentResponse::Key {
+                name: state.name.clone(),
+                verification_key: state.verification_key.clone(),
+            },
+            Ok(AgentRequest::Sign(file)) => match sign_for_client(&state, file).await {
+                Ok(signature) => AgentResponse::Signature(signature),
+                Err(e) => AgentResponse::Error(format!("{:#}", e)),
+            },
+            Err(e) => AgentResponse::Error(format!("Invalid request: {}", e)),
+        };
+
+        let mut response = serde_json::to_string(&response)?;
+        response.push('\n');
+        writer.write_all(response.as_bytes()).await?;
+    }
+
+    Ok(())
+}
+
+async fn sign_for_client(state: &AgentState, file: PathBuf) -> anyhow::Result<String> {
+    if !file.is_absolute() {
+        anyhow::bail!("The path to sign must be absolute - {:?}", file);
+    }
+
+    if !state.confirm {
+        let signature = make_signature(&file, &state.sk, state.vk.as_ref())?;
+        println!("Signed {}", file.display());
+
+        return Ok(signature.into_string());
+    }
+
+    let _guard = state.confirm_lock.lock().await;
+
+    // The file could change while waiting for confirmation, so sign a copy of what was confirmed.
+    let (copy, sha256) = tokio::task::spawn_blocking({
+        let file = file.clone();
+        move || copy_for_signing(&file)
+    })
+    .await??;
+
+    let prompt = format!(
+        "Sign {} (SHA-256 {}) with '{}'?",
+        file.display(),
+        sha256,
+        state.name
+    );
+    let confirmed = tokio::task::spawn_blocking(move || {
+        dialoguer::Confirm::new()
+            .with_prompt(prompt)
+            .default(false)
+            .interact()
+    })
+    .await??;
+
+    if !confirmed {
+        anyhow::bail!("Signing was refused by the agent");
+    }
+
+    let signature = make_named_signature(copy.path(), &file, &state.sk, state.vk.as_ref())?;
+    println!("Signed {} with SHA-256 {}", file.display(), sha256);
+
+    Ok(signature.into_string())
+}
+
+/// Copy `file` to a temporary file that only the current user can access, giving the copy and its
+/// hex encoded SHA-256 digest.
+fn copy_for_signing(file: &Path) -> anyhow::Result<(NamedTempFile, String)> {
+    let mut source =
+        std::fs::File::open(file).with_context(|| format!("Could not open {:?}", file))?;
+    let mut copy = NamedTempFile::new().context("Could not create temporary file")?;
+    std::io::copy(&mut source, copy.as_file_mut())
+        .with_context(|| format!("Could not copy {:?}", file))?;
+
+    let sha256 = hex::encode(sha256_file(copy.path())?);
+
+    Ok((copy, sha256))
+}
+
+/// A connection to a running [agent].
+pub(crate) struct AgentClient {
+    lines: tokio::io::Lines<BufReader<tokio::net::unix::OwnedReadHalf>>,
+    writer: tokio::net::unix::OwnedWriteHalf,
+    /// The local verification key for the key that the agent holds, which its signatures are
+    /// checked with.
+    vk: PublicKey,
+}
+
+impl AgentClient {
+    /// Connect to the agent if one is running and holds the key called `name`.
+    ///
+    /// The agent is only used if its key matches the verification key for `name` in `store_dir`.
+    pub(crate) async fn connect(store_dir: &Path, name: &str) -> Option<Self> {
+        let socket = agent_socket_path(store_dir);
+        if !socket.exists() {
+            return None;
+        }
+
+        let vk_path = get_verification_key_path(store_dir, name);
+        let vk = match PublicKey::from_file(&vk_path) {
+            Ok(vk) => vk,
+            Err(e) => {
+                println!(
+                    "Not using the agent, the verification key to check its signatures with was not found: {:?}",
+                    e
+                );
+                return None;
+            }
+        };
+
+        Self::connect_socket(&socket, name, vk).await
+    }
+
+    async fn connect_socket(socket: &Path, name: &str, vk: PublicKey) -> Option<Self> {
+        let stream = UnixStream::connect(socket).await.ok()?;
+        let (reader, writer) = stream.into_split();
+        let mut client = AgentClient {
+            lines: BufReader::new(reader).lines(),
+            writer,
+            vk,
+        };
+
+        match client.request(&AgentRequest::Key).await {
+            Ok(AgentResponse::Key {
+                name: key_name,
+                verification_key,
+            }) if key_name == name => {
+                if verification_key != client.vk.to_base64() {
+                    println!(
+                        "Not using the agent, its key '{}' does not match your verification key",
+                        name
+                    );
+                    return None;
+                }
+
+                Some(client)
+            }
+            _ => None,
+        }
+    }
+
+    /// Ask the agent to sign `file`, returning the signature once it has been checked against the
+    /// file with the local verification key.
+    pub(crate) async fn sign(&mut self, file: &Path) -> anyhow::Result<String> {
+        let file = std::fs::canonicalize(file)
+            .with_context(|| format!("Could not find the file to sign - {:?}", file))?;
+
+        match self.request(&AgentRequest::Sign(file.clone())).await? {
+            AgentResponse::Signature(signature) => {
+                let signature_box = SignatureBox::from_string(&signature)
+                    .context("The agent returned an invalid signature")?;
+                let reader = std::io::BufReader::new(std::fs::File::open(&file)?);
+                minisign::verify(&self.vk, &signature_box, reader, true, false, false)
+                    .with_context(|| {
+                        format!(
+                            "The agent's signature does not verify for {:?} with your verification key",
+                            file
+                        )
+                    })?;
+
+                Ok(signature)
+            }
+            AgentResponse::Error(e) => Err(anyhow::anyhow!("The agent could not sign: {}", e)),
+            r => Err(anyhow::anyhow!(
+                "Unexpected response from the agent: {:?}",
+                r
+            )),
+        }
+    }
+
+    async fn request(&mut self, request: &AgentRequest) -> anyhow::Result<AgentResponse> {
+        let mut line = serde_json::to_string(request)?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes()).await?;
+
+        let response = self
+            .lines
+            .next_line()
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("The agent closed the connection"))?;
+
+        Ok(serde_json::from_str(&response)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn sign_through_agent() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("agent.sock");
+        let listener = bind_private_socket(&socket).unwrap();
+
+        let key_pair = minisign::KeyPair::generate_unencrypted_keypair().unwrap();
+        let state = Arc::new(AgentState {
+            name: "default".to_string(),
+            sk: key_pair.sk,
+            vk: Some(key_pair.pk.clone()),
+            verification_key: key_pair.pk.to_base64(),
+            confirm: false,
+            confirm_lock: Mutex::new(()),
+        });
+
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(handle_connection(stream, state.clone()));
+            }
+        });
+
+        let file = dir.path().join("test.txt");
+        std::fs::write(&file, "test").unwrap();
+
+        assert!(
+            AgentClient::connect_socket(&socket, "other", key_pair.pk.clone())
+                .await
+                .is_none()
+        );
+        // An agent holding a different key than the local verification key is not used.
+        let other_key_pair = minisign::KeyPair::generate_unencrypted_keypair().unwrap();
+        assert!(
+            AgentClient::connect_socket(&socket, "default", other_key_pair.pk)
+                .await
+                .is_none()
+        );
+        let mut client = AgentClient::connect_socket(&socket, "default", key_pair.pk.clone())
+            .await
+            .unwrap();
+
+        let signature = client.sign(&file).await.unwrap();
+        let signature = minisign::SignatureBox::from_string(&signature).unwrap();
+        minisign::verify(
+            &key_pair.pk,
+            &signature,
+            std::io::Cursor::new(b"test"),
+            true,
+            false,
+            false,
+        )
+        .unwrap();
+
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(
+            0o600,
+            std::fs::metadata(&socket).unwrap().permissions().mode() & 0o777
+        );
+    }
+
+    #[test]
+    fn copy_before_confirming() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("test.txt");
+        std::fs::write(&file, "test").unwrap();
+
+        let (copy, sha256) = copy_for_signing(&file).unwrap();
+        std::fs::write(&file, "modified").unwrap();
+
+        assert_eq!(
+            "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08",
+            sha256
+        );
+        assert_eq!("test", std::fs::read_to_string(copy.path()).unwrap());
+    }
+}
@@ -1,11 +1,10 @@
 //! CLI tool wrapping the `checked_cli` library.
 
 use checked_cli::prelude::*;
-use clap::Parser;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
+    let cli = parse_cli()?;
 
     match cli.command {
         Commands::Generate(generate_args) => {
@@ -14,10 +13,41 @@ async fn main() -> anyhow::Result<()> {
         Commands::Sign(sign_args) => {
             sign(sign_args).await?;
         }
+        Commands::SignRelease(sign_release_args) => {
+            sign_release(sign_release_args).await?;
+        }
         Commands::Verify(verify_args) => verify(verify_args)?,
         Commands::Distribute(distribute_args) => distribute(distribute_args).await?,
         Commands::Fetch(fetch_args) => {
-            fetch(fetch_args).await?;
+            if fetch_args.manifest.is_some() {
+                fetch_manifest(fetch_args).await?;
+            } else {
+                fetch(fetch_args).await?;
+            }
+        }
+        Commands::Identify(identify_args) => {
+            identify(identify_args).await?;
+        }
+        Commands::ClaimPublisher(claim_publisher_args) => {
+            claim_publisher(claim_publisher_args).await?;
+        }
+        Commands::ClaimDomain(claim_domain_args) => {
+            claim_domain(claim_domain_args).await?;
+        }
+        Commands::VerifyDomain(verify_domain_args) => {
+            verify_domain(verify_domain_args).await?;
+        }
+        Commands::Proxy(proxy_args) => {
+            proxy(proxy_args).await?;
+        }
+        Commands::Daemon(daemon_args) => {
+            daemon(daemon_args).await?;
+        }
+        Commands::Config(config_args) => config(config_args, cli.profile)?,
+        Commands::Credentials(credentials_args) => credentials(credentials_args)?,
+        #[cfg(unix)]
+        Commands::Agent(agent_args) => {
+            agent(agent_args).await?;
         }
     }
 
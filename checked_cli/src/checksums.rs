@@ -0,0 +1,288 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use url::Url;
+
+use crate::common::sha256_file;
+use crate::sidecar::{download_signature, make_sidecar_url, verify_with_keys, SidecarKey};
+
+/// The name of the checksums manifest that is created when signing a directory.
+pub const CHECKSUMS_FILE_NAME: &str = "SHA256SUMS";
+
+/// The result of checking a fetched asset against a signed checksums manifest.
+#[derive(Debug)]
+pub struct ChecksumsReport {
+    /// Where the checksums manifest was fetched from.
+    pub manifest_url: Url,
+    /// Where the signature for the manifest was fetched from.
+    pub signature_url: Url,
+    /// Trusted keys that the manifest's signature was verified with.
+    pub passed_keys: Vec<SidecarKey>,
+    /// Trusted keys that the manifest's signature claims to be made by, but which failed to verify
+    /// the manifest.
+    pub failed_keys: Vec<SidecarKey>,
+    /// The SHA-256 digest listed for the asset in the manifest, if it is listed.
+    pub listed_sha256: Option<Vec<u8>>,
+    /// The SHA-256 digest of the fetched asset.
+    pub asset_sha256: Vec<u8>,
+}
+
+impl ChecksumsReport {
+    /// Whether the manifest was signed by a trusted key and lists the fetched asset's digest.
+    pub fn passed(&self) -> bool {
+        !self.passed_keys.is_empty()
+            && self.failed_keys.is_empty()
+            && self.listed_sha256.as_ref() == Some(&self.asset_sha256)
+    }
+
+    /// Fail unless the manifest [passed](ChecksumsReport::passed), explaining why it didn't.
+    pub(crate) fn ensure_passed(&self) -> anyhow::Result<()> {
+        if self.passed() {
+            return Ok(());
+        }
+
+        let reason = if self.passed_keys.is_empty() || !self.failed_keys.is_empty() {
+            "its signature was not verified by a key that you trust"
+        } else if self.listed_sha256.is_none() {
+            "the asset is not listed in it"
+        } else {
+            "the asset's SHA-256 digest does not match it"
+        };
+
+        anyhow::bail!(
+            "The asset could not be confirmed by the checksums manifest at {} because {}",
+            self.manifest_url,
+            reason
+        )
+    }
+}
+
+/// Write a [CHECKSUMS_FILE_NAME] manifest, in the format produced by `sha256sum`, for the files in
+/// `dir`.
+///
+/// Only files directly in `dir` are included. Signatures and any existing manifest are skipped. An
+/// existing manifest is only replaced if `overwrite` is set.
+pub(crate) fn write_checksums_manifest(dir: &Path, overwrite: bool) -> anyhow::Result<PathBuf> {
+    let files = list_release_files(dir)?;
+    if files.is_empty() {
+        anyhow::bail!("No files to include in the checksums manifest in {:?}", dir);
+    }
+
+    let manifest_path = dir.join(CHECKSUMS_FILE_NAME);
+    let mut options = std::fs::File::options();
+    if overwrite {
+        options.write(true).create(true).truncate(true);
+    } else {
+        options.write(true).create_new(true);
+    }
+    let mut manifest = match options.open(&manifest_path) {
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            anyhow::bail!(
+                "Checksums manifest already exists - {:?}, use `--overwrite-manifest` to replace it",
+                manifest_path
+            );
+        }
+        r => r.with_context(|| format!("Could not create {:?}", manifest_path))?,
+    };
+    for name in files {
+        writeln!(
+            manifest,
+            "{}  {}",
+            hex::encode(sha256_file(dir.join(&name))?),
+            name
+        )?;
+    }
+    manifest.flush()?;
+
+    println!("Checksums manifest created in: {}", manifest_path.display());
+
+    Ok(manifest_path)
+}
+
+/// The names of the files directly in `dir` that make up a release, sorted by name. Signatures and
+/// checksums manifests are skipped.
+pub(crate) fn list_release_files(dir: &Path) -> anyhow::Result<Vec<String>> {
+    let mut files = std::fs::read_dir(dir)
+        .with_context(|| format!("Could not read directory {:?}", dir))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().map(|t| t.is_file()).unwrap_or(false))
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| !name.starts_with(CHECKSUMS_FILE_NAME) && !name.ends_with(".minisig"))
+        .collect::<Vec<_>>();
+    files.sort();
+
+    Ok(files)
+}
+
+/// Parse a checksums manifest in the format produced by `sha256sum`, in either text or binary mode.
+///
+/// Returns the digest and file name for each line. Blank lines and comments are skipped.
+pub(crate) fn parse_checksums_manifest(content: &str) -> anyhow::Result<Vec<(Vec<u8>, String)>> {
+    content
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (digest, name) = line
+                .split_once(' ')
+                .ok_or_else(|| anyhow::anyhow!("Invalid checksums line: {}", line))?;
+            let digest = hex::decode(digest)
+                .ok()
+                .filter(|d| d.len() == 32)
+                .ok_or_else(|| anyhow::anyhow!("Invalid SHA-256 digest: {}", digest))?;
+            let name = name
+                .strip_prefix(' ')
+                .or_else(|| name.strip_prefix('*'))
+                .unwrap_or(name);
+
+            Ok((digest, name.trim_start_matches("./").to_string()))
+        })
+        .collect()
+}
+
+/// Check `check_file`, which was fetched from `fetch_url`, against the checksums manifest at
+/// `manifest_url`.
+///
+/// The manifest's signature is found by applying `sidecar_pattern` to the manifest URL and is
+/// checked with the `keys` that it claims to be made by.
+pub(crate) async fn check_checksums(
+    manifest_url: Url,
+    sidecar_pattern: &str,
+    fetch_url: &Url,
+    check_file: &Path,
+    keys: &[SidecarKey],
+) -> anyhow::Result<ChecksumsReport> {
+    println!("Fetching checksums manifest from {}", manifest_url);
+    let response = reqwest::get(manifest_url.clone()).await?;
+    if !response.status().is_success() {
+        anyhow::bail!(
+            "Failed to fetch checksums manifest: {:?}",
+            response.status()
+        );
+    }
+    // The signature is over the exact bytes that were served, so they are only decoded for parsing.
+    let manifest = response.bytes().await?;
+
+    let signature_url = make_sidecar_url(sidecar_pattern, &manifest_url)?;
+    let signature = download_signature(&signature_url)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("No signature found at {}", signature_url))?;
+
+    let mut manifest_file = tempfile::NamedTempFile::new()?;
+    manifest_file.write_all(&manifest)?;
+    manifest_file.flush()?;
+
+    let (passed_keys, failed_keys) = verify_with_keys(&signature, manifest_file.path(), keys)?;
+
+    let asset_name = asset_name(fetch_url)?;
+    let manifest = String::from_utf8_lossy(&manifest);
+    let listed_sha256 = parse_checksums_manifest(manifest.trim_start_matches('\u{feff}'))?
+        .into_iter()
+        .find(|(_, name)| *name == asset_name)
+        .map(|(digest, _)| digest);
+
+    Ok(ChecksumsReport {
+        manifest_url,
+        signature_url,
+        passed_keys,
+        failed_keys,
+        listed_sha256,
+        asset_sha256: sha256_file(check_file)?,
+    })
+}
+
+/// The file name that `fetch_url` is expected to be listed under in a checksums manifest, which is
+/// its last path segment without percent-encoding.
+fn asset_name(fetch_url: &Url) -> anyhow::Result<String> {
+    let segment = fetch_url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .ok_or_else(|| anyhow::anyhow!("Invalid URL"))?;
+
+    Ok(percent_encoding::percent_decode_str(segment)
+        .decode_utf8()
+        .with_context(|| format!("Invalid file name in URL: {}", segment))?
+        .into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_and_parse_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("b.txt"), "test").unwrap();
+        std::fs::write(dir.path().join("a.txt"), "other").unwrap();
+        std::fs::write(dir.path().join("a.txt.minisig"), "ignored").unwrap();
+        std::fs::create_dir(dir.path().join("nested")).unwrap();
+
+        let manifest_path = write_checksums_manifest(dir.path(), false).unwrap();
+        assert_eq!(dir.path().join(CHECKSUMS_FILE_NAME), manifest_path);
+
+        // An existing manifest is only replaced when asked.
+        assert!(write_checksums_manifest(dir.path(), false).is_err());
+        write_checksums_manifest(dir.path(), true).unwrap();
+
+        let manifest = std::fs::read_to_string(manifest_path).unwrap();
+        let entries = parse_checksums_manifest(&manifest).unwrap();
+
+        assert_eq!(2, entries.len());
+        assert_eq!("a.txt", entries[0].1);
+        assert_eq!("b.txt", entries[1].1);
+        assert_eq!(
+            "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08",
+            hex::encode(&entries[1].0)
+        );
+    }
+
+    #[test]
+    fn parse_binary_mode_and_comments() {
+        let manifest = "# release v1\n\n9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08 *./test.txt\n";
+
+        let entries = parse_checksums_manifest(manifest).unwrap();
+
+        assert_eq!(1, entries.len());
+        assert_eq!("test.txt", entries[0].1);
+    }
+
+    #[test]
+    fn asset_name_is_decoded() {
+        let fetch_url = Url::parse("https://example.com/v1/my%20tool%2B1.tar.gz?x=1").unwrap();
+
+        assert_eq!("my tool+1.tar.gz", asset_name(&fetch_url).unwrap());
+    }
+
+    #[test]
+    fn fail_unless_manifest_passed() {
+        let report = |listed_sha256: Option<Vec<u8>>| ChecksumsReport {
+            manifest_url: Url::parse("https://example.com/SHA256SUMS").unwrap(),
+            signature_url: Url::parse("https://example.com/SHA256SUMS.minisig").unwrap(),
+            passed_keys: vec![],
+            failed_keys: vec![],
+            listed_sha256,
+            asset_sha256: vec![1; 32],
+        };
+
+        // Listed with the right digest, but nobody you trust signed the manifest.
+        let err = report(Some(vec![1; 32])).ensure_passed().unwrap_err();
+        assert!(err.to_string().contains("not verified"), "{}", err);
+
+        let signed = |listed_sha256| ChecksumsReport {
+            passed_keys: vec![SidecarKey::for_test()],
+            ..report(listed_sha256)
+        };
+        assert!(signed(Some(vec![1; 32])).ensure_passed().is_ok());
+        let err = signed(Some(vec![2; 32])).ensure_passed().unwrap_err();
+        assert!(err.to_string().contains("does not match"), "{}", err);
+        let err = signed(None).ensure_passed().unwrap_err();
+        assert!(err.to_string().contains("not listed"), "{}", err);
+    }
+
+    #[test]
+    fn reject_invalid_digest() {
+        assert!(parse_checksums_manifest("abcd  test.txt").is_err());
+        assert!(parse_checksums_manifest("not-a-line").is_err());
+    }
+}
@@ -0,0 +1,68 @@
+use anyhow::Context;
+use holochain_types::prelude::{ActionHash, ExternIO};
+
+use checked_types::canonical_url::canonicalize_url_prefix;
+use checked_types::{CreatePublisherClaim, VerificationKeyType};
+
+use crate::cli::ClaimPublisherArgs;
+use crate::common::{get_store_dir, get_verification_key_path};
+use crate::hc_client::{get_authenticated_app_agent_client, CredentialScope};
+
+/// Declare yourself the publisher of the assets under a URL prefix.
+///
+/// Signatures that you create with the named key, for URLs under the prefix, will be shown to
+/// anyone fetching those URLs as coming from the publisher rather than from another user. Anyone can
+/// claim a prefix, so the claim is only trusted once the key is also verified for the domain with
+/// [claim_domain](crate::domain::claim_domain).
+pub async fn claim_publisher(claim_publisher_args: ClaimPublisherArgs) -> anyhow::Result<()> {
+    let url_prefix = canonicalize_url_prefix(&claim_publisher_args.url_prefix)
+        .map_err(|e| anyhow::anyhow!(e))?
+        .to_string();
+
+    let store_dir = get_store_dir(claim_publisher_args.config_dir.clone())?;
+    let vk_path = get_verification_key_path(&store_dir, &claim_publisher_args.name);
+    let verification_key = std::fs::read_to_string(&vk_path).with_context(|| {
+        format!(
+            "Verification key '{}' not found, maybe you need to create it first?",
+            claim_publisher_args.name
+        )
+    })?;
+
+    let admin_port = claim_publisher_args.connect.admin_port().await?;
+
+    let app_client = get_authenticated_app_agent_client(
+        admin_port,
+        claim_publisher_args.config_dir.clone(),
+        claim_publisher_args.app_id.clone(),
+        claim_publisher_args.connect.timeout(),
+        CredentialScope::Write,
+    )
+    .await?;
+
+    println!(
+        "Claiming to publish assets under {} with key: {}",
+        url_prefix, claim_publisher_args.name
+    );
+
+    let response = app_client
+        .call_zome(
+            "fetch",
+            "create_publisher_claim",
+            ExternIO::encode(CreatePublisherClaim {
+                url_prefix,
+                key_type: VerificationKeyType::MiniSignEd25519,
+                verification_key,
+            })?,
+        )
+        .await
+        .context("Failed to create publisher claim")?;
+
+    let publisher_claim_address: ActionHash = response.decode()?;
+    println!(
+        "Publisher claim stored on Holochain at: {:?}",
+        publisher_claim_address
+    );
+    println!("Others will only trust this claim if the key is also verified for the domain, see `checked claim-domain`");
+
+    Ok(())
+}
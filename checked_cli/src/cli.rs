@@ -8,6 +8,13 @@ pub struct Cli {
     /// The command that has been run.
     #[command(subcommand)]
     pub command: Commands,
+
+    /// The profile from the config file, `~/.checked/config.toml`, to take default values from.
+    ///
+    /// Defaults to the `CHECKED_PROFILE` environment variable if it is set, otherwise the config
+    /// file's `default_profile`.
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
 }
 
 /// The main commands that can be run by the CLI.
@@ -19,6 +26,9 @@ pub enum Commands {
     /// Sign a file
     Sign(SignArgs),
 
+    /// Sign every file in a release directory, with a single password prompt
+    SignRelease(SignReleaseArgs),
+
     /// Verify a file
     Verify(VerifyArgs),
 
@@ -27,6 +37,34 @@ pub enum Commands {
 
     /// Fetch an asset from a URL and check signatures for it
     Fetch(FetchArgs),
+
+    /// Find signatures for a file by its content, without knowing where it came from
+    Identify(IdentifyArgs),
+
+    /// Declare yourself the publisher of the assets under a URL prefix
+    ClaimPublisher(ClaimPublisherArgs),
+
+    /// Claim that one of your keys belongs to the owner of a domain
+    ClaimDomain(ClaimDomainArgs),
+
+    /// Check the keys claimed for a domain against the domain's `.well-known/checked.json`
+    VerifyDomain(VerifyDomainArgs),
+
+    /// Run an HTTP proxy that only serves assets whose signatures pass a policy
+    Proxy(ProxyArgs),
+
+    /// Run a local API for checking, fetching and signing without starting `checked` each time
+    Daemon(DaemonArgs),
+
+    /// View and edit the profiles in the config file
+    Config(ConfigArgs),
+
+    /// List or forget the Holochain credentials that are saved for each app install
+    Credentials(CredentialsArgs),
+
+    /// Unlock a signing key once and sign for other `checked` commands until it times out
+    #[cfg(unix)]
+    Agent(AgentArgs),
 }
 
 /// Arguments for the [generate](crate::generate::generate) operation.
@@ -38,19 +76,24 @@ pub struct GenerateArgs {
     #[arg(long, short, default_value_t = String::from("default"))]
     pub name: String,
 
-    /// The admin port for Holochain.
-    #[arg(long, short)]
-    pub port: Option<u16>,
+    /// How to find and connect to Holochain.
+    #[command(flatten)]
+    pub connect: ConnectArgs,
 
     /// Provide a password on the command line instead of prompting for it.
     ///
-    /// If this flag is not provided, then an interactive prompt is used to get the password.
+    /// If this flag is not provided, then the password is read from one of the other password
+    /// sources, or an interactive prompt is used to get the password.
     ///
     /// This is not recommended when using as a CLI flag because the password may stay in your
-    /// shell history. Use the interactive prompt instead if possible!
-    #[arg(long)]
+    /// shell history. Use the interactive prompt or another password source instead if possible!
+    #[arg(long, conflicts_with = "password_source")]
     pub password: Option<String>,
 
+    /// Other places to read the password from, for when there is nobody to answer a prompt.
+    #[command(flatten)]
+    pub password_source: PasswordSource,
+
     /// Whether to distribute the key on Holochain after generating it.
     ///
     /// If this flag is not provided, then an interactive prompt is used to confirm.
@@ -80,19 +123,24 @@ pub struct SignArgs {
     #[arg(long, short, default_value_t = String::from("default"))]
     pub name: String,
 
-    /// The admin port for Holochain.
-    #[arg(long, short)]
-    pub port: Option<u16>,
+    /// How to find and connect to Holochain.
+    #[command(flatten)]
+    pub connect: ConnectArgs,
 
     /// Provide a password on the command line instead of prompting for it.
     ///
-    /// If this flag is not provided, then an interactive prompt is used to get the password.
+    /// If this flag is not provided, then the password is read from one of the other password
+    /// sources, or an interactive prompt is used to get the password.
     ///
     /// This is not recommended when using as a CLI flag because the password may stay in your
-    /// shell history. Use the interactive prompt instead if possible!
-    #[arg(long)]
+    /// shell history. Use the interactive prompt or another password source instead if possible!
+    #[arg(long, conflicts_with = "password_source")]
     pub password: Option<String>,
 
+    /// Other places to read the password from, for when there is nobody to answer a prompt.
+    #[command(flatten)]
+    pub password_source: PasswordSource,
+
     /// The config directory for `checked`.
     ///
     /// Defaults to `.config/checked` in your home directory.
@@ -100,6 +148,9 @@ pub struct SignArgs {
     pub config_dir: Option<PathBuf>,
 
     /// The file to sign.
+    ///
+    /// If this is a directory, then a `SHA256SUMS` manifest is created for the files in it and the
+    /// manifest is signed.
     #[arg(long, short)]
     pub file: PathBuf,
 
@@ -109,6 +160,10 @@ pub struct SignArgs {
     #[arg(long, short)]
     pub output: Option<PathBuf>,
 
+    /// Replace an existing `SHA256SUMS` manifest when signing a directory.
+    #[arg(long)]
+    pub overwrite_manifest: bool,
+
     /// Whether to distribute the asset signature
     #[arg(long, short, default_value_t = true)]
     pub distribute: bool,
@@ -139,9 +194,9 @@ pub struct VerifyArgs {
 /// Arguments for the [distribute](crate::distribute::distribute) operation.
 #[derive(clap::Args, Debug)]
 pub struct DistributeArgs {
-    /// The admin port for Holochain
-    #[arg(long, short)]
-    pub port: Option<u16>,
+    /// How to find and connect to Holochain.
+    #[command(flatten)]
+    pub connect: ConnectArgs,
 
     /// Name of the key to distribute.
     ///
@@ -151,13 +206,18 @@ pub struct DistributeArgs {
 
     /// Provide a password on the command line instead of prompting for it.
     ///
-    /// If this flag is not provided, then an interactive prompt is used to get the password.
+    /// If this flag is not provided, then the password is read from one of the other password
+    /// sources, or an interactive prompt is used to get the password.
     ///
     /// This is not recommended when using as a CLI flag because the password may stay in your
-    /// shell history. Use the interactive prompt instead if possible!
-    #[arg(long)]
+    /// shell history. Use the interactive prompt or another password source instead if possible!
+    #[arg(long, conflicts_with = "password_source")]
     pub password: Option<String>,
 
+    /// Other places to read the password from, for when there is nobody to answer a prompt.
+    #[command(flatten)]
+    pub password_source: PasswordSource,
+
     /// The directory to find the verification key in.
     ///
     /// Defaults to `.config/checked` in your home directory.
@@ -173,11 +233,21 @@ pub struct DistributeArgs {
 #[derive(clap::Args, Debug)]
 pub struct FetchArgs {
     /// URL to fetch the asset from.
-    pub url: String,
+    #[arg(required_unless_present = "manifest")]
+    pub url: Option<String>,
 
-    /// The admin port for Holochain
-    #[arg(long, short)]
-    pub port: Option<u16>,
+    /// Fetch every asset listed in a TOML manifest instead of a single URL.
+    ///
+    /// The assets are fetched concurrently and checked against the manifest's policy without
+    /// prompting. A lock file is written next to the manifest recording the digest and the
+    /// signatures that passed for each URL. If an asset's digest no longer matches the lock file,
+    /// the fetch fails.
+    #[arg(long, conflicts_with = "url")]
+    pub manifest: Option<PathBuf>,
+
+    /// How to find and connect to Holochain.
+    #[command(flatten)]
+    pub connect: ConnectArgs,
 
     /// Name of the key to use for signing.
     ///
@@ -200,13 +270,18 @@ pub struct FetchArgs {
 
     /// Provide a password on the command line instead of prompting for it.
     ///
-    /// If this flag is not provided, then an interactive prompt is used to get the password.
+    /// If this flag is not provided, then the password is read from one of the other password
+    /// sources, or an interactive prompt is used to get the password.
     ///
     /// This is not recommended when using as a CLI flag because the password may stay in your
-    /// shell history. Use the interactive prompt instead if possible!
-    #[arg(long)]
+    /// shell history. Use the interactive prompt or another password source instead if possible!
+    #[arg(long, conflicts_with = "password_source")]
     pub password: Option<String>,
 
+    /// Other places to read the password from, for when there is nobody to answer a prompt.
+    #[command(flatten)]
+    pub password_source: PasswordSource,
+
     /// The directory to find the signing key in.
     ///
     /// Defaults to `.config/checked` in your home directory.
@@ -236,4 +311,491 @@ pub struct FetchArgs {
     /// confirmation then you are responsible for checking the signature report some other way.
     #[arg(long)]
     pub approve: Option<bool>,
+
+    /// Only check signatures that your Holochain node already holds, without asking the network.
+    ///
+    /// Use this when your conductor has no peers. The report may be missing recent signatures and
+    /// recently compromised keys, so it is labelled as local-only.
+    #[arg(long, default_value_t = false)]
+    pub offline: bool,
+
+    /// Also check signatures made for other URLs that signers have asserted serve the same asset.
+    ///
+    /// Signatures found this way are labelled with the URL that they were made for in the report.
+    #[arg(long, default_value_t = false)]
+    pub include_aliases: bool,
+
+    /// Also check a MiniSign signature that has been published next to the asset.
+    ///
+    /// The signature is checked with the keys in your key collections, the keys of the publisher
+    /// and keys verified for the domain. It is reported separately from the signatures on Holochain.
+    #[arg(long, default_value_t = false)]
+    pub check_sidecar: bool,
+
+    /// Where to find the signature published next to the asset, see `--check-sidecar`.
+    ///
+    /// `{url}` is replaced with the URL of the asset.
+    #[arg(long, default_value_t = String::from(crate::sidecar::DEFAULT_SIDECAR_PATTERN))]
+    pub sidecar_pattern: String,
+
+    /// The URL of a checksums manifest, such as `SHA256SUMS`, that lists the asset.
+    ///
+    /// The manifest's signature is found using `--sidecar-pattern` and is checked with the
+    /// same keys as a signature published next to the asset. The asset's SHA-256 digest must then
+    /// be listed in the manifest.
+    #[arg(long)]
+    pub checksums: Option<String>,
+}
+
+/// Arguments for the [identify](crate::identify::identify) operation.
+#[derive(clap::Args, Debug)]
+pub struct IdentifyArgs {
+    /// The file to find signatures for.
+    pub file: PathBuf,
+
+    /// How to find and connect to Holochain.
+    #[command(flatten)]
+    pub connect: ConnectArgs,
+
+    /// The config directory for `checked`.
+    ///
+    /// Defaults to `.config/checked` in your home directory.
+    #[arg(long, short)]
+    pub config_dir: Option<PathBuf>,
+
+    /// Override the app id that `checked` has been installed with on Holochain.
+    #[arg(long)]
+    pub app_id: Option<String>,
+}
+
+/// Arguments for the [claim_publisher](crate::claim_publisher::claim_publisher) operation.
+#[derive(clap::Args, Debug)]
+pub struct ClaimPublisherArgs {
+    /// The URL prefix that you publish assets under, such as `https://downloads.example.org/tool/`.
+    ///
+    /// Must end with `/` and have a path below the origin. Use a domain claim for the whole domain.
+    pub url_prefix: String,
+
+    /// How to find and connect to Holochain.
+    #[command(flatten)]
+    pub connect: ConnectArgs,
+
+    /// Name of the key that you sign published assets with. It must already be distributed.
+    ///
+    /// Defaults to `default`.
+    #[arg(long, short, default_value_t = String::from("default"))]
+    pub name: String,
+
+    /// The directory to find the verification key in.
+    ///
+    /// Defaults to `.config/checked` in your home directory.
+    #[arg(long, short)]
+    pub config_dir: Option<PathBuf>,
+
+    /// Override the app id that `checked` has been installed with on Holochain.
+    #[arg(long)]
+    pub app_id: Option<String>,
+}
+
+/// Arguments for the [claim_domain](crate::domain::claim_domain) operation.
+#[derive(clap::Args, Debug)]
+pub struct ClaimDomainArgs {
+    /// The domain that you control, such as `downloads.example.org`.
+    pub domain: String,
+
+    /// How to find and connect to Holochain.
+    #[command(flatten)]
+    pub connect: ConnectArgs,
+
+    /// Name of the key to claim for the domain. It must already be distributed.
+    ///
+    /// Defaults to `default`.
+    #[arg(long, short, default_value_t = String::from("default"))]
+    pub name: String,
+
+    /// The directory to find the verification key in.
+    ///
+    /// Defaults to `.config/checked` in your home directory.
+    #[arg(long, short)]
+    pub config_dir: Option<PathBuf>,
+
+    /// Override the app id that `checked` has been installed with on Holochain.
+    #[arg(long)]
+    pub app_id: Option<String>,
+}
+
+/// Arguments for the [verify_domain](crate::domain::verify_domain) operation.
+#[derive(clap::Args, Debug)]
+pub struct VerifyDomainArgs {
+    /// The domain to check claims for, such as `downloads.example.org`.
+    pub domain: String,
+
+    /// How to find and connect to Holochain.
+    #[command(flatten)]
+    pub connect: ConnectArgs,
+
+    /// The config directory for `checked`, where the `.well-known` file is cached.
+    ///
+    /// Defaults to `.config/checked` in your home directory.
+    #[arg(long, short)]
+    pub config_dir: Option<PathBuf>,
+
+    /// Override the app id that `checked` has been installed with on Holochain.
+    #[arg(long)]
+    pub app_id: Option<String>,
+
+    /// Fetch the `.well-known` file again, even if a recent copy has been cached.
+    #[arg(long, default_value_t = false)]
+    pub refresh: bool,
+
+    /// Fetch the `.well-known` file from this URL instead of from the domain. For testing only.
+    #[arg(skip)]
+    pub well_known_url: Option<url::Url>,
+}
+
+/// Arguments for the [sign_release](crate::sign_release::sign_release) operation.
+#[derive(clap::Args, Debug)]
+pub struct SignReleaseArgs {
+    /// The directory containing the files of the release.
+    ///
+    /// Only files directly in the directory are signed. Existing signatures and checksums
+    /// manifests are skipped.
+    #[arg(long, short)]
+    pub dir: PathBuf,
+
+    /// The URL that the files of the release are published under. Each file is expected at this
+    /// URL followed by its file name.
+    #[arg(long, short)]
+    pub base_url: String,
+
+    /// Only sign files whose name matches this pattern. `*` matches any characters and `?` matches
+    /// a single character.
+    #[arg(long)]
+    pub include: Option<String>,
+
+    /// Name of the key to use for signing.
+    ///
+    /// Defaults to `default`.
+    #[arg(long, short, default_value_t = String::from("default"))]
+    pub name: String,
+
+    /// How to find and connect to Holochain.
+    #[command(flatten)]
+    pub connect: ConnectArgs,
+
+    /// Provide a password on the command line instead of prompting for it.
+    ///
+    /// If this flag is not provided, then the password is read from one of the other password
+    /// sources, or an interactive prompt is used to get the password.
+    ///
+    /// This is not recommended when using as a CLI flag because the password may stay in your
+    /// shell history. Use the interactive prompt or another password source instead if possible!
+    #[arg(long, conflicts_with = "password_source")]
+    pub password: Option<String>,
+
+    /// Other places to read the password from, for when there is nobody to answer a prompt.
+    #[command(flatten)]
+    pub password_source: PasswordSource,
+
+    /// The config directory for `checked`.
+    ///
+    /// Defaults to `.config/checked` in your home directory.
+    #[arg(long, short)]
+    pub config_dir: Option<PathBuf>,
+
+    /// Whether to distribute the asset signatures. Use `--distribute false` to only sign the files.
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    pub distribute: bool,
+
+    /// Override the app id that `checked` has been installed with on Holochain.
+    #[arg(long)]
+    pub app_id: Option<String>,
+}
+
+/// Arguments for the [proxy](crate::proxy::proxy) operation.
+#[derive(clap::Args, Debug)]
+pub struct ProxyArgs {
+    /// The address to listen for proxy connections on.
+    #[arg(long, short, default_value = "127.0.0.1:8089")]
+    pub listen: std::net::SocketAddr,
+
+    /// Allow listening on an address that is not a loopback address.
+    ///
+    /// Anyone who can reach the proxy can use it to fetch through this machine.
+    #[arg(long, default_value_t = false)]
+    pub allow_remote: bool,
+
+    /// How to find and connect to Holochain.
+    #[command(flatten)]
+    pub connect: ConnectArgs,
+
+    /// The config directory for `checked`.
+    ///
+    /// Defaults to `.config/checked` in your home directory.
+    #[arg(long, short)]
+    pub config_dir: Option<PathBuf>,
+
+    /// Where to keep assets that have been verified, so that they are not checked again.
+    ///
+    /// Defaults to `proxy_cache` in the config directory.
+    #[arg(long)]
+    pub cache_dir: Option<PathBuf>,
+
+    /// How long, in seconds, an accepted asset is served from the cache before its signatures are
+    /// checked again.
+    #[arg(long, default_value_t = 3600)]
+    pub cache_ttl: u64,
+
+    /// Only use signatures that are already held by the local Holochain node.
+    #[arg(long, default_value_t = false)]
+    pub offline: bool,
+
+    /// Also check signatures that were made for aliases of the requested URL.
+    #[arg(long, default_value_t = false)]
+    pub include_aliases: bool,
+
+    /// The rules that an asset's signatures must pass before it is served.
+    #[command(flatten)]
+    pub policy: crate::fetch_manifest::FetchPolicy,
+
+    /// Override the app id that `checked` has been installed with on Holochain.
+    #[arg(long)]
+    pub app_id: Option<String>,
+}
+
+/// Arguments for the [daemon](crate::daemon::daemon) operation.
+#[derive(clap::Args, Debug)]
+pub struct DaemonArgs {
+    /// The address to serve the API on.
+    ///
+    /// Ignored if `--socket` is provided.
+    #[arg(long, short, default_value = "127.0.0.1:8090")]
+    pub listen: std::net::SocketAddr,
+
+    /// Serve the API on a Unix socket at this path instead of on a TCP address.
+    ///
+    /// The socket is only accessible to the current user.
+    #[cfg(unix)]
+    #[arg(long)]
+    pub socket: Option<PathBuf>,
+
+    /// How to find and connect to Holochain.
+    #[command(flatten)]
+    pub connect: ConnectArgs,
+
+    /// The config directory for `checked`.
+    ///
+    /// Defaults to `.config/checked` in your home directory.
+    #[arg(long, short)]
+    pub config_dir: Option<PathBuf>,
+
+    /// How long, in seconds, to keep a signing key unlocked after its password was last provided.
+    #[arg(long, default_value_t = 900)]
+    pub key_timeout: u64,
+
+    /// The directory that fetched assets are saved in. The `output` of a fetch request is relative
+    /// to it and can't leave it.
+    ///
+    /// Defaults to the directory that the daemon is started in.
+    #[arg(long)]
+    pub output_dir: Option<PathBuf>,
+
+    /// The rules that an asset's signatures must pass before it is accepted.
+    #[command(flatten)]
+    pub policy: crate::fetch_manifest::FetchPolicy,
+
+    /// Override the app id that `checked` has been installed with on Holochain.
+    #[arg(long)]
+    pub app_id: Option<String>,
+}
+
+/// Arguments for the [agent](crate::agent::agent) operation.
+#[cfg(unix)]
+#[derive(clap::Args, Debug)]
+pub struct AgentArgs {
+    /// Name of the key to hold.
+    ///
+    /// Defaults to `default`.
+    #[arg(long, short, default_value_t = String::from("default"))]
+    pub name: String,
+
+    /// Provide a password on the command line instead of prompting for it.
+    ///
+    /// If this flag is not provided, then the password is read from one of the other password
+    /// sources, or an interactive prompt is used to get the password.
+    ///
+    /// This is not recommended when using as a CLI flag because the password may stay in your
+    /// shell history. Use the interactive prompt or another password source instead if possible!
+    #[arg(long, conflicts_with = "password_source")]
+    pub password: Option<String>,
+
+    /// Other places to read the password from, for when there is nobody to answer a prompt.
+    #[command(flatten)]
+    pub password_source: PasswordSource,
+
+    /// The config directory for `checked`.
+    ///
+    /// Defaults to `.config/checked` in your home directory.
+    #[arg(long, short)]
+    pub config_dir: Option<PathBuf>,
+
+    /// Where to create the agent's socket.
+    ///
+    /// Defaults to the `CHECKED_AGENT_SOCK` environment variable if it is set, otherwise
+    /// `agent.sock` in the config directory. Other commands look for the agent in the same place.
+    #[arg(long)]
+    pub socket: Option<PathBuf>,
+
+    /// How long, in seconds, to hold the key before forgetting it and stopping.
+    #[arg(long, default_value_t = 3600)]
+    pub ttl: u64,
+
+    /// Ask for confirmation in the agent's terminal before each signature is made.
+    #[arg(long, default_value_t = false)]
+    pub confirm: bool,
+}
+
+/// Places to read a password from instead of prompting for it.
+///
+/// These are checked after `--password`, in the order `--password-fd`, `--password-file`,
+/// `--password-command`, then the [PASSWORD_ENV] environment variable. Only the first line that is
+/// read is used as the password. Only one of them can be provided.
+#[derive(clap::Args, Debug, Clone, Default)]
+#[group(id = "password_source", multiple = false)]
+pub struct PasswordSource {
+    /// Read the password from this open file descriptor, such as one set up by your CI.
+    #[cfg(unix)]
+    #[arg(long)]
+    pub password_fd: Option<u32>,
+
+    /// Read the password from this file.
+    #[arg(long)]
+    pub password_file: Option<PathBuf>,
+
+    /// Run this command with the shell and read the password from its output, such as
+    /// `pass show checked`.
+    #[arg(long)]
+    pub password_command: Option<String>,
+}
+
+/// The environment variable that a password is read from if no other password source is provided.
+pub const PASSWORD_ENV: &str = "CHECKED_PASSWORD";
+
+/// The environment variable that the passphrase for saved Holochain credentials is read from,
+/// instead of prompting for it.
+pub const CREDENTIALS_PASSWORD_ENV: &str = "CHECKED_CREDENTIALS_PASSWORD";
+
+/// Set this environment variable to `1` to authorize write access to Holochain without a terminal
+/// to confirm it on, such as in scripts that sign or publish.
+pub const ALLOW_WRITE_ENV: &str = "CHECKED_ALLOW_WRITE";
+
+/// Arguments for the [config](crate::config::config) operation.
+#[derive(clap::Args, Debug)]
+pub struct ConfigArgs {
+    /// What to do with the config file.
+    #[command(subcommand)]
+    pub command: ConfigCommand,
+}
+
+/// Operations on the config file. Keys are read from and written to the selected profile, except
+/// for `default_profile` which applies to the whole file.
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Show the value of a key
+    Get {
+        /// The key to show.
+        key: String,
+    },
+
+    /// Set the value of a key
+    Set {
+        /// The key to set.
+        key: String,
+        /// The value to set the key to.
+        value: String,
+    },
+
+    /// Remove a key, so that the usual default is used
+    Unset {
+        /// The key to remove.
+        key: String,
+    },
+
+    /// Show every profile and its values
+    List,
+}
+
+/// Options for finding and connecting to Holochain's admin interface.
+#[derive(clap::Args, Debug, Clone, Copy)]
+pub struct ConnectArgs {
+    /// The admin port for Holochain.
+    ///
+    /// If not provided, then the tool will scan for a Holochain process and open ports on that
+    /// process. The user will be prompted for the process and port to use where multiple of either
+    /// are found.
+    #[arg(long, short)]
+    pub port: Option<u16>,
+
+    /// How long to wait, in seconds, for each connection to Holochain before giving up.
+    #[arg(long, default_value_t = 10)]
+    pub connect_timeout: u64,
+
+    /// Never prompt to pick a Holochain process or port. If `--port` is not provided, fail with
+    /// the admin ports that were found instead.
+    #[arg(long, default_value_t = false)]
+    pub no_discover: bool,
+}
+
+impl Default for ConnectArgs {
+    fn default() -> Self {
+        ConnectArgs {
+            port: None,
+            connect_timeout: 10,
+            no_discover: false,
+        }
+    }
+}
+
+impl ConnectArgs {
+    /// The timeout for each connection to Holochain.
+    pub fn timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.connect_timeout)
+    }
+}
+
+/// Arguments for the [credentials](crate::credentials::credentials) operation.
+#[derive(clap::Args, Debug)]
+pub struct CredentialsArgs {
+    /// The config directory for `checked`.
+    ///
+    /// Defaults to `.config/checked` in your home directory.
+    #[arg(long, short)]
+    pub config_dir: Option<PathBuf>,
+
+    /// What to do with the saved credentials.
+    #[command(subcommand)]
+    pub command: CredentialsCommand,
+}
+
+/// Operations on the saved Holochain credentials. Credentials are saved for each app id and agent,
+/// so each conductor and each install of the app has its own.
+#[derive(Subcommand, Debug)]
+pub enum CredentialsCommand {
+    /// Show the app installs that credentials are saved for
+    List,
+
+    /// Remove saved credentials, so that new ones are authorized when they are next needed
+    Forget {
+        /// Forget the credentials for this app id.
+        #[arg(long)]
+        app_id: Option<String>,
+
+        /// Forget the credentials for this agent, as shown by `checked credentials list`.
+        #[arg(long)]
+        agent: Option<String>,
+
+        /// Forget all saved credentials.
+        #[arg(long, conflicts_with_all = ["app_id", "agent"])]
+        all: bool,
+    },
 }
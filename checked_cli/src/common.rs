@@ -1,6 +1,7 @@
 use anyhow::Context;
+use sha2::{Digest, Sha256};
 use std::fs::File;
-use std::io::BufWriter;
+use std::io::{BufReader, BufWriter};
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -50,6 +51,46 @@ pub(crate) fn get_verification_key_path<P: AsRef<Path>>(store_dir: P, name: &str
     store_dir.as_ref().join(format!("{}.pub", name))
 }
 
+/// Listen on a Unix socket at `path` that only the current user can connect to.
+///
+/// The socket is created in a private directory, which only the current user can access, and is
+/// only moved to `path` once its permissions have been restricted. That way there is no time at
+/// which another user could connect to it.
+///
+/// A socket left behind by a previous run is replaced, but any other file at `path` is an error.
+#[cfg(unix)]
+pub(crate) fn bind_private_socket(path: &Path) -> anyhow::Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            anyhow::bail!("{:?} already exists and is not a socket", path);
+        }
+        std::fs::remove_file(path)?;
+    }
+
+    // Next to `path` so that the socket can be renamed into place. New temporary directories are
+    // only accessible to the current user.
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let private_dir = tempfile::Builder::new()
+        .prefix(".checked-")
+        .tempdir_in(parent)
+        .with_context(|| format!("Could not create a private directory in {:?}", parent))?;
+    std::fs::set_permissions(private_dir.path(), std::fs::Permissions::from_mode(0o700))?;
+
+    let private_path = private_dir.path().join("socket");
+    let listener = tokio::net::UnixListener::bind(&private_path)
+        .with_context(|| format!("Could not listen on {:?}", path))?;
+    std::fs::set_permissions(&private_path, std::fs::Permissions::from_mode(0o600))?;
+    std::fs::rename(&private_path, path)
+        .with_context(|| format!("Could not move the socket to {:?}", path))?;
+
+    Ok(listener)
+}
+
 pub(crate) fn unix_timestamp() -> u64 {
     let start = SystemTime::now();
     let since_the_epoch = start
@@ -58,6 +99,19 @@ pub(crate) fn unix_timestamp() -> u64 {
     since_the_epoch.as_secs()
 }
 
+/// Compute the SHA-256 digest of the file at `path`, which is used to index signatures by content.
+pub(crate) fn sha256_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Vec<u8>> {
+    let mut reader = BufReader::new(
+        File::open(path.as_ref())
+            .with_context(|| format!("Could not open - {:?}", path.as_ref()))?,
+    );
+
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut reader, &mut hasher)?;
+
+    Ok(hasher.finalize().to_vec())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -71,4 +125,20 @@ mod tests {
         r.write_all("test".as_bytes()).unwrap();
         r.flush().unwrap();
     }
+
+    #[test]
+    fn sha256_of_a_file() {
+        let f = tempfile::tempdir().unwrap();
+        let path = f.path().join("test.txt");
+
+        let mut w = open_file(&path).unwrap();
+        w.write_all("test".as_bytes()).unwrap();
+        w.flush().unwrap();
+        drop(w);
+
+        assert_eq!(
+            "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08",
+            hex::encode(sha256_file(&path).unwrap())
+        );
+    }
 }
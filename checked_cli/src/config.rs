@@ -0,0 +1,510 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use clap::{CommandFactory, FromArgMatches};
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{Cli, ConfigArgs, ConfigCommand};
+use crate::common::get_store_dir;
+
+/// The environment variable that selects a profile when `--profile` is not provided.
+pub const PROFILE_ENV: &str = "CHECKED_PROFILE";
+
+/// The name of the config file, which is kept in the default config directory, so the config is
+/// read from `~/.checked/config.toml`.
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// The profile that `checked config` edits when no profile is selected.
+const DEFAULT_PROFILE_NAME: &str = "default";
+
+/// The contents of the config file.
+///
+/// ```toml
+/// default_profile = "work"
+///
+/// [profiles.work]
+/// port = 45678
+/// app_id = "checked-work"
+/// name = "work"
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    /// The profile to use when none is selected with `--profile` or [PROFILE_ENV].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_profile: Option<String>,
+    /// Profiles by name.
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// Default values for command line arguments. Each value is only used by the commands that accept
+/// it, and only when it isn't provided on the command line.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// The admin port for Holochain.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// The app id that `checked` has been installed with on Holochain.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_id: Option<String>,
+    /// The name of the key to use.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The config directory for `checked`. The config file itself is always read from the default
+    /// config directory, `~/.checked/config.toml`, so that it can't point somewhere else.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_dir: Option<PathBuf>,
+    /// How long to wait, in seconds, for each connection to Holochain.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect_timeout: Option<u64>,
+    /// Never prompt to pick a Holochain process or port.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_discover: Option<bool>,
+    /// The answer to `fetch` asking whether to continue without signatures.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow_no_signatures: Option<bool>,
+    /// The answer to `fetch` asking whether to sign the asset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sign: Option<bool>,
+    /// The answer to `fetch` asking whether to approve the asset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approve: Option<bool>,
+}
+
+/// The keys of a [Profile], which match the names of the arguments that they provide defaults for.
+const PROFILE_KEYS: &[&str] = &[
+    "port",
+    "app_id",
+    "name",
+    "config_dir",
+    "connect_timeout",
+    "no_discover",
+    "allow_no_signatures",
+    "sign",
+    "approve",
+];
+
+impl Profile {
+    /// The values that are set, by key.
+    fn values(&self) -> Vec<(&'static str, String)> {
+        PROFILE_KEYS
+            .iter()
+            .filter_map(|key| self.get(key).ok().flatten().map(|value| (*key, value)))
+            .collect()
+    }
+
+    fn get(&self, key: &str) -> anyhow::Result<Option<String>> {
+        Ok(match key {
+            "port" => self.port.map(|v| v.to_string()),
+            "app_id" => self.app_id.clone(),
+            "name" => self.name.clone(),
+            "config_dir" => self.config_dir.as_ref().map(|v| v.display().to_string()),
+            "connect_timeout" => self.connect_timeout.map(|v| v.to_string()),
+            "no_discover" => self.no_discover.map(|v| v.to_string()),
+            "allow_no_signatures" => self.allow_no_signatures.map(|v| v.to_string()),
+            "sign" => self.sign.map(|v| v.to_string()),
+            "approve" => self.approve.map(|v| v.to_string()),
+            _ => return Err(unknown_key(key)),
+        })
+    }
+
+    /// Set `key` to `value`, or remove it if `value` is `None`.
+    fn set(&mut self, key: &str, value: Option<&str>) -> anyhow::Result<()> {
+        let parse_bool = |v: &str| {
+            v.parse::<bool>()
+                .with_context(|| format!("Invalid value for {}, expected true or false", key))
+        };
+
+        match key {
+            "port" => {
+                self.port = value
+                    .map(|v| v.parse::<u16>().context("Invalid value for port"))
+                    .transpose()?
+            }
+            "app_id" => self.app_id = value.map(str::to_string),
+            "name" => self.name = value.map(str::to_string),
+            "config_dir" => self.config_dir = value.map(PathBuf::from),
+            "connect_timeout" => {
+                self.connect_timeout = value
+                    .map(|v| {
+                        v.parse::<u64>()
+                            .context("Invalid value for connect_timeout")
+                    })
+                    .transpose()?
+            }
+            "no_discover" => self.no_discover = value.map(parse_bool).transpose()?,
+            "allow_no_signatures" => {
+                self.allow_no_signatures = value.map(parse_bool).transpose()?
+            }
+            "sign" => self.sign = value.map(parse_bool).transpose()?,
+            "approve" => self.approve = value.map(parse_bool).transpose()?,
+            _ => return Err(unknown_key(key)),
+        }
+
+        Ok(())
+    }
+}
+
+fn unknown_key(key: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "Unknown key '{}', expected default_profile or one of: {}",
+        key,
+        PROFILE_KEYS.join(", ")
+    )
+}
+
+/// Parse the command line, taking the default values of arguments that weren't provided from the
+/// selected profile.
+///
+/// This is the common layer that profiles are applied in. Rather than every command reading the
+/// config, each [Profile] key is injected as the default of the arguments with the same id, in
+/// whichever command declares them. That covers the connection arguments in
+/// [ConnectArgs](crate::cli::ConnectArgs) as well as `config_dir`, `app_id` and `name`, which stay
+/// on each command's own arguments because not every command that connects to Holochain takes
+/// them. The admin port is only resolved in one place,
+/// [ConnectArgs::admin_port](crate::cli::ConnectArgs::admin_port).
+///
+/// Commands get their values from the profile through their usual arguments, so an argument that
+/// is provided on the command line always wins over the profile.
+///
+/// An invalid config is reported and ignored rather than failing, so that `config` can still be
+/// used to fix it.
+pub fn parse_cli() -> anyhow::Result<Cli> {
+    let config = load_config_or_default(&config_path()?);
+
+    parse_cli_from(
+        std::env::args_os().collect(),
+        &config,
+        std::env::var(PROFILE_ENV).ok(),
+    )
+}
+
+fn parse_cli_from(
+    args: Vec<OsString>,
+    config: &Config,
+    env_profile: Option<String>,
+) -> anyhow::Result<Cli> {
+    // Find the profile first, ignoring errors so that they are reported with the profile's defaults
+    // applied, as is help.
+    let matches = Cli::command()
+        .ignore_errors(true)
+        .get_matches_from(args.clone());
+    let flag_profile = matches.get_one::<String>("profile").cloned();
+
+    // Profiles are edited by `config`, so it mustn't depend on the profile existing.
+    let profile = match select_profile(flag_profile, env_profile, config) {
+        Some(profile_name) if matches.subcommand_name() != Some("config") => {
+            Some(config.profiles.get(&profile_name).ok_or_else(|| {
+                anyhow::anyhow!("Profile '{}' not found in the config", profile_name)
+            })?)
+        }
+        _ => None,
+    };
+
+    let mut command = Cli::command();
+    if let Some(profile) = profile {
+        let values = profile.values();
+        for subcommand in command.get_subcommands_mut() {
+            *subcommand = std::mem::take(subcommand).mut_args(|arg| {
+                match values.iter().find(|(key, _)| *arg.get_id() == *key) {
+                    Some((_, value)) => arg.default_value(value.clone()),
+                    None => arg,
+                }
+            });
+        }
+    }
+
+    Ok(Cli::from_arg_matches(&command.get_matches_from(args))?)
+}
+
+/// Choose the profile from `--profile`, then [PROFILE_ENV], then the config's default profile.
+fn select_profile(
+    flag: Option<String>,
+    env_profile: Option<String>,
+    config: &Config,
+) -> Option<String> {
+    flag.or(env_profile.filter(|p| !p.is_empty()))
+        .or_else(|| config.default_profile.clone())
+}
+
+/// View and edit the profiles in the config file.
+///
+/// `profile` is the profile selected with `--profile`. Without one, [PROFILE_ENV] and then the
+/// config's default profile are used, falling back to a profile called `default`.
+pub fn config(config_args: ConfigArgs, profile: Option<String>) -> anyhow::Result<()> {
+    let path = config_path()?;
+    let mut config = match load_config(&path) {
+        Ok(config) => config,
+        Err(e)
+            if matches!(
+                config_args.command,
+                ConfigCommand::Set { .. } | ConfigCommand::Unset { .. }
+            ) =>
+        {
+            set_aside_invalid_config(&path, e)?
+        }
+        Err(e) => {
+            return Err(
+                e.context("Fix or remove the config, or use `config set` to start a new one")
+            )
+        }
+    };
+
+    let profile_name = select_profile(profile, std::env::var(PROFILE_ENV).ok(), &config)
+        .unwrap_or_else(|| DEFAULT_PROFILE_NAME.to_string());
+
+    match config_args.command {
+        ConfigCommand::Get { key } => {
+            let value = if key == "default_profile" {
+                config.default_profile.clone()
+            } else {
+                config
+                    .profiles
+                    .get(&profile_name)
+                    .cloned()
+                    .unwrap_or_default()
+                    .get(&key)?
+            };
+
+            match value {
+                Some(value) => println!("{}", value),
+                None => anyhow::bail!("'{}' is not set in profile '{}'", key, profile_name),
+            }
+        }
+        ConfigCommand::Set { key, value } => {
+            update_config(&mut config, &profile_name, &key, Some(&value))?;
+            save_config(&path, &config)?;
+        }
+        ConfigCommand::Unset { key } => {
+            update_config(&mut config, &profile_name, &key, None)?;
+            save_config(&path, &config)?;
+        }
+        ConfigCommand::List => {
+            if let Some(default_profile) = &config.default_profile {
+                println!("default_profile = {}", default_profile);
+            }
+            for (name, profile) in &config.profiles {
+                let marker = if *name == profile_name {
+                    " (selected)"
+                } else {
+                    ""
+                };
+                println!("\n[{}]{}", name, marker);
+                for (key, value) in profile.values() {
+                    println!("{} = {}", key, value);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn update_config(
+    config: &mut Config,
+    profile_name: &str,
+    key: &str,
+    value: Option<&str>,
+) -> anyhow::Result<()> {
+    if key == "default_profile" {
+        config.default_profile = value.map(str::to_string);
+        return Ok(());
+    }
+
+    let profile = config.profiles.entry(profile_name.to_string()).or_default();
+    profile.set(key, value)?;
+
+    if *profile == Profile::default() {
+        config.profiles.remove(profile_name);
+    }
+
+    Ok(())
+}
+
+/// The config file is always `~/.checked/config.toml`, ignoring `--config-dir` and the profile's
+/// `config_dir` because those are only known once the config has been read.
+fn config_path() -> anyhow::Result<PathBuf> {
+    Ok(get_store_dir(None)?.join(CONFIG_FILE_NAME))
+}
+
+fn load_config(path: &Path) -> anyhow::Result<Config> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => {
+            toml::from_str(&content).with_context(|| format!("Invalid config {:?}", path))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(e) => Err(e).with_context(|| format!("Could not read config {:?}", path)),
+    }
+}
+
+fn load_config_or_default(path: &Path) -> Config {
+    match load_config(path) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Warning: {:#}, ignoring it", e);
+            Config::default()
+        }
+    }
+}
+
+/// Move an invalid config out of the way so that a new one can be written in its place.
+///
+/// The invalid config is kept next to the new one so that its values can be copied over by hand.
+fn set_aside_invalid_config(path: &Path, error: anyhow::Error) -> anyhow::Result<Config> {
+    let invalid_path = path.with_extension("toml.invalid");
+    std::fs::rename(path, &invalid_path).with_context(|| {
+        format!(
+            "{:#}, and it could not be moved to {:?}",
+            error, invalid_path
+        )
+    })?;
+    eprintln!(
+        "Warning: {:#}, moved it to {:?} and starting a new config",
+        error, invalid_path
+    );
+
+    Ok(Config::default())
+}
+
+fn save_config(path: &Path, config: &Config) -> anyhow::Result<()> {
+    std::fs::write(path, toml::to_string_pretty(config)?)
+        .with_context(|| format!("Could not write config {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Commands;
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    fn work_config() -> Config {
+        let mut config = Config::default();
+        update_config(&mut config, "work", "port", Some("45678")).unwrap();
+        update_config(&mut config, "work", "name", Some("work")).unwrap();
+        update_config(&mut config, "work", "allow_no_signatures", Some("true")).unwrap();
+        update_config(&mut config, "work", "no_discover", Some("true")).unwrap();
+        config
+    }
+
+    #[test]
+    fn profile_provides_defaults() {
+        let cli = parse_cli_from(
+            args(&[
+                "checked",
+                "--profile",
+                "work",
+                "fetch",
+                "https://example.com/a",
+            ]),
+            &work_config(),
+            None,
+        )
+        .unwrap();
+
+        let Commands::Fetch(fetch_args) = cli.command else {
+            panic!("Expected fetch");
+        };
+        assert_eq!(Some(45678), fetch_args.connect.port);
+        assert_eq!("work", fetch_args.name);
+        assert_eq!(Some(true), fetch_args.allow_no_signatures);
+        assert_eq!(None, fetch_args.app_id);
+        assert!(fetch_args.connect.no_discover);
+    }
+
+    #[test]
+    fn command_line_wins_over_profile() {
+        let cli = parse_cli_from(
+            args(&[
+                "checked",
+                "fetch",
+                "https://example.com/a",
+                "--port",
+                "1234",
+            ]),
+            &work_config(),
+            Some("work".to_string()),
+        )
+        .unwrap();
+
+        let Commands::Fetch(fetch_args) = cli.command else {
+            panic!("Expected fetch");
+        };
+        assert_eq!(Some(1234), fetch_args.connect.port);
+        assert_eq!("work", fetch_args.name);
+    }
+
+    #[test]
+    fn missing_profile() {
+        let config = work_config();
+
+        assert!(parse_cli_from(
+            args(&[
+                "checked",
+                "--profile",
+                "home",
+                "fetch",
+                "https://example.com/a"
+            ]),
+            &config,
+            None,
+        )
+        .is_err());
+
+        // Without a selected profile, the usual defaults are used.
+        let cli = parse_cli_from(
+            args(&["checked", "fetch", "https://example.com/a"]),
+            &config,
+            None,
+        )
+        .unwrap();
+        let Commands::Fetch(fetch_args) = cli.command else {
+            panic!("Expected fetch");
+        };
+        assert_eq!(None, fetch_args.connect.port);
+        assert_eq!("default", fetch_args.name);
+    }
+
+    #[test]
+    fn set_and_unset_values() {
+        let mut config = work_config();
+        update_config(&mut config, "work", "default_profile", Some("work")).unwrap();
+        assert_eq!(Some("work".to_string()), config.default_profile);
+
+        assert!(update_config(&mut config, "work", "port", Some("not-a-port")).is_err());
+        assert!(update_config(&mut config, "work", "sign", Some("maybe")).is_err());
+        assert!(update_config(&mut config, "work", "unknown", Some("1")).is_err());
+
+        let parsed: Config = toml::from_str(&toml::to_string_pretty(&config).unwrap()).unwrap();
+        assert_eq!(config, parsed);
+
+        for key in ["port", "name", "allow_no_signatures", "no_discover"] {
+            update_config(&mut config, "work", key, None).unwrap();
+        }
+        assert!(config.profiles.is_empty());
+    }
+
+    #[test]
+    fn invalid_config_is_set_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE_NAME);
+        std::fs::write(&path, "[profiles.work]\nport = \"not-a-port\"\n").unwrap();
+
+        assert!(load_config(&path).is_err());
+        assert_eq!(Config::default(), load_config_or_default(&path));
+
+        let error = load_config(&path).unwrap_err();
+        assert_eq!(
+            Config::default(),
+            set_aside_invalid_config(&path, error).unwrap()
+        );
+        assert!(!path.exists());
+        assert!(path.with_extension("toml.invalid").exists());
+        assert_eq!(Config::default(), load_config(&path).unwrap());
+    }
+}
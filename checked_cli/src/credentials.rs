@@ -0,0 +1,967 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+use holochain_client::SigningCredentials;
+use holochain_types::prelude::{AgentPubKey, CapSecret, CellId};
+use serde::{Deserialize, Serialize};
+use sodoken::argon2::{
+    blocking_argon2id, ARGON2_ID_MEMLIMIT_INTERACTIVE, ARGON2_ID_MEMLIMIT_SENSITIVE,
+    ARGON2_ID_OPSLIMIT_INTERACTIVE, ARGON2_ID_OPSLIMIT_SENSITIVE, ARGON2_ID_SALTBYTES,
+};
+use sodoken::secretbox::{
+    xsalsa_easy, xsalsa_open_easy, XSALSA_KEYBYTES, XSALSA_MACBYTES, XSALSA_NONCEBYTES,
+};
+use zeroize::Zeroizing;
+
+use crate::cli::{CredentialsArgs, CredentialsCommand};
+use crate::common::get_store_dir;
+use crate::hc_client::CredentialScope;
+use crate::interactive::{credentials_password_available, get_credentials_password};
+
+/// The directory in the store that credentials are saved in, with a directory for each app install.
+const CREDENTIALS_DIR_NAME: &str = "credentials";
+
+/// Describes the app install that the credentials in a directory are for. It is not encrypted, so
+/// that credentials can be listed without the passphrase.
+const APP_INFO_FILE_NAME: &str = "app.json";
+
+/// Credentials used to be saved to this file, authorized to call every zome function.
+const LEGACY_CREDENTIALS_FILE_NAME: &str = "credentials.json";
+
+const SCOPES: [CredentialScope; 2] = [CredentialScope::Read, CredentialScope::Write];
+
+/// The app install that credentials are saved for.
+///
+/// Two conductors, or two installs of the app on one conductor, have different agents for their
+/// `checked` cell. So keeping credentials by app id and agent stops them replacing each other.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CredentialsKey {
+    pub(crate) installed_app_id: String,
+    /// The `checked` cell of the app.
+    pub(crate) cell_id: CellId,
+}
+
+impl CredentialsKey {
+    /// The directory that credentials for this app install are saved in.
+    ///
+    /// Characters other than ASCII letters, digits and `-` are escaped in the app id as their hex
+    /// code between underscores, so that different app ids never share a directory.
+    fn dir(&self, store_dir: &Path) -> PathBuf {
+        let mut app_id = String::new();
+        for c in self.installed_app_id.chars() {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                app_id.push(c);
+            } else {
+                app_id.push_str(&format!("_{:x}_", u32::from(c)));
+            }
+        }
+
+        store_dir.join(CREDENTIALS_DIR_NAME).join(format!(
+            "{}-{}",
+            app_id,
+            self.cell_id.agent_pubkey()
+        ))
+    }
+}
+
+fn scope_name(scope: CredentialScope) -> &'static str {
+    match scope {
+        CredentialScope::Read => "read",
+        CredentialScope::Write => "write",
+    }
+}
+
+fn scope_file(dir: &Path, scope: CredentialScope) -> PathBuf {
+    dir.join(format!("{}.json", scope_name(scope)))
+}
+
+/// The passphrase that saved credentials are encrypted with. Kept out of debug output.
+///
+/// The passphrase is only asked for when an encrypted file has to be read or written, and then kept
+/// so that it is asked for once. Deriving a key from the passphrase is slow, so the keys are kept
+/// too, and new files are encrypted with a key that has already been derived where possible.
+#[derive(Clone)]
+pub(crate) struct Passphrase {
+    passphrase: Arc<Mutex<Option<Arc<Zeroizing<String>>>>>,
+    // Checked for encrypted credentials when asking, to know whether a passphrase is being chosen.
+    store_dir: PathBuf,
+    keys: Arc<Mutex<Vec<DerivedKey>>>,
+}
+
+struct DerivedKey {
+    salt: [u8; ARGON2_ID_SALTBYTES],
+    ops_limit: u32,
+    mem_limit: u32,
+    key: Zeroizing<[u8; XSALSA_KEYBYTES]>,
+}
+
+impl Passphrase {
+    /// A passphrase that is asked for with [get_credentials_password] when it is first needed.
+    pub(crate) fn ask_when_needed(store_dir: PathBuf) -> Self {
+        Passphrase {
+            passphrase: Arc::new(Mutex::new(None)),
+            store_dir,
+            keys: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    #[cfg(test)]
+    fn new(passphrase: Zeroizing<String>) -> Self {
+        Passphrase {
+            passphrase: Arc::new(Mutex::new(Some(Arc::new(passphrase)))),
+            store_dir: PathBuf::new(),
+            keys: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Whether the passphrase is known or can be asked for, so that credentials can be saved.
+    pub(crate) fn is_available(&self) -> bool {
+        self.passphrase
+            .lock()
+            .is_ok_and(|passphrase| passphrase.is_some())
+            || credentials_password_available()
+    }
+
+    fn get(&self) -> anyhow::Result<Arc<Zeroizing<String>>> {
+        let mut passphrase = self
+            .passphrase
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Credentials passphrase is poisoned"))?;
+        if let Some(passphrase) = passphrase.as_ref() {
+            return Ok(passphrase.clone());
+        }
+
+        let confirm = !encrypted_credentials_saved(&self.store_dir)?;
+        let asked = Arc::new(get_credentials_password(confirm)?);
+        *passphrase = Some(asked.clone());
+
+        Ok(asked)
+    }
+
+    /// The key for `salt` and the limits, derived the first time that it is needed.
+    fn key(
+        &self,
+        salt: &[u8; ARGON2_ID_SALTBYTES],
+        ops_limit: u32,
+        mem_limit: u32,
+    ) -> anyhow::Result<Zeroizing<[u8; XSALSA_KEYBYTES]>> {
+        let mut keys = self
+            .keys
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Credentials key cache is poisoned"))?;
+        if let Some(derived) = keys.iter().find(|derived| {
+            &derived.salt == salt
+                && derived.ops_limit == ops_limit
+                && derived.mem_limit == mem_limit
+        }) {
+            return Ok(derived.key.clone());
+        }
+
+        let key = derive_credentials_key(&self.get()?, salt, ops_limit, mem_limit)?;
+        keys.push(DerivedKey {
+            salt: *salt,
+            ops_limit,
+            mem_limit,
+            key: key.clone(),
+        });
+
+        Ok(key)
+    }
+
+    /// A salt and key to encrypt new credentials with, reusing a key that was derived with the
+    /// current limits if there is one.
+    fn key_for_writing(
+        &self,
+    ) -> anyhow::Result<([u8; ARGON2_ID_SALTBYTES], Zeroizing<[u8; XSALSA_KEYBYTES]>)> {
+        let existing = self
+            .keys
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Credentials key cache is poisoned"))?
+            .iter()
+            .find(|derived| {
+                derived.ops_limit == ARGON2_ID_OPSLIMIT_INTERACTIVE
+                    && derived.mem_limit == ARGON2_ID_MEMLIMIT_INTERACTIVE
+            })
+            .map(|derived| derived.salt);
+
+        let salt = match existing {
+            Some(salt) => salt,
+            None => {
+                let mut salt = [0; ARGON2_ID_SALTBYTES];
+                sodoken::random::randombytes_buf(&mut salt)?;
+                salt
+            }
+        };
+        let key = self.key(
+            &salt,
+            ARGON2_ID_OPSLIMIT_INTERACTIVE,
+            ARGON2_ID_MEMLIMIT_INTERACTIVE,
+        )?;
+
+        Ok((salt, key))
+    }
+}
+
+impl std::fmt::Debug for Passphrase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Passphrase(..)")
+    }
+}
+
+/// Load the credentials for `scope` that were saved for `key`.
+pub(crate) fn load_credentials(
+    store_dir: &Path,
+    key: &CredentialsKey,
+    scope: CredentialScope,
+    passphrase: &Passphrase,
+) -> anyhow::Result<Option<SigningCredentials>> {
+    let credentials_path = scope_file(&key.dir(store_dir), scope);
+    match read_credentials_file(&credentials_path, passphrase)? {
+        Some((cell_id, credentials)) if cell_id == key.cell_id => Ok(Some(credentials)),
+        // Saved for an earlier install of the app, with the same agent.
+        _ => Ok(None),
+    }
+}
+
+/// Save the credentials for `scope` for `key`, replacing any that were saved before.
+pub(crate) fn save_credentials(
+    store_dir: &Path,
+    key: &CredentialsKey,
+    scope: CredentialScope,
+    signing_credentials: &SigningCredentials,
+    passphrase: &Passphrase,
+) -> anyhow::Result<()> {
+    let dir = key.dir(store_dir);
+    create_private_dir(&dir)?;
+    std::fs::write(
+        dir.join(APP_INFO_FILE_NAME),
+        serde_json::to_string_pretty(key)?,
+    )
+    .with_context(|| format!("Could not write {:?}", dir.join(APP_INFO_FILE_NAME)))?;
+
+    let saved = SavedCredentials {
+        cell_id: key.cell_id.clone(),
+        signing_agent_key: signing_credentials.signing_agent_key.clone(),
+        keypair: signing_credentials.keypair.to_keypair_bytes().to_vec(),
+        cap_secret: signing_credentials.cap_secret,
+    };
+
+    write_credentials(&scope_file(&dir, scope), &saved, passphrase)
+}
+
+/// Whether encrypted credentials have been saved for any app, so a passphrase has already been
+/// chosen. Plaintext credentials from an older version don't count, they are encrypted with the
+/// passphrase that is chosen now.
+pub(crate) fn encrypted_credentials_saved(store_dir: &Path) -> anyhow::Result<bool> {
+    let mut paths = Vec::new();
+    for app in list_saved_apps(store_dir)? {
+        paths.extend(app.scopes.iter().map(|scope| scope_file(&app.dir, *scope)));
+    }
+
+    Ok(paths.iter().any(|path| {
+        std::fs::read(path)
+            .is_ok_and(|content| serde_json::from_slice::<EncryptedCredentials>(&content).is_ok())
+    }))
+}
+
+/// Remove credentials that were saved before they were split by [CredentialScope], because they
+/// can call every zome function.
+///
+/// The conductor has no way to revoke the grant that they were authorized with, so the user is told
+/// that it stays live for anyone who has copied the file.
+pub(crate) fn remove_legacy_credentials(store_dir: &Path) -> anyhow::Result<()> {
+    let legacy_path = store_dir.join(LEGACY_CREDENTIALS_FILE_NAME);
+    if legacy_path.exists() {
+        std::fs::remove_file(&legacy_path)
+            .with_context(|| format!("Could not remove old credentials {:?}", legacy_path))?;
+        println!("Removed old Holochain credentials that could call every zome function");
+        eprintln!(
+            "Warning: the grant for the removed credentials can't be revoked and stays valid on the conductor. If {:?} may have been copied, reinstall the `checked` app to invalidate it.",
+            legacy_path
+        );
+    }
+
+    Ok(())
+}
+
+fn create_private_dir(dir: &Path) -> anyhow::Result<()> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Could not create {:?}", dir))?;
+
+    #[cfg(target_family = "unix")]
+    {
+        use std::fs::Permissions;
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(dir, Permissions::from_mode(0o700))
+            .with_context(|| format!("Could not set permissions on {:?}", dir))?;
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedCredentials {
+    cell_id: CellId,
+    signing_agent_key: AgentPubKey,
+    keypair: Vec<u8>,
+    cap_secret: CapSecret,
+}
+
+/// [SavedCredentials] encrypted with a key that is derived from the credentials passphrase with
+/// argon2id. The limits are saved so that they can be raised without breaking existing files.
+#[derive(Serialize, Deserialize)]
+struct EncryptedCredentials {
+    ops_limit: u32,
+    mem_limit: u32,
+    salt: Vec<u8>,
+    nonce: Vec<u8>,
+    cipher: Vec<u8>,
+}
+
+impl EncryptedCredentials {
+    fn encrypt(saved: &SavedCredentials, passphrase: &Passphrase) -> anyhow::Result<Self> {
+        let mut nonce = [0; XSALSA_NONCEBYTES];
+        sodoken::random::randombytes_buf(&mut nonce)?;
+
+        let (salt, key) = passphrase.key_for_writing()?;
+
+        let message = Zeroizing::new(
+            serde_json::to_vec(saved)
+                .map_err(|e| anyhow::anyhow!("Error serializing credentials: {:?}", e))?,
+        );
+        let mut cipher = vec![0; message.len() + XSALSA_MACBYTES];
+        xsalsa_easy(&mut cipher, &nonce, &message, &key)
+            .map_err(|e| anyhow::anyhow!("Error encrypting credentials: {:?}", e))?;
+
+        Ok(EncryptedCredentials {
+            ops_limit: ARGON2_ID_OPSLIMIT_INTERACTIVE,
+            mem_limit: ARGON2_ID_MEMLIMIT_INTERACTIVE,
+            salt: salt.to_vec(),
+            nonce: nonce.to_vec(),
+            cipher,
+        })
+    }
+
+    fn decrypt(&self, passphrase: &Passphrase) -> anyhow::Result<SavedCredentials> {
+        let salt = self.salt.as_slice().try_into().context("Invalid salt")?;
+        let nonce = self.nonce.as_slice().try_into().context("Invalid nonce")?;
+        if self.cipher.len() < XSALSA_MACBYTES {
+            anyhow::bail!("Invalid encrypted credentials");
+        }
+        // Don't let a modified file make us use an unreasonable amount of memory or time.
+        if self.mem_limit > ARGON2_ID_MEMLIMIT_SENSITIVE {
+            anyhow::bail!("Invalid memory limit for the credentials passphrase");
+        }
+        if self.ops_limit > ARGON2_ID_OPSLIMIT_SENSITIVE {
+            anyhow::bail!("Invalid operations limit for the credentials passphrase");
+        }
+
+        let key = passphrase.key(salt, self.ops_limit, self.mem_limit)?;
+
+        let mut message = Zeroizing::new(vec![0; self.cipher.len() - XSALSA_MACBYTES]);
+        xsalsa_open_easy(&mut message, &self.cipher, nonce, &key).map_err(|_| {
+            anyhow::anyhow!(
+                "Could not decrypt the saved Holochain credentials, is the passphrase correct?"
+            )
+        })?;
+
+        Ok(serde_json::from_slice(&message)?)
+    }
+}
+
+fn derive_credentials_key(
+    passphrase: &str,
+    salt: &[u8; ARGON2_ID_SALTBYTES],
+    ops_limit: u32,
+    mem_limit: u32,
+) -> anyhow::Result<Zeroizing<[u8; XSALSA_KEYBYTES]>> {
+    let mut key = Zeroizing::new([0; XSALSA_KEYBYTES]);
+    blocking_argon2id(
+        key.as_mut_slice(),
+        passphrase.as_bytes(),
+        salt,
+        ops_limit,
+        mem_limit,
+    )
+    .map_err(|e| anyhow::anyhow!("Error deriving the credentials key: {:?}", e))?;
+
+    Ok(key)
+}
+
+fn write_credentials(
+    credentials_path: &Path,
+    saved: &SavedCredentials,
+    passphrase: &Passphrase,
+) -> anyhow::Result<()> {
+    let serialized = serde_json::to_string(&EncryptedCredentials::encrypt(saved, passphrase)?)
+        .map_err(|e| anyhow::anyhow!("Error serializing credentials: {:?}", e))?;
+
+    let mut options = File::options();
+    options.create(true).write(true).truncate(true);
+    #[cfg(target_family = "unix")]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut f = options
+        .open(credentials_path)
+        .map_err(|e| anyhow::anyhow!("Error opening credentials file: {:?}", e))?;
+
+    // The mode only applies to new files, an existing file may have been created with a wider one.
+    #[cfg(target_family = "unix")]
+    {
+        use std::fs::Permissions;
+        use std::os::unix::fs::PermissionsExt;
+        f.set_permissions(Permissions::from_mode(0o600))
+            .map_err(|e| {
+                anyhow::anyhow!("Error setting permissions on credentials file: {:?}", e)
+            })?;
+    }
+
+    f.write_all(serialized.as_bytes())
+        .map_err(|e| anyhow::anyhow!("Error writing credentials file: {:?}", e))?;
+
+    Ok(())
+}
+
+/// Read the credentials saved at `credentials_path`.
+///
+/// Encrypted credentials that other users can read are refused. Credentials that were saved as
+/// plaintext are encrypted, unless other users could have read them, in which case they are removed
+/// so that new ones are authorized.
+fn read_credentials_file(
+    credentials_path: &Path,
+    passphrase: &Passphrase,
+) -> anyhow::Result<Option<(CellId, SigningCredentials)>> {
+    let content = match std::fs::read(credentials_path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(None);
+        }
+        Err(e) => {
+            return Err(anyhow::anyhow!("Error reading credentials file: {:?}", e));
+        }
+    };
+    let private = is_private_file(credentials_path)?;
+
+    let saved = if let Ok(encrypted) = serde_json::from_slice::<EncryptedCredentials>(&content) {
+        if !private {
+            anyhow::bail!(
+                "Saved credentials {:?} can be read by other users. Restrict them to your user with `chmod 600`, or remove them with `checked credentials forget`",
+                credentials_path
+            );
+        }
+
+        encrypted.decrypt(passphrase).with_context(|| {
+            format!(
+                "Use `checked credentials forget` to authorize new credentials if the passphrase for {:?} has been lost",
+                credentials_path
+            )
+        })?
+    } else {
+        match serde_json::from_slice::<SavedCredentials>(&content) {
+            Ok(saved) if private => {
+                write_credentials(credentials_path, &saved, passphrase)?;
+                println!("Encrypted the saved Holochain credentials");
+                saved
+            }
+            Ok(_) => {
+                std::fs::remove_file(credentials_path).with_context(|| {
+                    format!(
+                        "Could not remove exposed credentials {:?}",
+                        credentials_path
+                    )
+                })?;
+                println!("Removed unencrypted Holochain credentials that other users could read");
+                return Ok(None);
+            }
+            Err(e) => {
+                eprintln!("Saved credentials file is corrupt: {:?}", e);
+                return Ok(None);
+            }
+        }
+    };
+
+    let keypair = match ed25519_dalek::SigningKey::from_keypair_bytes(
+        saved
+            .keypair
+            .as_slice()
+            .try_into()
+            .context("Could not create signing key from saved data")?,
+    ) {
+        Ok(keypair) => keypair,
+        Err(e) => {
+            eprintln!("Saved credentials file is corrupt: {:?}", e);
+            return Ok(None);
+        }
+    };
+
+    Ok(Some((
+        saved.cell_id,
+        SigningCredentials {
+            signing_agent_key: saved.signing_agent_key,
+            keypair,
+            cap_secret: saved.cap_secret,
+        },
+    )))
+}
+
+/// Whether only the current user can access the file at `path`.
+fn is_private_file(path: &Path) -> anyhow::Result<bool> {
+    #[cfg(target_family = "unix")]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(path)
+            .with_context(|| format!("Could not check the permissions of {:?}", path))?
+            .permissions()
+            .mode();
+        Ok(mode & 0o077 == 0)
+    }
+
+    #[cfg(not(target_family = "unix"))]
+    {
+        let _ = path;
+        Ok(true)
+    }
+}
+
+/// The credentials that are saved for an app install.
+#[derive(Debug)]
+struct SavedApp {
+    dir: PathBuf,
+    /// Missing if the app info could not be read.
+    key: Option<CredentialsKey>,
+    scopes: Vec<CredentialScope>,
+}
+
+impl SavedApp {
+    fn matches(&self, app_id: Option<&str>, agent: Option<&str>) -> bool {
+        let Some(key) = &self.key else {
+            return false;
+        };
+
+        app_id.is_none_or(|app_id| key.installed_app_id == app_id)
+            && agent.is_none_or(|agent| key.cell_id.agent_pubkey().to_string() == agent)
+    }
+
+    fn describe(&self) -> String {
+        let scopes = self
+            .scopes
+            .iter()
+            .map(|scope| scope_name(*scope))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        match &self.key {
+            Some(key) => format!(
+                "App '{}' with agent {} and DNA {}: {} ({})",
+                key.installed_app_id,
+                key.cell_id.agent_pubkey(),
+                key.cell_id.dna_hash(),
+                scopes,
+                self.dir.display()
+            ),
+            None => format!("Unknown app: {} ({})", scopes, self.dir.display()),
+        }
+    }
+}
+
+fn list_saved_apps(store_dir: &Path) -> anyhow::Result<Vec<SavedApp>> {
+    let credentials_dir = store_dir.join(CREDENTIALS_DIR_NAME);
+    let entries = match std::fs::read_dir(&credentials_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Could not read {:?}", credentials_dir));
+        }
+    };
+
+    let mut apps = Vec::new();
+    for entry in entries {
+        let dir = entry?.path();
+        if !dir.is_dir() {
+            continue;
+        }
+
+        let key = std::fs::read(dir.join(APP_INFO_FILE_NAME))
+            .ok()
+            .and_then(|content| serde_json::from_slice(&content).ok());
+        let scopes = SCOPES
+            .into_iter()
+            .filter(|scope| scope_file(&dir, *scope).exists())
+            .collect();
+
+        apps.push(SavedApp { dir, key, scopes });
+    }
+    apps.sort_by(|a, b| a.dir.cmp(&b.dir));
+
+    Ok(apps)
+}
+
+/// List or forget the Holochain credentials that are saved for each app install.
+///
+/// Forgetting credentials only removes the saved copy. The grant stays on the conductor, but it
+/// can't be used without the secret that was saved.
+pub fn credentials(credentials_args: CredentialsArgs) -> anyhow::Result<()> {
+    let store_dir = get_store_dir(credentials_args.config_dir)?;
+    let apps = list_saved_apps(&store_dir)?;
+
+    match credentials_args.command {
+        CredentialsCommand::List => {
+            if apps.is_empty() {
+                println!("No Holochain credentials are saved");
+            }
+            for app in &apps {
+                println!("{}", app.describe());
+            }
+        }
+        CredentialsCommand::Forget { app_id, agent, all } => {
+            if !all && app_id.is_none() && agent.is_none() {
+                anyhow::bail!(
+                    "Choose the credentials to forget with `--app-id` or `--agent`, or forget them all with `--all`"
+                );
+            }
+
+            let mut forgotten = 0;
+            for app in &apps {
+                if all || app.matches(app_id.as_deref(), agent.as_deref()) {
+                    std::fs::remove_dir_all(&app.dir)
+                        .with_context(|| format!("Could not remove {:?}", app.dir))?;
+                    println!("Forgot {}", app.describe());
+                    forgotten += 1;
+                }
+            }
+
+            if forgotten == 0 {
+                println!("No saved credentials matched");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use holochain_types::prelude::DnaHash;
+
+    fn test_key(installed_app_id: &str, agent: u8) -> CredentialsKey {
+        CredentialsKey {
+            installed_app_id: installed_app_id.to_string(),
+            cell_id: CellId::new(
+                DnaHash::from_raw_36(vec![1; 36]),
+                AgentPubKey::from_raw_36(vec![agent; 36]),
+            ),
+        }
+    }
+
+    fn test_credentials(seed: u8) -> SigningCredentials {
+        SigningCredentials {
+            signing_agent_key: AgentPubKey::from_raw_36(vec![seed; 36]),
+            keypair: ed25519_dalek::SigningKey::from_bytes(&[seed; 32]),
+            cap_secret: CapSecret::from([seed; 64]),
+        }
+    }
+
+    fn test_saved(key: &CredentialsKey, credentials: &SigningCredentials) -> SavedCredentials {
+        SavedCredentials {
+            cell_id: key.cell_id.clone(),
+            signing_agent_key: credentials.signing_agent_key.clone(),
+            keypair: credentials.keypair.to_keypair_bytes().to_vec(),
+            cap_secret: credentials.cap_secret,
+        }
+    }
+
+    fn test_passphrase(passphrase: &str) -> Passphrase {
+        Passphrase::new(Zeroizing::new(passphrase.to_string()))
+    }
+
+    #[test]
+    fn encrypt_saved_credentials() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = test_key("checked", 2);
+        let credentials = test_credentials(4);
+
+        save_credentials(
+            dir.path(),
+            &key,
+            CredentialScope::Read,
+            &credentials,
+            &test_passphrase("passphrase"),
+        )
+        .unwrap();
+
+        let content =
+            std::fs::read(scope_file(&key.dir(dir.path()), CredentialScope::Read)).unwrap();
+        assert!(serde_json::from_slice::<EncryptedCredentials>(&content).is_ok());
+
+        let loaded = load_credentials(
+            dir.path(),
+            &key,
+            CredentialScope::Read,
+            &test_passphrase("passphrase"),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(credentials.cap_secret, loaded.cap_secret);
+        assert_eq!(
+            credentials.keypair.to_keypair_bytes(),
+            loaded.keypair.to_keypair_bytes()
+        );
+
+        assert!(load_credentials(
+            dir.path(),
+            &key,
+            CredentialScope::Read,
+            &test_passphrase("wrong")
+        )
+        .is_err());
+        assert!(load_credentials(
+            dir.path(),
+            &key,
+            CredentialScope::Write,
+            &test_passphrase("passphrase")
+        )
+        .unwrap()
+        .is_none());
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn credentials_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let credentials_path = dir.path().join("read.json");
+        let key = test_key("checked", 2);
+        let saved = test_saved(&key, &test_credentials(4));
+        let passphrase = test_passphrase("passphrase");
+
+        // Plaintext credentials from an older version are encrypted in place.
+        std::fs::write(&credentials_path, serde_json::to_vec(&saved).unwrap()).unwrap();
+        std::fs::set_permissions(&credentials_path, std::fs::Permissions::from_mode(0o600))
+            .unwrap();
+        assert!(read_credentials_file(&credentials_path, &passphrase)
+            .unwrap()
+            .is_some());
+        let content = std::fs::read(&credentials_path).unwrap();
+        assert!(serde_json::from_slice::<EncryptedCredentials>(&content).is_ok());
+        assert_eq!(
+            0o600,
+            std::fs::metadata(&credentials_path)
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o777
+        );
+
+        // Encrypted credentials that others can read are refused.
+        std::fs::set_permissions(&credentials_path, std::fs::Permissions::from_mode(0o640))
+            .unwrap();
+        assert!(read_credentials_file(&credentials_path, &passphrase).is_err());
+
+        // Plaintext credentials that others could read are discarded.
+        std::fs::write(&credentials_path, serde_json::to_vec(&saved).unwrap()).unwrap();
+        assert!(read_credentials_file(&credentials_path, &passphrase)
+            .unwrap()
+            .is_none());
+        assert!(!credentials_path.exists());
+    }
+
+    #[test]
+    fn credentials_by_app_install() {
+        let dir = tempfile::tempdir().unwrap();
+        let passphrase = test_passphrase("passphrase");
+        let first = test_key("checked", 2);
+        let second = test_key("checked", 3);
+        let other_app = test_key("checked-test", 2);
+
+        for (key, seed) in [(&first, 4), (&second, 5), (&other_app, 6)] {
+            save_credentials(
+                dir.path(),
+                key,
+                CredentialScope::Read,
+                &test_credentials(seed),
+                &passphrase,
+            )
+            .unwrap();
+        }
+
+        for (key, seed) in [(&first, 4), (&second, 5), (&other_app, 6)] {
+            let loaded = load_credentials(dir.path(), key, CredentialScope::Read, &passphrase)
+                .unwrap()
+                .unwrap();
+            assert_eq!(CapSecret::from([seed; 64]), loaded.cap_secret);
+        }
+
+        // A reinstall with the same agent gets a new DNA hash.
+        let reinstalled = CredentialsKey {
+            cell_id: CellId::new(
+                DnaHash::from_raw_36(vec![9; 36]),
+                first.cell_id.agent_pubkey().clone(),
+            ),
+            ..first.clone()
+        };
+        assert!(
+            load_credentials(dir.path(), &reinstalled, CredentialScope::Read, &passphrase)
+                .unwrap()
+                .is_none()
+        );
+
+        let apps = list_saved_apps(dir.path()).unwrap();
+        assert_eq!(3, apps.len());
+        assert_eq!(
+            2,
+            apps.iter()
+                .filter(|app| app.matches(Some("checked"), None))
+                .count()
+        );
+        assert_eq!(
+            1,
+            apps.iter()
+                .filter(|app| app.matches(
+                    Some("checked"),
+                    Some(&first.cell_id.agent_pubkey().to_string())
+                ))
+                .count()
+        );
+
+        credentials(CredentialsArgs {
+            config_dir: Some(dir.path().to_path_buf()),
+            command: CredentialsCommand::Forget {
+                app_id: Some("checked".to_string()),
+                agent: None,
+                all: false,
+            },
+        })
+        .unwrap();
+
+        let apps = list_saved_apps(dir.path()).unwrap();
+        assert_eq!(1, apps.len());
+        assert_eq!(Some(other_app), apps[0].key);
+    }
+
+    #[test]
+    fn app_ids_do_not_collide() {
+        let dir = tempfile::tempdir().unwrap();
+        let app_ids = ["a.b", "a_b", "a-b", "a/b", "ab", "a\u{e9}b"];
+
+        let dirs = app_ids
+            .iter()
+            .map(|app_id| test_key(app_id, 2).dir(dir.path()))
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(app_ids.len(), dirs.len());
+
+        for app_dir in &dirs {
+            assert_eq!(
+                Some(dir.path().join(CREDENTIALS_DIR_NAME).as_path()),
+                app_dir.parent()
+            );
+        }
+    }
+
+    #[test]
+    fn passphrase_is_not_asked_for_without_saved_credentials() {
+        let dir = tempfile::tempdir().unwrap();
+        // Asking would fail, there is no terminal or passphrase variable in tests.
+        let passphrase = Passphrase::ask_when_needed(dir.path().to_path_buf());
+
+        assert!(load_credentials(
+            dir.path(),
+            &test_key("checked", 2),
+            CredentialScope::Read,
+            &passphrase
+        )
+        .unwrap()
+        .is_none());
+        assert!(passphrase.passphrase.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn derive_key_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let passphrase = test_passphrase("passphrase");
+        let key = test_key("checked", 2);
+
+        for scope in SCOPES {
+            save_credentials(dir.path(), &key, scope, &test_credentials(4), &passphrase).unwrap();
+            assert!(load_credentials(dir.path(), &key, scope, &passphrase)
+                .unwrap()
+                .is_some());
+        }
+        assert_eq!(1, passphrase.keys.lock().unwrap().len());
+
+        // Another client derives the key once to read files with the same salt.
+        let other = test_passphrase("passphrase");
+        for scope in SCOPES {
+            assert!(load_credentials(dir.path(), &key, scope, &other)
+                .unwrap()
+                .is_some());
+        }
+        assert_eq!(1, other.keys.lock().unwrap().len());
+    }
+
+    #[test]
+    fn refuse_excessive_limits() {
+        let passphrase = test_passphrase("passphrase");
+        let saved = test_saved(&test_key("checked", 2), &test_credentials(4));
+        let encrypted = EncryptedCredentials::encrypt(&saved, &passphrase).unwrap();
+
+        for (ops_limit, mem_limit) in [
+            (ARGON2_ID_OPSLIMIT_SENSITIVE + 1, encrypted.mem_limit),
+            (encrypted.ops_limit, ARGON2_ID_MEMLIMIT_SENSITIVE + 1),
+        ] {
+            let modified = EncryptedCredentials {
+                ops_limit,
+                mem_limit,
+                salt: encrypted.salt.clone(),
+                nonce: encrypted.nonce.clone(),
+                cipher: encrypted.cipher.clone(),
+            };
+            let err = modified.decrypt(&passphrase).unwrap_err();
+            assert!(err.to_string().contains("limit"), "{:?}", err);
+        }
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn choose_passphrase_for_plaintext_credentials() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let key = test_key("checked", 2);
+        let credentials_dir = key.dir(dir.path());
+        create_private_dir(&credentials_dir).unwrap();
+        std::fs::write(
+            credentials_dir.join(APP_INFO_FILE_NAME),
+            serde_json::to_vec(&key).unwrap(),
+        )
+        .unwrap();
+
+        let credentials_path = scope_file(&credentials_dir, CredentialScope::Read);
+        std::fs::write(
+            &credentials_path,
+            serde_json::to_vec(&test_saved(&key, &test_credentials(4))).unwrap(),
+        )
+        .unwrap();
+        std::fs::set_permissions(&credentials_path, std::fs::Permissions::from_mode(0o600))
+            .unwrap();
+
+        // Only plaintext credentials are saved, so the passphrase is being chosen.
+        assert!(!encrypted_credentials_saved(dir.path()).unwrap());
+
+        load_credentials(
+            dir.path(),
+            &key,
+            CredentialScope::Read,
+            &test_passphrase("passphrase"),
+        )
+        .unwrap()
+        .unwrap();
+        assert!(encrypted_credentials_saved(dir.path()).unwrap());
+    }
+}
@@ -0,0 +1,707 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use bytes::Bytes;
+use holochain_types::prelude::{ActionHash, ExternIO};
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::Incoming;
+use hyper::header::HeaderValue;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use minisign::{PublicKey, SecretKey};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+use zeroize::Zeroizing;
+
+use crate::cli::DaemonArgs;
+use crate::common::{get_store_dir, get_verification_key_path};
+use crate::fetch::SignatureCheckReport;
+use crate::fetch_manifest::{
+    check_output_path, fetch_and_check, FetchCheckOptions, FetchPolicy, LockedSignature,
+};
+use crate::hc_client::{get_authenticated_app_agent_client, CredentialScope, HolochainClient};
+use crate::sign::{
+    default_signature_path, load_signing_keys, make_create_asset_signature, sign_file,
+};
+
+/// The largest request body that the daemon will accept. Requests only carry paths and URLs.
+const MAX_REQUEST_SIZE: usize = 1024 * 1024;
+
+/// The file in the config directory that the daemon writes its API token to.
+pub const DAEMON_TOKEN_FILE_NAME: &str = "daemon.token";
+
+struct DaemonState {
+    app_client: HolochainClient,
+    store_dir: PathBuf,
+    output_dir: PathBuf,
+    policy: FetchPolicy,
+    keys: Mutex<KeyCache>,
+    /// The token that requests must present, unless they come over a Unix socket.
+    token: Option<String>,
+}
+
+/// Run a long-lived local API over a single Holochain connection.
+///
+/// The API accepts and returns JSON:
+/// - `POST /check` downloads an asset and reports on its signatures, without saving it.
+/// - `POST /fetch` downloads an asset and saves it to `output` if its signatures pass the `policy`.
+/// - `POST /sign` signs a local file, and distributes the signature if `distribute` is set.
+/// - `GET /keys` lists your signing keys and whether they are unlocked.
+/// - `DELETE /keys` locks every signing key.
+///
+/// A signing key is unlocked when a request to `/sign` includes its password, and stays unlocked
+/// for [DaemonArgs::key_timeout] seconds so that later requests don't need the password.
+///
+/// Assets are accepted by the daemon's own [DaemonArgs::policy] and saved under
+/// [DaemonArgs::output_dir].
+///
+/// Over TCP, every request must have an `Authorization: Bearer <token>` header with the token that
+/// is written to [DAEMON_TOKEN_FILE_NAME] in the config directory at startup, readable only by the
+/// current user. A Unix socket is only accessible to the current user, so it doesn't need one.
+/// Requests with an `Origin` header are refused so that web pages can't use the API, and request
+/// bodies must be sent as `application/json`.
+///
+/// Holochain is connected to with read-only credentials. The first request to `/sign` that
+/// distributes its signature asks, on the daemon's terminal, to authorize write access. Without a
+/// terminal, write access needs [ALLOW_WRITE_ENV](crate::cli::ALLOW_WRITE_ENV) to be set.
+pub async fn daemon(daemon_args: DaemonArgs) -> anyhow::Result<()> {
+    let admin_port = daemon_args.connect.admin_port().await?;
+
+    let app_client = get_authenticated_app_agent_client(
+        admin_port,
+        daemon_args.config_dir.clone(),
+        daemon_args.app_id.clone(),
+        daemon_args.connect.timeout(),
+        CredentialScope::Read,
+    )
+    .await?;
+
+    let store_dir = get_store_dir(daemon_args.config_dir.clone())?;
+    let output_dir = match daemon_args.output_dir {
+        Some(output_dir) => output_dir,
+        None => std::env::current_dir().context("Could not get the current directory")?,
+    };
+
+    #[cfg(unix)]
+    let use_socket = daemon_args.socket.is_some();
+    #[cfg(not(unix))]
+    let use_socket = false;
+
+    let token = if use_socket {
+        None
+    } else {
+        let token = hex::encode(rand::random::<[u8; 32]>());
+        let token_path = store_dir.join(DAEMON_TOKEN_FILE_NAME);
+        write_private_file(&token_path, &token)?;
+        println!("API token written to {:?}", token_path);
+        Some(token)
+    };
+
+    let state = Arc::new(DaemonState {
+        app_client,
+        store_dir,
+        output_dir,
+        policy: daemon_args.policy,
+        keys: Mutex::new(KeyCache::new(Duration::from_secs(daemon_args.key_timeout))),
+        token,
+    });
+
+    #[cfg(unix)]
+    if let Some(socket) = &daemon_args.socket {
+        return serve_unix_socket(socket, state).await;
+    }
+
+    let listener = TcpListener::bind(daemon_args.listen)
+        .await
+        .with_context(|| format!("Could not listen on {}", daemon_args.listen))?;
+    println!("Daemon listening on http://{}", daemon_args.listen);
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        spawn_connection(stream, state.clone());
+    }
+}
+
+#[cfg(unix)]
+async fn serve_unix_socket(socket: &Path, state: Arc<DaemonState>) -> anyhow::Result<()> {
+    let listener = crate::common::bind_private_socket(socket)?;
+    println!("Daemon listening on {}", socket.display());
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        spawn_connection(stream, state.clone());
+    }
+}
+
+/// Write `content` to `path`, so that only the current user can read it.
+fn write_private_file(path: &Path, content: &str) -> anyhow::Result<()> {
+    let mut options = std::fs::File::options();
+    options.create(true).write(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut f = options
+        .open(path)
+        .with_context(|| format!("Could not open {:?}", path))?;
+
+    // The mode only applies to new files, an existing file may have been created with a wider one.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        f.set_permissions(std::fs::Permissions::from_mode(0o600))
+            .with_context(|| format!("Could not set permissions on {:?}", path))?;
+    }
+
+    f.write_all(content.as_bytes())
+        .with_context(|| format!("Could not write {:?}", path))
+}
+
+fn spawn_connection<S>(stream: S, state: Arc<DaemonState>)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let service = service_fn(move |request| handle_request(state.clone(), request));
+        if let Err(e) = http1::Builder::new()
+            .serve_connection(TokioIo::new(stream), service)
+            .await
+        {
+            eprintln!("Daemon connection failed: {:?}", e);
+        }
+    });
+}
+
+/// An error to return to the client, with the status to return it with.
+#[derive(Debug)]
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl ToString) -> Self {
+        ApiError {
+            status,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e))
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+async fn handle_request(
+    state: Arc<DaemonState>,
+    request: Request<Incoming>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+
+    if let Err(e) = authorize(&state, &request) {
+        return Ok(json_response(e.status, &ErrorResponse { error: e.message })
+            .expect("Error response should serialize"));
+    }
+
+    let result = match (method.clone(), path.as_str()) {
+        (Method::POST, "/check") => match read_json(request).await {
+            Ok(check_request) => check(&state, check_request).await,
+            Err(e) => Err(e),
+        },
+        (Method::POST, "/fetch") => match read_json(request).await {
+            Ok(fetch_request) => fetch(&state, fetch_request).await,
+            Err(e) => Err(e),
+        },
+        (Method::POST, "/sign") => match read_json(request).await {
+            Ok(sign_request) => sign(&state, sign_request).await,
+            Err(e) => Err(e),
+        },
+        (Method::GET, "/keys") => list_keys(&state).await,
+        (Method::DELETE, "/keys") => {
+            state.keys.lock().await.lock_all();
+            json_response(StatusCode::OK, &serde_json::json!({}))
+        }
+        (_, "/check" | "/fetch" | "/sign" | "/keys") => Err(ApiError::new(
+            StatusCode::METHOD_NOT_ALLOWED,
+            format!("{} is not supported for {}", method, path),
+        )),
+        _ => Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            format!("Unknown path {}", path),
+        )),
+    };
+
+    Ok(result.unwrap_or_else(|e| {
+        if e.status.is_server_error() {
+            eprintln!("{} {} failed: {}", method, path, e.message);
+        }
+
+        json_response(e.status, &ErrorResponse { error: e.message })
+            .expect("Error response should serialize")
+    }))
+}
+
+/// Refuse requests from web pages and, when the daemon has a token, requests without it.
+fn authorize<B>(state: &DaemonState, request: &Request<B>) -> Result<(), ApiError> {
+    if request.headers().contains_key(hyper::header::ORIGIN) {
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "Requests from web pages are not accepted",
+        ));
+    }
+
+    let Some(token) = &state.token else {
+        return Ok(());
+    };
+
+    let presented = request
+        .headers()
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match presented {
+        Some(presented) if tokens_match(presented, token) => Ok(()),
+        _ => Err(ApiError::new(
+            StatusCode::UNAUTHORIZED,
+            format!(
+                "A bearer token is required, it is in {} in the config directory",
+                DAEMON_TOKEN_FILE_NAME
+            ),
+        )),
+    }
+}
+
+/// Compare tokens without giving away how much of them matched through the time it takes.
+fn tokens_match(presented: &str, token: &str) -> bool {
+    presented.len() == token.len()
+        && presented
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+async fn read_json<T: DeserializeOwned>(request: Request<Incoming>) -> Result<T, ApiError> {
+    let is_json = request
+        .headers()
+        .get(hyper::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/json"));
+    if !is_json {
+        return Err(ApiError::new(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "The request body must be application/json",
+        ));
+    }
+
+    let body = Limited::new(request.into_body(), MAX_REQUEST_SIZE)
+        .collect()
+        .await
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, format!("Invalid body: {}", e)))?
+        .to_bytes();
+
+    serde_json::from_slice(&body)
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, format!("Invalid request: {}", e)))
+}
+
+fn json_response<T: Serialize>(
+    status: StatusCode,
+    value: &T,
+) -> Result<Response<Full<Bytes>>, ApiError> {
+    let body = serde_json::to_vec(value).context("Could not serialize response")?;
+
+    let mut response = Response::new(Full::new(Bytes::from(body)));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+
+    Ok(response)
+}
+
+#[derive(Deserialize, Debug)]
+struct CheckRequest {
+    url: String,
+    #[serde(default)]
+    offline: bool,
+    #[serde(default)]
+    include_aliases: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct FetchRequest {
+    #[serde(flatten)]
+    check: CheckRequest,
+    /// Where to save the asset, relative to [DaemonState::output_dir].
+    output: PathBuf,
+}
+
+#[derive(Serialize, Debug)]
+struct CheckResponse {
+    url: String,
+    sha256: String,
+    accepted: bool,
+    /// Why the asset was rejected by the policy.
+    reason: Option<String>,
+    reports: Vec<CheckReport>,
+    /// Where the asset was saved, for a fetch that was accepted.
+    output: Option<PathBuf>,
+}
+
+#[derive(Serialize, Debug)]
+struct CheckReport {
+    reason: String,
+    passed_signatures: Vec<LockedSignature>,
+    failed_signatures: Vec<LockedSignature>,
+}
+
+impl From<&SignatureCheckReport> for CheckReport {
+    fn from(report: &SignatureCheckReport) -> Self {
+        let to_signatures = |signatures: &[crate::fetch::CheckedSignature]| {
+            signatures
+                .iter()
+                .map(|s| LockedSignature {
+                    author: s.author.to_string(),
+                    key_dist_address: s.key_dist_address.to_string(),
+                })
+                .collect()
+        };
+
+        CheckReport {
+            reason: format!("{:?}", report.reason),
+            passed_signatures: to_signatures(&report.passed_signatures),
+            failed_signatures: to_signatures(&report.failed_signatures),
+        }
+    }
+}
+
+async fn check(
+    state: &DaemonState,
+    check_request: CheckRequest,
+) -> Result<Response<Full<Bytes>>, ApiError> {
+    let (response, _) = check_asset(state, &check_request).await?;
+
+    json_response(StatusCode::OK, &response)
+}
+
+async fn fetch(
+    state: &DaemonState,
+    fetch_request: FetchRequest,
+) -> Result<Response<Full<Bytes>>, ApiError> {
+    check_output_path(&fetch_request.output)
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, format!("{:#}", e)))?;
+
+    let (mut response, tmp_file) = check_asset(state, &fetch_request.check).await?;
+
+    if !response.accepted {
+        return json_response(StatusCode::FORBIDDEN, &response);
+    }
+
+    let output_path = state.output_dir.join(&fetch_request.output);
+    if let Some(parent) = output_path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Could not create {:?}", parent))?;
+    }
+    // Copy rather than rename, the temporary directory may be on a different file system.
+    std::fs::copy(tmp_file.path(), &output_path)
+        .with_context(|| format!("Could not save {:?}", output_path))?;
+    response.output = Some(output_path);
+
+    json_response(StatusCode::OK, &response)
+}
+
+async fn check_asset(
+    state: &DaemonState,
+    check_request: &CheckRequest,
+) -> Result<(CheckResponse, tempfile::NamedTempFile), ApiError> {
+    let options = FetchCheckOptions {
+        offline: check_request.offline,
+        include_aliases: check_request.include_aliases,
+        config_dir: Some(state.store_dir.clone()),
+    };
+
+    let fetched = fetch_and_check(&state.app_client, &check_request.url, &options)
+        .await
+        .map_err(|e| ApiError::new(StatusCode::BAD_GATEWAY, format!("{:#}", e)))?;
+
+    let reason = state.policy.check(&fetched.reports).err();
+
+    Ok((
+        CheckResponse {
+            url: check_request.url.clone(),
+            sha256: fetched.sha256,
+            accepted: reason.is_none(),
+            reason,
+            reports: fetched.reports.iter().map(CheckReport::from).collect(),
+            output: None,
+        },
+        fetched.tmp_file,
+    ))
+}
+
+#[derive(Deserialize, Debug)]
+struct SignRequest {
+    file: PathBuf,
+    url: Option<String>,
+    #[serde(default = "default_key_name")]
+    name: String,
+    /// Unlocks the key, only needed if it isn't already unlocked.
+    password: Option<String>,
+    #[serde(default)]
+    distribute: bool,
+}
+
+fn default_key_name() -> String {
+    "default".to_string()
+}
+
+#[derive(Serialize, Debug)]
+struct SignResponse {
+    signature_path: PathBuf,
+    /// Where the signature was stored on Holochain, if it was distributed.
+    signature_address: Option<String>,
+}
+
+async fn sign(
+    state: &DaemonState,
+    sign_request: SignRequest,
+) -> Result<Response<Full<Bytes>>, ApiError> {
+    if !sign_request.file.is_file() {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("{:?} is not a file", sign_request.file),
+        ));
+    }
+
+    if sign_request.distribute && sign_request.url.is_none() {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "URL is required for distribution",
+        ));
+    }
+
+    let sig_path = default_signature_path(&sign_request.file);
+
+    // Unlocking and signing block, so they run off the runtime and without holding the key cache,
+    // which every other request to `/sign` and `/keys` needs.
+    if let Some(password) = sign_request.password {
+        let store_dir = state.store_dir.clone();
+        let name = sign_request.name.clone();
+        let (sk, vk) = tokio::task::spawn_blocking(move || {
+            load_signing_keys(&store_dir, &name, Zeroizing::new(password))
+        })
+        .await
+        .context("Could not load the signing key")?
+        .map_err(|e| ApiError::new(StatusCode::UNAUTHORIZED, format!("{:#}", e)))?;
+        state.keys.lock().await.unlock(&sign_request.name, sk, vk);
+    }
+
+    let (sk, vk) = state
+        .keys
+        .lock()
+        .await
+        .get(&sign_request.name)
+        .map(|(sk, vk)| (sk.clone(), vk.cloned()))
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                format!(
+                    "Signing key '{}' is locked, provide its password to unlock it",
+                    sign_request.name
+                ),
+            )
+        })?;
+
+    tokio::task::spawn_blocking({
+        let file = sign_request.file.clone();
+        let sig_path = sig_path.clone();
+        move || sign_file(&file, &sig_path, &sk, vk.as_ref())
+    })
+    .await
+    .context("Could not sign the file")??;
+
+    let mut signature_address = None;
+    if let (true, Some(url)) = (sign_request.distribute, &sign_request.url) {
+        let response = state
+            .app_client
+            .call_zome(
+                "fetch",
+                "create_asset_signature",
+                ExternIO::encode(make_create_asset_signature(
+                    &sign_request.file,
+                    &sig_path,
+                    url,
+                    &get_verification_key_path(&state.store_dir, &sign_request.name),
+                )?)
+                .context("Could not encode signature")?,
+            )
+            .await
+            .context("Failed to report signature to Holochain")?;
+
+        let address: ActionHash = response.decode().context("Invalid response")?;
+        signature_address = Some(address.to_string());
+    }
+
+    json_response(
+        StatusCode::OK,
+        &SignResponse {
+            signature_path: sig_path,
+            signature_address,
+        },
+    )
+}
+
+#[derive(Serialize, Debug)]
+struct KeyInfo {
+    name: String,
+    verification_key: Option<String>,
+    unlocked: bool,
+}
+
+async fn list_keys(state: &DaemonState) -> Result<Response<Full<Bytes>>, ApiError> {
+    let mut keys = state.keys.lock().await;
+
+    let infos = list_key_names(&state.store_dir)?
+        .into_iter()
+        .map(|name| KeyInfo {
+            verification_key: std::fs::read_to_string(get_verification_key_path(
+                &state.store_dir,
+                &name,
+            ))
+            .ok(),
+            unlocked: keys.get(&name).is_some(),
+            name,
+        })
+        .collect::<Vec<_>>();
+
+    json_response(StatusCode::OK, &infos)
+}
+
+/// The names of the signing keys in `store_dir`, sorted by name.
+fn list_key_names(store_dir: &Path) -> anyhow::Result<Vec<String>> {
+    let mut names = std::fs::read_dir(store_dir)
+        .with_context(|| format!("Could not read directory {:?}", store_dir))?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter_map(|name| name.strip_suffix(".key").map(str::to_string))
+        .collect::<Vec<_>>();
+    names.sort();
+
+    Ok(names)
+}
+
+/// Signing keys that have been unlocked with their password, which are forgotten after a timeout.
+struct KeyCache {
+    timeout: Duration,
+    keys: HashMap<String, (SecretKey, Option<PublicKey>, Instant)>,
+}
+
+impl KeyCache {
+    fn new(timeout: Duration) -> Self {
+        KeyCache {
+            timeout,
+            keys: HashMap::new(),
+        }
+    }
+
+    fn unlock(&mut self, name: &str, sk: SecretKey, vk: Option<PublicKey>) {
+        self.keys
+            .insert(name.to_string(), (sk, vk, Instant::now() + self.timeout));
+    }
+
+    /// Get an unlocked key, forgetting it if it has timed out.
+    fn get(&mut self, name: &str) -> Option<(&SecretKey, Option<&PublicKey>)> {
+        if self
+            .keys
+            .get(name)
+            .is_some_and(|(_, _, expires_at)| *expires_at <= Instant::now())
+        {
+            self.keys.remove(name);
+        }
+
+        self.keys.get(name).map(|(sk, vk, _)| (sk, vk.as_ref()))
+    }
+
+    fn lock_all(&mut self) {
+        self.keys.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_forgotten_after_timeout() {
+        let key_pair = minisign::KeyPair::generate_unencrypted_keypair().unwrap();
+
+        let mut keys = KeyCache::new(Duration::from_secs(60));
+        keys.unlock("default", key_pair.sk.clone(), Some(key_pair.pk.clone()));
+        assert!(keys.get("default").is_some());
+        assert!(keys.get("other").is_none());
+
+        keys.lock_all();
+        assert!(keys.get("default").is_none());
+
+        let mut keys = KeyCache::new(Duration::ZERO);
+        keys.unlock("default", key_pair.sk, Some(key_pair.pk));
+        assert!(keys.get("default").is_none());
+    }
+
+    #[test]
+    fn list_keys_in_store() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("release.key"), "").unwrap();
+        std::fs::write(dir.path().join("release.pub"), "").unwrap();
+        std::fs::write(dir.path().join("default.key"), "").unwrap();
+        std::fs::write(dir.path().join("well_known_cache.json"), "").unwrap();
+
+        assert_eq!(
+            vec!["default".to_string(), "release".to_string()],
+            list_key_names(dir.path()).unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_fetch_request() {
+        // The policy is the daemon's, so one from the client is ignored.
+        let request: FetchRequest = serde_json::from_str(
+            r#"{"url": "https://example.com/a", "output": "a", "policy": {"min_signatures": 0}}"#,
+        )
+        .unwrap();
+
+        assert_eq!("https://example.com/a", request.check.url);
+        assert!(!request.check.offline);
+        assert_eq!(PathBuf::from("a"), request.output);
+    }
+
+    #[test]
+    fn compare_tokens() {
+        assert!(tokens_match("abc", "abc"));
+        assert!(!tokens_match("abd", "abc"));
+        assert!(!tokens_match("ab", "abc"));
+        assert!(!tokens_match("", "abc"));
+    }
+}
@@ -1,16 +1,15 @@
 use anyhow::Context;
-use holochain_client::ZomeCallTarget;
 use holochain_types::prelude::ExternIO;
 use rand::Rng;
+use zeroize::Zeroizing;
 
 use checked_types::{DistributeVfKeyRequest, VerificationKeyType};
 
 use crate::cli::DistributeArgs;
 use crate::common::{get_store_dir, get_verification_key_path};
-use crate::hc_client::{get_authenticated_app_agent_client, maybe_handle_holochain_error};
+use crate::hc_client::{get_authenticated_app_agent_client, CredentialScope};
 use crate::interactive::GetPassword;
-use crate::prelude::SignArgs;
-use crate::sign::sign;
+use crate::sign::{default_signature_path, Signer};
 
 const PROOF_WORDS: [&str; 40] = [
     "handle",
@@ -59,14 +58,25 @@ const PROOF_WORDS: [&str; 40] = [
 /// have an existing key or wish to generate it using another tool then you can distribute it
 /// using this operation.
 pub async fn distribute(distribute_args: DistributeArgs) -> anyhow::Result<()> {
+    distribute_with_password(distribute_args, None).await
+}
+
+/// [distribute], with a password that has already been read for the key. Otherwise the password is
+/// found from the arguments, if the key isn't held by an agent.
+pub(crate) async fn distribute_with_password(
+    mut distribute_args: DistributeArgs,
+    password: Option<Zeroizing<String>>,
+) -> anyhow::Result<()> {
     println!("Distributing key: {}", distribute_args.name);
 
-    let admin_port = distribute_args.admin_port().await?;
+    let admin_port = distribute_args.connect.admin_port().await?;
 
     let app_client = get_authenticated_app_agent_client(
         admin_port,
         distribute_args.config_dir.clone(),
         distribute_args.app_id.clone(),
+        distribute_args.connect.timeout(),
+        CredentialScope::Write,
     )
     .await?;
 
@@ -84,25 +94,21 @@ pub async fn distribute(distribute_args: DistributeArgs) -> anyhow::Result<()> {
     std::fs::write(tmp_file.path(), proof.as_bytes())
         .context("Could not write proof to temporary file")?;
 
-    let sig_path = sign(SignArgs {
-        url: None,
-        name: distribute_args.name.clone(),
-        port: Some(admin_port),
-        password: Some(distribute_args.get_password()?),
-        config_dir: distribute_args.config_dir.clone(),
-        file: tmp_file.path().to_path_buf(),
-        output: None,
-        // This is a temporary file for demonstrating private key access, should not be distributed as an asset signature
-        distribute: false,
-        app_id: distribute_args.app_id,
+    // This is a temporary file for demonstrating private key access, it is not distributed as an
+    // asset signature.
+    let sig_path = default_signature_path(tmp_file.path());
+    let name = distribute_args.name.clone();
+    let mut signer = Signer::new(&store_dir, &name, || match password {
+        Some(password) => Ok(password),
+        None => distribute_args.get_password(),
     })
     .await?;
+    signer.sign_file(tmp_file.path(), &sig_path).await?;
 
     app_client
         .call_zome(
-            ZomeCallTarget::RoleName("checked".to_string()),
-            "signing_keys".into(),
-            "distribute_verification_key".into(),
+            "signing_keys",
+            "distribute_verification_key",
             ExternIO::encode(DistributeVfKeyRequest {
                 name: distribute_args.name.clone(),
                 verification_key: std::fs::read_to_string(vk_path)
@@ -115,10 +121,7 @@ pub async fn distribute(distribute_args: DistributeArgs) -> anyhow::Result<()> {
             .unwrap(),
         )
         .await
-        .map_err(|e| {
-            maybe_handle_holochain_error(&e, distribute_args.config_dir);
-            anyhow::anyhow!("Failed to distribute verification key: {:?}", e)
-        })?;
+        .context("Failed to distribute verification key")?;
 
     println!("Successfully distributed on Holochain!");
 
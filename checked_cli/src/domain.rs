@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use holochain_types::prelude::{ActionHash, AgentPubKey, ExternIO};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use checked_types::canonical_url::canonicalize_domain;
+use checked_types::{CreateDomainClaim, DomainClaimResponse, VerificationKeyType};
+
+use crate::cli::{ClaimDomainArgs, VerifyDomainArgs};
+use crate::common::{get_store_dir, get_verification_key_path, unix_timestamp};
+use crate::hc_client::{get_authenticated_app_agent_client, CredentialScope, HolochainClient};
+
+/// Where a domain publishes the keys that it has claimed, relative to the root of the domain.
+const WELL_KNOWN_PATH: &str = ".well-known/checked.json";
+
+/// How long a fetched `.well-known` file is used for before it is fetched again.
+const WELL_KNOWN_CACHE_SECONDS: u64 = 60 * 60 * 24; // 1 day
+
+/// The content of a domain's `.well-known/checked.json` file.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WellKnownChecked {
+    /// The keys that the owner of the domain has claimed.
+    pub keys: Vec<WellKnownCheckedKey>,
+}
+
+/// A key that has been claimed by the owner of a domain.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WellKnownCheckedKey {
+    /// The agent that distributed the key and created the domain claim.
+    pub agent: String,
+    /// The verification key, as it is found in the `.pub` file.
+    pub verification_key: String,
+}
+
+/// A domain claim found on Holochain, and whether the domain agrees with it.
+#[derive(Debug)]
+pub struct VerifiedDomainClaim {
+    /// The agent that created the claim.
+    pub author: AgentPubKey,
+    /// The address of the key that was claimed.
+    pub key_dist_address: ActionHash,
+    /// The type of the key that was claimed.
+    pub key_type: VerificationKeyType,
+    /// The key that was claimed.
+    pub verification_key: String,
+    /// Whether the domain's `.well-known` file lists the key and agent.
+    pub verified: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct WellKnownCache {
+    domains: HashMap<String, CachedWellKnown>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CachedWellKnown {
+    fetched_at: u64,
+    document: WellKnownChecked,
+}
+
+/// Claim that one of your keys belongs to the owner of a domain, and print the `.well-known` file
+/// that must be published on the domain for others to trust the claim.
+pub async fn claim_domain(claim_domain_args: ClaimDomainArgs) -> anyhow::Result<()> {
+    let domain = canonicalize_domain(&claim_domain_args.domain).map_err(|e| anyhow::anyhow!(e))?;
+
+    let store_dir = get_store_dir(claim_domain_args.config_dir.clone())?;
+    let vk_path = get_verification_key_path(&store_dir, &claim_domain_args.name);
+    let verification_key = std::fs::read_to_string(&vk_path).with_context(|| {
+        format!(
+            "Verification key '{}' not found, maybe you need to create it first?",
+            claim_domain_args.name
+        )
+    })?;
+
+    let admin_port = claim_domain_args.connect.admin_port().await?;
+
+    let app_client = get_authenticated_app_agent_client(
+        admin_port,
+        claim_domain_args.config_dir.clone(),
+        claim_domain_args.app_id.clone(),
+        claim_domain_args.connect.timeout(),
+        CredentialScope::Write,
+    )
+    .await?;
+
+    let response = app_client
+        .call_zome(
+            "fetch",
+            "create_domain_claim",
+            ExternIO::encode(CreateDomainClaim {
+                domain: domain.clone(),
+                key_type: VerificationKeyType::MiniSignEd25519,
+                verification_key: verification_key.clone(),
+            })?,
+        )
+        .await
+        .context("Failed to create domain claim")?;
+
+    let domain_claim_address: ActionHash = response.decode()?;
+    println!(
+        "Domain claim stored on Holochain at: {:?}",
+        domain_claim_address
+    );
+
+    let document = WellKnownChecked {
+        keys: vec![WellKnownCheckedKey {
+            agent: app_client.my_pub_key().to_string(),
+            verification_key: verification_key.trim().to_string(),
+        }],
+    };
+
+    println!(
+        "\nTo complete the claim, publish the following at {}. If the file already exists, add the key to it.\n",
+        make_well_known_url(&domain)?
+    );
+    println!("{}", serde_json::to_string_pretty(&document)?);
+
+    Ok(())
+}
+
+/// Check the claims that have been made for a domain against the `.well-known` file that it serves.
+pub async fn verify_domain(
+    verify_domain_args: VerifyDomainArgs,
+) -> anyhow::Result<Vec<VerifiedDomainClaim>> {
+    let domain = canonicalize_domain(&verify_domain_args.domain).map_err(|e| anyhow::anyhow!(e))?;
+
+    let admin_port = verify_domain_args.connect.admin_port().await?;
+
+    let app_client = get_authenticated_app_agent_client(
+        admin_port,
+        verify_domain_args.config_dir.clone(),
+        verify_domain_args.app_id.clone(),
+        verify_domain_args.connect.timeout(),
+        CredentialScope::Read,
+    )
+    .await?;
+
+    let verified_claims = verify_domain_claims(
+        &app_client,
+        &domain,
+        verify_domain_args.config_dir.clone(),
+        verify_domain_args.well_known_url.clone(),
+        verify_domain_args.refresh,
+    )
+    .await?;
+
+    if verified_claims.is_empty() {
+        println!("No keys have been claimed for {}", domain);
+    }
+
+    for claim in &verified_claims {
+        println!(
+            "Key {:?} from author {:?}: {}",
+            claim.key_dist_address,
+            claim.author,
+            if claim.verified { "✅" } else { "❌" }
+        );
+    }
+
+    Ok(verified_claims)
+}
+
+/// Find the claims that have been made for `domain` on Holochain and check each of them against the
+/// domain's `.well-known` file.
+///
+/// The `.well-known` file is cached in the config directory for [WELL_KNOWN_CACHE_SECONDS], unless
+/// `refresh` is set. It is fetched from `well_known_url` if provided, which is intended for testing.
+pub(crate) async fn verify_domain_claims(
+    app_client: &HolochainClient,
+    domain: &str,
+    config_dir: Option<PathBuf>,
+    well_known_url: Option<Url>,
+    refresh: bool,
+) -> anyhow::Result<Vec<VerifiedDomainClaim>> {
+    let response = app_client
+        .call_zome(
+            "fetch",
+            "get_domain_claims",
+            ExternIO::encode(domain.to_string())?,
+        )
+        .await
+        .context("Failed to get domain claims")?;
+
+    let claims: Vec<DomainClaimResponse> = response.decode()?;
+    if claims.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let well_known_url = match well_known_url {
+        Some(url) => url,
+        None => make_well_known_url(domain)?,
+    };
+    let document =
+        get_well_known(&get_store_dir(config_dir)?, domain, well_known_url, refresh).await?;
+
+    Ok(claims
+        .into_iter()
+        .map(|claim| {
+            let verified = document.keys.iter().any(|key| {
+                key.agent == claim.author.to_string()
+                    && key_line(&key.verification_key) == key_line(&claim.verification_key)
+            });
+
+            VerifiedDomainClaim {
+                author: claim.author,
+                key_dist_address: claim.key_dist_address,
+                key_type: claim.key_type,
+                verification_key: claim.verification_key,
+                verified,
+            }
+        })
+        .collect())
+}
+
+fn make_well_known_url(domain: &str) -> anyhow::Result<Url> {
+    Ok(Url::parse(&format!(
+        "https://{}/{}",
+        domain, WELL_KNOWN_PATH
+    ))?)
+}
+
+async fn get_well_known(
+    store_dir: &Path,
+    domain: &str,
+    well_known_url: Url,
+    refresh: bool,
+) -> anyhow::Result<WellKnownChecked> {
+    let cache_path = store_dir.join("well_known_cache.json");
+
+    let mut cache: WellKnownCache = match std::fs::read_to_string(&cache_path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+        Err(_) => WellKnownCache::default(),
+    };
+
+    let now = unix_timestamp();
+    if !refresh {
+        if let Some(cached) = cache.domains.get(domain) {
+            if now.saturating_sub(cached.fetched_at) < WELL_KNOWN_CACHE_SECONDS {
+                return Ok(cached.document.clone());
+            }
+        }
+    }
+
+    println!("Fetching {}", well_known_url);
+    // The file has to be served by the domain itself, a redirect could hand the claim to another.
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?;
+    let response = client.get(well_known_url.clone()).send().await?;
+    if response.status().is_redirection() {
+        anyhow::bail!(
+            "{} redirects, it must be served directly by {}",
+            well_known_url,
+            domain
+        );
+    }
+    if !response.status().is_success() {
+        anyhow::bail!(
+            "Failed to fetch {}: {:?}",
+            well_known_url,
+            response.status()
+        );
+    }
+
+    let document: WellKnownChecked = serde_json::from_str(&response.text().await?)
+        .with_context(|| format!("Invalid content at {}", well_known_url))?;
+
+    cache.domains.insert(
+        domain.to_string(),
+        CachedWellKnown {
+            fetched_at: now,
+            document: document.clone(),
+        },
+    );
+    if let Err(e) = write_cache(&cache_path, &cache) {
+        println!("Could not cache {}: {:?}", well_known_url, e);
+    }
+
+    Ok(document)
+}
+
+/// Replace the cache in one step, so that a concurrent reader never sees a partly written file.
+fn write_cache(cache_path: &Path, cache: &WellKnownCache) -> anyhow::Result<()> {
+    let dir = cache_path
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("Invalid cache path {:?}", cache_path))?;
+    let mut tmp_file = tempfile::NamedTempFile::new_in(dir)?;
+    tmp_file.write_all(serde_json::to_string(cache)?.as_bytes())?;
+    tmp_file.persist(cache_path)?;
+
+    Ok(())
+}
+
+/// The line of a MiniSign public key that holds the key, ignoring the untrusted comment.
+fn key_line(verification_key: &str) -> &str {
+    verification_key
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .last()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn well_known_redirects_are_refused() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0; 1024];
+            assert!(stream.read(&mut request).await.unwrap() > 0);
+            stream
+                .write_all(b"HTTP/1.1 302 Found\r\nLocation: https://example.org/.well-known/checked.json\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let well_known_url = Url::parse(&format!("http://{}/{}", addr, WELL_KNOWN_PATH)).unwrap();
+        let err = get_well_known(dir.path(), "example.com", well_known_url, true)
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("redirects"), "{:?}", err);
+    }
+
+    #[test]
+    fn write_cache_replaces_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join("well_known_cache.json");
+        std::fs::write(&cache_path, "corrupt").unwrap();
+
+        write_cache(&cache_path, &WellKnownCache::default()).unwrap();
+
+        let cache: WellKnownCache =
+            serde_json::from_str(&std::fs::read_to_string(&cache_path).unwrap()).unwrap();
+        assert!(cache.domains.is_empty());
+        assert_eq!(1, std::fs::read_dir(dir.path()).unwrap().count());
+    }
+
+    #[test]
+    fn key_line_ignores_comment_and_whitespace() {
+        let key = "RWTxWagDsFvtt+e4V5KJAahwTh381E6PMTFvgvGYsnWLXCwIxe4YE/sM";
+
+        assert_eq!(key, key_line(key));
+        assert_eq!(
+            key,
+            key_line(&format!(
+                "untrusted comment: minisign public key: B7ED5BB003A859F1\n{}\n\n",
+                key
+            ))
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::fs::File;
 use std::io::{BufReader, BufWriter, Seek, SeekFrom, Write};
 use std::path::PathBuf;
@@ -5,7 +6,6 @@ use std::sync::atomic::AtomicUsize;
 use std::sync::Arc;
 
 use anyhow::Context;
-use holochain_client::ZomeCallTarget;
 use holochain_types::prelude::{ActionHash, AgentPubKey, ExternIO};
 use indicatif::{ProgressFinish, ProgressStyle};
 use itertools::Itertools;
@@ -13,16 +13,20 @@ use minisign::PublicKeyBox;
 use tempfile::NamedTempFile;
 use url::Url;
 
+use checked_types::canonical_url::{canonicalize_domain, canonicalize_url};
 use checked_types::{
     FetchCheckSignature, FetchCheckSignatureReason, PrepareFetchRequest, VerificationKeyType,
 };
 
-use crate::cli::FetchArgs;
-use crate::hc_client;
-use crate::hc_client::maybe_handle_holochain_error;
-use crate::interactive::GetPassword;
+use crate::checksums::{check_checksums, ChecksumsReport};
+use crate::cli::{ConnectArgs, FetchArgs};
+use crate::domain::{verify_domain_claims, VerifiedDomainClaim};
+use crate::hc_client::{self, CredentialScope, HolochainClient};
 use crate::prelude::SignArgs;
-use crate::sign::sign;
+use crate::sidecar::{
+    check_sidecar, get_sidecar_keys, make_sidecar_url, SidecarKeySource, SidecarReport,
+};
+use crate::sign::sign_with_client;
 
 /// Information about the result of fetching an asset.
 #[derive(Debug)]
@@ -36,10 +40,19 @@ pub struct FetchInfo {
     /// the user decided to sign the asset and the fetch process made it far enough to create the
     /// signature.
     pub signature_path: Option<PathBuf>,
+    /// Whether the signatures were found using only data that was already held by the local
+    /// Holochain node. See [FetchArgs::offline].
+    pub local_only: bool,
+    /// The result of checking the signature published next to the asset. This is only present if
+    /// it was requested with [FetchArgs::check_sidecar] and the check could be completed.
+    pub sidecar_report: Option<SidecarReport>,
+    /// The result of checking the asset against a signed checksums manifest. This is only present
+    /// if a manifest was provided with [FetchArgs::checksums].
+    pub checksums_report: Option<ChecksumsReport>,
 }
 
-#[derive(Debug)]
-struct FetchState {
+#[derive(Debug, Default)]
+pub(crate) struct FetchState {
     asset_size: AtomicUsize,
     downloaded_size: AtomicUsize,
 }
@@ -57,41 +70,49 @@ struct FetchState {
 /// If the decides to reject the asset then the temporary file is deleted and the process ends.
 ///
 /// Otherwise, the file is moved to the output location and the user is prompted to sign the asset.
-/// Unlike with [sign] where the user is is prompted about whether to distribute the signature, here
+/// Unlike with [sign](crate::sign::sign) where the user is is prompted about whether to distribute the signature, here
 /// the signature is always distributed after being created.
 pub async fn fetch(fetch_args: FetchArgs) -> anyhow::Result<FetchInfo> {
-    let fetch_url = Url::parse(&fetch_args.url).context("Invalid URL")?;
+    let url = fetch_args
+        .url
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("A URL to fetch is required"))?;
+    let fetch_url = Url::parse(&url).context("Invalid URL")?;
     println!("Fetching from {}", fetch_url);
 
+    // Signatures are stored against the canonical form of the URL.
+    let canonical_fetch_url = canonicalize_url(&url).context("Invalid URL")?;
+    if canonical_fetch_url != fetch_url {
+        println!("Looking up signatures for {}", canonical_fetch_url);
+    }
+
     let output_path = get_output_path(&fetch_args, &fetch_url)?;
 
-    let admin_port = fetch_args.admin_port().await?;
+    let admin_port = fetch_args.connect.admin_port().await?;
 
     let app_client = hc_client::get_authenticated_app_agent_client(
         admin_port,
         fetch_args.config_dir.clone(),
         fetch_args.app_id.clone(),
+        fetch_args.connect.timeout(),
+        CredentialScope::Read,
     )
     .await?;
 
-    // TODO if this fails because the credentials are no longer valid then we need a recovery mechanism that isn't `rm ~/.checked/credentials.json`
-    let response = app_client
-        .call_zome(
-            ZomeCallTarget::RoleName("checked".to_string()),
-            "fetch".into(),
-            "prepare_fetch".into(),
-            ExternIO::encode(PrepareFetchRequest {
-                fetch_url: fetch_args.url.clone(),
-            })
-            .unwrap(),
-        )
-        .await
-        .map_err(|e| {
-            maybe_handle_holochain_error(&e, fetch_args.config_dir.clone());
-            anyhow::anyhow!("Failed to get signatures for the asset: {:?}", e)
-        })?;
+    let response = get_signatures_to_check(
+        &app_client,
+        &canonical_fetch_url,
+        fetch_args.offline,
+        fetch_args.include_aliases,
+    )
+    .await?;
 
-    let response: Vec<FetchCheckSignature> = response.decode()?;
+    let local_only = fetch_args.offline;
+    if local_only {
+        println!(
+            "Offline mode: only signatures already held by your Holochain node will be checked"
+        );
+    }
 
     if response.is_empty() {
         println!("No signatures found for this asset. This is normal but please consider asking the author to create a signature!");
@@ -102,12 +123,26 @@ pub async fn fetch(fetch_args: FetchArgs) -> anyhow::Result<FetchInfo> {
                 output_path: None,
                 signature_path: None,
                 reports: vec![],
+                local_only,
+                sidecar_report: None,
+                checksums_report: None,
             });
         }
     } else {
         println!("Found {} signatures to check against", response.len());
     }
 
+    // Verifying the claims relies on the network, so it is skipped in offline mode.
+    let domain = asset_domain(&canonical_fetch_url);
+    let want_domain_claims = !response.is_empty() || fetch_args.check_sidecar;
+    let domain_claims = match (local_only, want_domain_claims, &domain) {
+        (false, true, Some(domain)) => {
+            get_verified_domain_claims(&app_client, domain, fetch_args.config_dir.clone()).await
+        }
+        _ => Vec::new(),
+    };
+    let domain_verified_keys = domain_verified_keys(&domain_claims);
+
     let has_mine_signature = response
         .iter()
         .any(|s| s.reason == FetchCheckSignatureReason::Mine);
@@ -167,10 +202,69 @@ pub async fn fetch(fetch_args: FetchArgs) -> anyhow::Result<FetchInfo> {
 
     println!("Downloaded to {:?}", path);
 
+    // Published signatures and checksums manifests are checked with the same trusted keys.
+    let sidecar_keys = if fetch_args.check_sidecar || fetch_args.checksums.is_some() {
+        get_sidecar_keys(&app_client, local_only, &response, &domain_claims).await?
+    } else {
+        vec![]
+    };
+
+    // Checking the published signature is optional, so a failure is reported but doesn't stop the
+    // fetch.
+    let sidecar_report = if fetch_args.check_sidecar {
+        let sidecar_report = async {
+            let sidecar_url = make_sidecar_url(&fetch_args.sidecar_pattern, &fetch_url)?;
+            check_sidecar(sidecar_url, &path, &sidecar_keys).await
+        }
+        .await;
+
+        match sidecar_report {
+            Ok(sidecar_report) => Some(sidecar_report),
+            Err(e) => {
+                println!("Could not check the published signature: {:?}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // The user asked for the asset to be checked against the manifest, so it isn't safe to continue
+    // if that can't be done.
+    let checksums_report = match &fetch_args.checksums {
+        Some(manifest_url) => {
+            let manifest_url = Url::parse(manifest_url).context("Invalid checksums URL")?;
+            Some(
+                check_checksums(
+                    manifest_url,
+                    &fetch_args.sidecar_pattern,
+                    &fetch_url,
+                    &path,
+                    &sidecar_keys,
+                )
+                .await
+                .context("Could not check the checksums manifest")?,
+            )
+        }
+        None => None,
+    };
+
     // No point running the check and report if there are no signatures
-    let reports = if !response.is_empty() {
-        let reports = check_signatures(path.clone(), response)?;
-        show_report(&reports);
+    let reports = if !response.is_empty() || sidecar_report.is_some() || checksums_report.is_some()
+    {
+        let reports = check_signatures(path.clone(), response, &domain_verified_keys)?;
+        show_report(
+            &reports,
+            local_only,
+            domain.as_deref(),
+            sidecar_report.as_ref(),
+            checksums_report.as_ref(),
+        );
+
+        // Approving can't override a manifest that contradicts the asset.
+        if let Some(checksums_report) = &checksums_report {
+            checksums_report.ensure_passed()?;
+        }
 
         if !fetch_args.approve_signatures_report()? {
             println!("Discarding temporary asset...");
@@ -181,6 +275,9 @@ pub async fn fetch(fetch_args: FetchArgs) -> anyhow::Result<FetchInfo> {
                 output_path: None,
                 signature_path: None,
                 reports,
+                local_only,
+                sidecar_report,
+                checksums_report,
             });
         }
 
@@ -197,20 +294,33 @@ pub async fn fetch(fetch_args: FetchArgs) -> anyhow::Result<FetchInfo> {
             output_path: Some(output_path),
             signature_path: None,
             reports,
+            local_only,
+            sidecar_report,
+            checksums_report,
         });
     }
 
-    let signature_path = sign(SignArgs {
-        url: Some(fetch_args.url.clone()),
-        name: fetch_args.name.clone(),
-        port: Some(admin_port),
-        password: Some(fetch_args.get_password()?),
-        config_dir: fetch_args.config_dir.clone(),
-        file: output_path.clone(),
-        output: None,
-        distribute: true,
-        app_id: fetch_args.app_id,
-    })
+    // Distributed with the same client, which asks to authorize write access.
+    let signature_path = sign_with_client(
+        SignArgs {
+            url: Some(url),
+            name: fetch_args.name.clone(),
+            connect: ConnectArgs {
+                port: Some(admin_port),
+                ..fetch_args.connect
+            },
+            // Let `sign` try the agent before prompting.
+            password: fetch_args.password,
+            password_source: fetch_args.password_source,
+            config_dir: fetch_args.config_dir.clone(),
+            file: output_path.clone(),
+            output: None,
+            overwrite_manifest: false,
+            distribute: true,
+            app_id: fetch_args.app_id,
+        },
+        Some(app_client),
+    )
     .await?;
 
     println!("Created signature!");
@@ -219,13 +329,47 @@ pub async fn fetch(fetch_args: FetchArgs) -> anyhow::Result<FetchInfo> {
         output_path: Some(output_path),
         signature_path: Some(signature_path),
         reports,
+        local_only,
+        sidecar_report,
+        checksums_report,
     })
 }
 
+/// Ask Holochain for the signatures that should be checked for an asset at `canonical_fetch_url`.
+pub(crate) async fn get_signatures_to_check(
+    app_client: &HolochainClient,
+    canonical_fetch_url: &Url,
+    offline: bool,
+    include_aliases: bool,
+) -> anyhow::Result<Vec<FetchCheckSignature>> {
+    let response = app_client
+        .call_zome(
+            "fetch",
+            if offline {
+                "prepare_fetch_local"
+            } else {
+                "prepare_fetch"
+            },
+            ExternIO::encode(PrepareFetchRequest {
+                fetch_url: canonical_fetch_url.to_string(),
+                include_aliases,
+            })?,
+        )
+        .await
+        .context("Failed to get signatures for the asset")?;
+
+    Ok(response.decode()?)
+}
+
 #[derive(Debug)]
 pub struct CheckedSignature {
     pub key_dist_address: ActionHash,
     pub author: AgentPubKey,
+    /// The URL that the signature was made for, if it was found through an alias.
+    pub alias_url: Option<String>,
+    /// Whether the key that made the signature has been verified as belonging to the owner of the
+    /// domain that the asset was fetched from.
+    pub domain_verified: bool,
 }
 
 #[derive(Debug)]
@@ -235,9 +379,54 @@ pub struct SignatureCheckReport {
     pub failed_signatures: Vec<CheckedSignature>,
 }
 
-fn check_signatures(
+impl SignatureCheckReport {
+    /// Whether these are publisher signatures that all passed, at least one of which was made with
+    /// a key that is verified for the domain. A publisher claim on its own can be made by anyone.
+    pub fn has_verified_publisher(&self) -> bool {
+        self.reason == FetchCheckSignatureReason::Publisher
+            && self.failed_signatures.is_empty()
+            && self.passed_signatures.iter().any(|s| s.domain_verified)
+    }
+}
+
+/// The domain that the asset at `canonical_fetch_url` is fetched from, if it has one.
+pub(crate) fn asset_domain(canonical_fetch_url: &Url) -> Option<String> {
+    canonical_fetch_url
+        .host_str()
+        .and_then(|host| canonicalize_domain(host).ok())
+}
+
+/// Check whether any keys have been claimed by the owner of `domain`.
+///
+/// A failure here should not stop a fetch, it just means that no signatures can be shown as
+/// verified, so it is reported and no claims are returned.
+pub(crate) async fn get_verified_domain_claims(
+    app_client: &HolochainClient,
+    domain: &str,
+    config_dir: Option<PathBuf>,
+) -> Vec<VerifiedDomainClaim> {
+    match verify_domain_claims(app_client, domain, config_dir, None, false).await {
+        Ok(claims) => claims,
+        Err(e) => {
+            println!("Could not verify the keys claimed for {}: {:?}", domain, e);
+            Vec::new()
+        }
+    }
+}
+
+/// The keys from `domain_claims` that are verified for the domain.
+pub(crate) fn domain_verified_keys(domain_claims: &[VerifiedDomainClaim]) -> HashSet<ActionHash> {
+    domain_claims
+        .iter()
+        .filter(|c| c.verified)
+        .map(|c| c.key_dist_address.clone())
+        .collect()
+}
+
+pub(crate) fn check_signatures(
     check_file: PathBuf,
     signatures: Vec<FetchCheckSignature>,
+    domain_verified_keys: &HashSet<ActionHash>,
 ) -> anyhow::Result<Vec<SignatureCheckReport>> {
     let check_file = File::options().read(true).open(check_file)?;
     let mut check_file_reader = BufReader::new(check_file);
@@ -252,21 +441,38 @@ fn check_signatures(
             failed_signatures: vec![],
         };
         for sig in sigs {
-            println!("Checking signature from {:?}... ", sig.author);
-            match check_one_signature(&mut check_file_reader, sig) {
+            match &sig.alias_url {
+                Some(alias_url) => println!(
+                    "Checking signature from {:?} (via alias {})... ",
+                    sig.author, alias_url
+                ),
+                None => println!("Checking signature from {:?}... ", sig.author),
+            }
+            match check_one_signature(
+                &mut check_file_reader,
+                &sig.key_type,
+                &sig.verification_key,
+                &sig.signature,
+            ) {
                 Ok(true) => group_report.passed_signatures.push(CheckedSignature {
                     key_dist_address: sig.key_dist_address.clone(),
                     author: sig.author.clone(),
+                    alias_url: sig.alias_url.clone(),
+                    domain_verified: domain_verified_keys.contains(&sig.key_dist_address),
                 }),
                 Ok(false) => group_report.failed_signatures.push(CheckedSignature {
                     key_dist_address: sig.key_dist_address.clone(),
                     author: sig.author.clone(),
+                    alias_url: sig.alias_url.clone(),
+                    domain_verified: domain_verified_keys.contains(&sig.key_dist_address),
                 }),
                 Err(e) => {
                     println!("Error during verification: {:?}", e);
                     group_report.failed_signatures.push(CheckedSignature {
                         key_dist_address: sig.key_dist_address.clone(),
                         author: sig.author.clone(),
+                        alias_url: sig.alias_url.clone(),
+                        domain_verified: domain_verified_keys.contains(&sig.key_dist_address),
                     })
                 }
             }
@@ -278,14 +484,17 @@ fn check_signatures(
     Ok(signature_reports)
 }
 
-fn check_one_signature(
+/// Verify `signature`, made with `verification_key`, against the content of `check_file_reader`.
+pub(crate) fn check_one_signature(
     check_file_reader: &mut BufReader<File>,
-    sig: &FetchCheckSignature,
+    key_type: &VerificationKeyType,
+    verification_key: &str,
+    signature: &str,
 ) -> anyhow::Result<bool> {
-    match sig.key_type {
+    match key_type {
         VerificationKeyType::MiniSignEd25519 => {
-            let vf_key = PublicKeyBox::from_string(&sig.verification_key)?;
-            let sig = minisign::SignatureBox::from_string(&sig.signature)?;
+            let vf_key = PublicKeyBox::from_string(verification_key)?;
+            let sig = minisign::SignatureBox::from_string(signature)?;
 
             match minisign::verify(&vf_key.into(), &sig, check_file_reader, true, false, false) {
                 Ok(()) => Ok(true),
@@ -295,7 +504,17 @@ fn check_one_signature(
     }
 }
 
-fn show_report(report: &[SignatureCheckReport]) {
+fn show_report(
+    report: &[SignatureCheckReport],
+    local_only: bool,
+    domain: Option<&str>,
+    sidecar_report: Option<&SidecarReport>,
+    checksums_report: Option<&ChecksumsReport>,
+) {
+    if local_only {
+        println!("\n⚠️ LOCAL-ONLY REPORT: this report was produced from data already held by your Holochain node. Recent signatures and key compromise marks from the network may be missing.");
+    }
+
     println!("\nLooking for existing signature:");
     let maybe_mine_report = report
         .iter()
@@ -313,6 +532,146 @@ fn show_report(report: &[SignatureCheckReport]) {
         println!("No signature from you was found.");
     }
 
+    println!("\nLooking for signatures from the publisher:");
+    let maybe_publisher_report = report
+        .iter()
+        .find(|r| r.reason == FetchCheckSignatureReason::Publisher);
+    if let Some(publisher_report) = maybe_publisher_report {
+        let claim = |checked_sig: &CheckedSignature| {
+            if checked_sig.domain_verified {
+                "verified for the domain"
+            } else {
+                "unverified self-claim"
+            }
+        };
+        for checked_sig in &publisher_report.passed_signatures {
+            println!(
+                "Signature from publisher {:?} with key {:?} ({}): ✅",
+                checked_sig.author,
+                checked_sig.key_dist_address,
+                claim(checked_sig)
+            );
+        }
+        for checked_sig in &publisher_report.failed_signatures {
+            println!(
+                "Signature from publisher {:?} with key {:?} ({}): ❌",
+                checked_sig.author,
+                checked_sig.key_dist_address,
+                claim(checked_sig)
+            );
+        }
+
+        if !publisher_report.failed_signatures.is_empty() {
+            println!("A publisher's signature failed verification. This means that the asset you have fetched is not the one that the publisher says they published. You should not use it unless you can confirm with the publisher.");
+        } else if publisher_report.has_verified_publisher() {
+            println!("The publisher's signature passed verification. This means that you have the asset that the publisher says they published.");
+        } else {
+            println!("These keys claimed to publish this URL, but none of them are verified for the domain. Anyone can make that claim, so treat these like signatures from any other user.");
+        }
+    } else {
+        println!("No signatures from a publisher were found.");
+    }
+
+    if let Some(domain) = domain {
+        let domain_signatures = report
+            .iter()
+            .flat_map(|r| {
+                r.passed_signatures
+                    .iter()
+                    .map(|s| (s, true))
+                    .chain(r.failed_signatures.iter().map(|s| (s, false)))
+            })
+            .filter(|(s, _)| s.domain_verified)
+            .collect::<Vec<_>>();
+
+        if !domain_signatures.is_empty() {
+            println!("\nSignatures from keys verified for {}:", domain);
+            for (checked_sig, passed) in domain_signatures {
+                println!(
+                    "Signature from {:?} with key {:?}: {}",
+                    checked_sig.author,
+                    checked_sig.key_dist_address,
+                    if passed { "✅" } else { "❌" }
+                );
+            }
+        }
+    }
+
+    if let Some(sidecar_report) = sidecar_report {
+        println!(
+            "\nLooking for a signature published at {}:",
+            sidecar_report.sidecar_url
+        );
+        if !sidecar_report.found {
+            println!("No signature was published next to the asset.");
+        } else if sidecar_report.passed_keys.is_empty() && sidecar_report.failed_keys.is_empty() {
+            println!("The published signature was not made by any key that you trust, so it could not be checked.");
+        } else {
+            for key in &sidecar_report.passed_keys {
+                println!(
+                    "Published signature from {:?} with key {:?} ({}): ✅",
+                    key.author,
+                    key.key_dist_address,
+                    describe_sidecar_key_source(&key.source)
+                );
+            }
+            for key in &sidecar_report.failed_keys {
+                println!(
+                    "Published signature from {:?} with key {:?} ({}): ❌",
+                    key.author,
+                    key.key_dist_address,
+                    describe_sidecar_key_source(&key.source)
+                );
+            }
+            if !sidecar_report.failed_keys.is_empty() {
+                println!("The published signature failed verification. This means that the asset you have fetched is not the one that was signed next to it.");
+            }
+        }
+    }
+
+    if let Some(checksums_report) = checksums_report {
+        println!(
+            "\nChecking the checksums manifest at {}:",
+            checksums_report.manifest_url
+        );
+        if checksums_report.passed_keys.is_empty() && checksums_report.failed_keys.is_empty() {
+            println!(
+                "The signature at {} was not made by any key that you trust.",
+                checksums_report.signature_url
+            );
+        }
+        for key in &checksums_report.passed_keys {
+            println!(
+                "Manifest signature from {:?} with key {:?} ({}): ✅",
+                key.author,
+                key.key_dist_address,
+                describe_sidecar_key_source(&key.source)
+            );
+        }
+        for key in &checksums_report.failed_keys {
+            println!(
+                "Manifest signature from {:?} with key {:?} ({}): ❌",
+                key.author,
+                key.key_dist_address,
+                describe_sidecar_key_source(&key.source)
+            );
+        }
+        match &checksums_report.listed_sha256 {
+            Some(listed) if listed == &checksums_report.asset_sha256 => {
+                println!("The asset's SHA-256 digest matches the manifest: ✅")
+            }
+            Some(_) => println!("The asset's SHA-256 digest does not match the manifest: ❌"),
+            None => println!("The asset is not listed in the manifest: ❌"),
+        }
+        if checksums_report.passed() {
+            println!(
+                "The asset is listed in a checksums manifest that was signed by a key you trust."
+            );
+        } else {
+            println!("The asset could not be confirmed by the checksums manifest. You should not use it unless you can confirm it with the publisher.");
+        }
+    }
+
     println!("\nLooking for historical signatures:");
     let maybe_historical_report = report
         .iter()
@@ -384,9 +743,47 @@ fn show_report(report: &[SignatureCheckReport]) {
         println!("No recent signatures were found.");
     }
 
+    let aliased_signatures = report
+        .iter()
+        .flat_map(|r| {
+            r.passed_signatures
+                .iter()
+                .map(|s| (s, true))
+                .chain(r.failed_signatures.iter().map(|s| (s, false)))
+        })
+        .filter_map(|(s, passed)| s.alias_url.as_ref().map(|url| (url, passed)))
+        .collect::<Vec<_>>();
+    if !aliased_signatures.is_empty() {
+        println!("\nSignatures found through aliases:");
+        for (alias_url, sigs) in aliased_signatures
+            .iter()
+            .sorted_by_key(|(url, _)| *url)
+            .chunk_by(|(url, _)| *url)
+            .into_iter()
+        {
+            let sigs = sigs.collect::<Vec<_>>();
+            let passed = sigs.iter().filter(|(_, passed)| *passed).count();
+            println!(
+                "{}/{} signatures made for {} passed verification.",
+                passed,
+                sigs.len(),
+                alias_url
+            );
+        }
+        println!("Aliased URLs have been asserted to serve the same asset by a signer. Signatures for them are included in the counts above.");
+    }
+
     println!();
 }
 
+fn describe_sidecar_key_source(source: &SidecarKeySource) -> String {
+    match source {
+        SidecarKeySource::KeyCollection(name) => format!("from your key collection '{}'", name),
+        SidecarKeySource::Publisher => "the publisher's key".to_string(),
+        SidecarKeySource::Domain => "verified for the domain".to_string(),
+    }
+}
+
 fn get_output_path(fetch_args: &FetchArgs, fetch_url: &Url) -> anyhow::Result<PathBuf> {
     let guessed_file_name = fetch_url
         .path_segments()
@@ -419,7 +816,7 @@ fn get_output_path(fetch_args: &FetchArgs, fetch_url: &Url) -> anyhow::Result<Pa
 }
 
 /// Download from `fetch_url` into `writer` and update `state` with the download progress.
-async fn run_download<W>(
+pub(crate) async fn run_download<W>(
     fetch_url: Url,
     writer: &mut BufWriter<W>,
     state: Arc<FetchState>,
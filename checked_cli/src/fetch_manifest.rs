@@ -0,0 +1,597 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::BufWriter;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use url::Url;
+
+use checked_types::canonical_url::canonicalize_url;
+
+use crate::cli::FetchArgs;
+use crate::common::sha256_file;
+use crate::fetch::{
+    asset_domain, check_signatures, domain_verified_keys, get_signatures_to_check,
+    get_verified_domain_claims, run_download, FetchState, SignatureCheckReport,
+};
+use crate::hc_client::{self, CredentialScope, HolochainClient};
+
+/// A list of assets to fetch together, read from a TOML file.
+///
+/// ```toml
+/// concurrency = 4
+///
+/// [policy]
+/// min_signatures = 1
+///
+/// [[asset]]
+/// url = "https://example.com/releases/v1.2.3/tool.tar.gz"
+/// output = "vendor/tool.tar.gz"
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AssetManifest {
+    /// How many assets to fetch at the same time.
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    /// The rules that every asset's signatures must pass.
+    #[serde(default)]
+    pub policy: FetchPolicy,
+    /// The assets to fetch.
+    #[serde(default, rename = "asset")]
+    pub assets: Vec<ManifestAsset>,
+}
+
+fn default_concurrency() -> usize {
+    4
+}
+
+/// An asset listed in an [AssetManifest].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ManifestAsset {
+    /// The URL to fetch the asset from.
+    pub url: String,
+    /// Where to save the asset, relative to the manifest. It must stay inside the manifest's
+    /// directory.
+    pub output: PathBuf,
+}
+
+/// Rules for deciding whether to accept an asset without prompting.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, clap::Args)]
+#[serde(default)]
+pub struct FetchPolicy {
+    /// The minimum number of signatures that must pass.
+    #[arg(long, default_value_t = 1)]
+    pub min_signatures: usize,
+    /// The maximum number of signatures that may fail.
+    #[arg(long, default_value_t = 0)]
+    pub max_failed_signatures: usize,
+    /// Accept assets that nobody has signed yet.
+    #[arg(long, default_value_t = false)]
+    pub allow_no_signatures: bool,
+    /// Require a passing signature from a publisher of the asset's URL, whose key is verified for
+    /// the asset's domain.
+    #[arg(long, default_value_t = false)]
+    pub require_publisher: bool,
+}
+
+impl Default for FetchPolicy {
+    fn default() -> Self {
+        Self {
+            min_signatures: 1,
+            max_failed_signatures: 0,
+            allow_no_signatures: false,
+            require_publisher: false,
+        }
+    }
+}
+
+impl FetchPolicy {
+    /// Check the signature reports for an asset against this policy, giving the reason that the
+    /// asset should be rejected, if it should be.
+    pub(crate) fn check(&self, reports: &[SignatureCheckReport]) -> Result<(), String> {
+        let passed = reports
+            .iter()
+            .map(|r| r.passed_signatures.len())
+            .sum::<usize>();
+        let failed = reports
+            .iter()
+            .map(|r| r.failed_signatures.len())
+            .sum::<usize>();
+
+        if passed == 0 && failed == 0 {
+            return if self.allow_no_signatures && !self.require_publisher {
+                Ok(())
+            } else {
+                Err("No signatures were found".to_string())
+            };
+        }
+
+        if failed > self.max_failed_signatures {
+            return Err(format!(
+                "{} signatures failed, at most {} are allowed",
+                failed, self.max_failed_signatures
+            ));
+        }
+
+        if passed < self.min_signatures {
+            return Err(format!(
+                "{} signatures passed, at least {} are required",
+                passed, self.min_signatures
+            ));
+        }
+
+        if self.require_publisher && !reports.iter().any(|r| r.has_verified_publisher()) {
+            return Err(
+                "No passing signature from a publisher verified for the domain was found"
+                    .to_string(),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// The lock file written by [fetch_manifest], recording what was accepted for each URL.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct AssetLock {
+    /// The accepted assets.
+    #[serde(default, rename = "asset")]
+    pub assets: Vec<LockedAsset>,
+}
+
+/// An asset recorded in an [AssetLock].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LockedAsset {
+    /// The URL that the asset was fetched from.
+    pub url: String,
+    /// The hex encoded SHA-256 digest of the asset.
+    pub sha256: String,
+    /// The signatures that passed when the asset was accepted.
+    #[serde(default)]
+    pub passed_signatures: Vec<LockedSignature>,
+}
+
+/// A signature recorded in an [AssetLock].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LockedSignature {
+    /// The agent that created the signature.
+    pub author: String,
+    /// The address of the key that made the signature.
+    pub key_dist_address: String,
+}
+
+/// Information about the result of fetching the assets in a manifest.
+#[derive(Debug)]
+pub struct FetchManifestInfo {
+    /// Where the lock file was written.
+    pub lock_path: PathBuf,
+    /// The lock that was written.
+    pub lock: AssetLock,
+}
+
+/// Fetch every asset in the manifest given by [FetchArgs::manifest].
+///
+/// Assets are downloaded concurrently over a single Holochain connection and checked against the
+/// manifest's [FetchPolicy] without prompting. Assets are only moved to their outputs if every
+/// asset is accepted, and only then is the lock file written.
+///
+/// The lock file is the manifest with a `.lock` extension. If an asset's digest differs from the
+/// one recorded in an existing lock file then the fetch fails, even if its signatures pass.
+pub async fn fetch_manifest(fetch_args: FetchArgs) -> anyhow::Result<FetchManifestInfo> {
+    let manifest_path = fetch_args
+        .manifest
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("A manifest is required"))?;
+    let manifest: AssetManifest = toml::from_str(
+        &std::fs::read_to_string(&manifest_path)
+            .with_context(|| format!("Could not read manifest {:?}", manifest_path))?,
+    )
+    .with_context(|| format!("Invalid manifest {:?}", manifest_path))?;
+    check_manifest(&manifest).with_context(|| format!("Invalid manifest {:?}", manifest_path))?;
+
+    let base_dir = manifest_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+
+    let lock_path = manifest_path.with_extension("lock");
+    let previous_lock: AssetLock = match std::fs::read_to_string(&lock_path) {
+        Ok(content) => toml::from_str(&content)
+            .with_context(|| format!("Invalid lock file {:?}", lock_path))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => AssetLock::default(),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Could not read lock file {:?}", lock_path))
+        }
+    };
+
+    let admin_port = fetch_args.connect.admin_port().await?;
+
+    // Share one connection between all the assets.
+    let app_client = hc_client::get_authenticated_app_agent_client(
+        admin_port,
+        fetch_args.config_dir.clone(),
+        fetch_args.app_id.clone(),
+        fetch_args.connect.timeout(),
+        CredentialScope::Read,
+    )
+    .await?;
+
+    let semaphore = Arc::new(Semaphore::new(manifest.concurrency.max(1)));
+    let mut tasks = JoinSet::new();
+    for asset in manifest.assets.clone() {
+        let app_client = app_client.clone();
+        let semaphore = semaphore.clone();
+        let options = FetchCheckOptions {
+            offline: fetch_args.offline,
+            include_aliases: fetch_args.include_aliases,
+            config_dir: fetch_args.config_dir.clone(),
+        };
+
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await?;
+            let result = fetch_and_check(&app_client, &asset.url, &options).await;
+            anyhow::Result::<_>::Ok((asset, result))
+        });
+    }
+
+    let mut fetched = Vec::new();
+    let mut failures = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        let (asset, result) = joined??;
+        match result {
+            Ok(fetched_asset) => {
+                match manifest.policy.check(&fetched_asset.reports) {
+                    Ok(()) => {}
+                    Err(reason) => failures.push(format!("{}: {}", asset.url, reason)),
+                }
+
+                if let Some(locked) = previous_lock.assets.iter().find(|a| a.url == asset.url) {
+                    if locked.sha256 != fetched_asset.sha256 {
+                        failures.push(format!(
+                            "{}: the digest {} does not match {} recorded in the lock file",
+                            asset.url, fetched_asset.sha256, locked.sha256
+                        ));
+                    }
+                }
+
+                fetched.push((asset, fetched_asset));
+            }
+            Err(e) => failures.push(format!("{}: {:?}", asset.url, e)),
+        }
+    }
+
+    if !failures.is_empty() {
+        anyhow::bail!(
+            "Rejected {} of {} assets, nothing has been saved:\n{}",
+            failures.len(),
+            manifest.assets.len(),
+            failures.join("\n")
+        );
+    }
+
+    // Keep the lock in the same order as the manifest so that it is stable between runs.
+    let mut by_url = fetched
+        .into_iter()
+        .map(|(asset, fetched_asset)| (asset.url.clone(), (asset, fetched_asset)))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut lock = AssetLock::default();
+    for asset in &manifest.assets {
+        let Some((asset, fetched_asset)) = by_url.remove(&asset.url) else {
+            continue;
+        };
+
+        let output_path = base_dir.join(&asset.output);
+        if let Some(parent) = output_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Copy rather than rename, the temporary directory may be on a different file system.
+        std::fs::copy(fetched_asset.tmp_file.path(), &output_path)
+            .with_context(|| format!("Could not save {:?}", output_path))?;
+        println!("Saved {} to {:?}", asset.url, output_path);
+
+        lock.assets.push(LockedAsset {
+            url: asset.url.clone(),
+            sha256: fetched_asset.sha256,
+            passed_signatures: fetched_asset
+                .reports
+                .iter()
+                .flat_map(|r| r.passed_signatures.iter())
+                .map(|s| LockedSignature {
+                    author: s.author.to_string(),
+                    key_dist_address: s.key_dist_address.to_string(),
+                })
+                .collect(),
+        });
+    }
+
+    std::fs::write(&lock_path, toml::to_string_pretty(&lock)?)
+        .with_context(|| format!("Could not write lock file {:?}", lock_path))?;
+    println!("Lock file written to {:?}", lock_path);
+
+    Ok(FetchManifestInfo { lock_path, lock })
+}
+
+/// Options for [fetch_and_check] that are shared by every asset being fetched.
+#[derive(Debug, Clone)]
+pub(crate) struct FetchCheckOptions {
+    pub(crate) offline: bool,
+    pub(crate) include_aliases: bool,
+    /// Where the `.well-known` files used to verify domain claims are cached.
+    pub(crate) config_dir: Option<PathBuf>,
+}
+
+/// An asset that has been downloaded to a temporary file and had its signatures checked.
+pub(crate) struct FetchedAsset {
+    pub(crate) tmp_file: NamedTempFile,
+    /// The hex encoded SHA-256 digest of the asset.
+    pub(crate) sha256: String,
+    pub(crate) reports: Vec<SignatureCheckReport>,
+}
+
+/// Download the asset at `url` to a temporary file and check its signatures, without prompting.
+pub(crate) async fn fetch_and_check(
+    app_client: &HolochainClient,
+    url: &str,
+    options: &FetchCheckOptions,
+) -> anyhow::Result<FetchedAsset> {
+    let fetch_url = Url::parse(url).context("Invalid URL")?;
+    let canonical_fetch_url = canonicalize_url(url).context("Invalid URL")?;
+
+    let signatures = get_signatures_to_check(
+        app_client,
+        &canonical_fetch_url,
+        options.offline,
+        options.include_aliases,
+    )
+    .await?;
+
+    let mut tmp_file = tempfile::Builder::new()
+        .prefix("checked-")
+        .suffix(".unverified")
+        .tempfile()
+        .context("Could not create temporary file")?;
+
+    {
+        let mut writer = BufWriter::new(tmp_file.as_file_mut());
+        run_download(fetch_url, &mut writer, Arc::new(FetchState::default()))
+            .await
+            .context("Download failed")?;
+    }
+
+    let reports = if signatures.is_empty() {
+        vec![]
+    } else {
+        // Verifying the claims relies on the network, so it is skipped in offline mode.
+        let domain_claims = match (options.offline, asset_domain(&canonical_fetch_url)) {
+            (false, Some(domain)) => {
+                get_verified_domain_claims(app_client, &domain, options.config_dir.clone()).await
+            }
+            _ => Vec::new(),
+        };
+        check_signatures(
+            tmp_file.path().to_path_buf(),
+            signatures,
+            &domain_verified_keys(&domain_claims),
+        )?
+    };
+
+    Ok(FetchedAsset {
+        sha256: hex::encode(sha256_file(tmp_file.path())?),
+        tmp_file,
+        reports,
+    })
+}
+
+/// Check every asset's output path, and that no URL or output is listed twice. Assets are matched
+/// to their results and lock entries by URL, and a repeated output would be overwritten.
+fn check_manifest(manifest: &AssetManifest) -> anyhow::Result<()> {
+    let mut urls = BTreeSet::new();
+    let mut outputs = BTreeSet::new();
+    for asset in &manifest.assets {
+        check_output_path(&asset.output)
+            .with_context(|| format!("Invalid output for {}", asset.url))?;
+
+        if !urls.insert(asset.url.as_str()) {
+            anyhow::bail!("The asset {} is listed more than once", asset.url);
+        }
+
+        // Compare without `.` components, so that `./tool` and `tool` are the same output.
+        let output = asset
+            .output
+            .components()
+            .filter(|c| *c != Component::CurDir)
+            .collect::<PathBuf>();
+        if !outputs.insert(output) {
+            anyhow::bail!(
+                "The output {:?} for {} is used by another asset",
+                asset.output,
+                asset.url
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that an asset's output is a relative path that can't escape the directory that it is
+/// relative to.
+pub(crate) fn check_output_path(output: &Path) -> anyhow::Result<()> {
+    if output.as_os_str().is_empty() {
+        anyhow::bail!("The output path is empty");
+    }
+
+    for component in output.components() {
+        match component {
+            Component::Normal(_) | Component::CurDir => {}
+            Component::ParentDir => {
+                anyhow::bail!("The output path {:?} must not contain '..'", output)
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                anyhow::bail!(
+                    "The output path {:?} must be relative to the manifest",
+                    output
+                )
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::CheckedSignature;
+    use checked_types::FetchCheckSignatureReason;
+    use holochain_types::prelude::{ActionHash, AgentPubKey};
+
+    fn report(
+        reason: FetchCheckSignatureReason,
+        passed: usize,
+        failed: usize,
+    ) -> SignatureCheckReport {
+        domain_report(reason, passed, failed, false)
+    }
+
+    fn domain_report(
+        reason: FetchCheckSignatureReason,
+        passed: usize,
+        failed: usize,
+        domain_verified: bool,
+    ) -> SignatureCheckReport {
+        let sig = || CheckedSignature {
+            key_dist_address: ActionHash::from_raw_36(vec![0; 36]),
+            author: AgentPubKey::from_raw_36(vec![0; 36]),
+            alias_url: None,
+            domain_verified,
+        };
+
+        SignatureCheckReport {
+            reason,
+            passed_signatures: (0..passed).map(|_| sig()).collect(),
+            failed_signatures: (0..failed).map(|_| sig()).collect(),
+        }
+    }
+
+    #[test]
+    fn parse_manifest() {
+        let manifest: AssetManifest = toml::from_str(
+            r#"
+[policy]
+min_signatures = 2
+
+[[asset]]
+url = "https://example.com/tool.tar.gz"
+output = "vendor/tool.tar.gz"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(4, manifest.concurrency);
+        assert_eq!(2, manifest.policy.min_signatures);
+        assert!(!manifest.policy.allow_no_signatures);
+        assert_eq!(1, manifest.assets.len());
+        assert_eq!(
+            PathBuf::from("vendor/tool.tar.gz"),
+            manifest.assets[0].output
+        );
+    }
+
+    #[test]
+    fn default_policy() {
+        let policy = FetchPolicy::default();
+
+        assert!(policy.check(&[]).is_err());
+        assert!(policy
+            .check(&[report(FetchCheckSignatureReason::RandomRecent, 1, 0)])
+            .is_ok());
+        assert!(policy
+            .check(&[report(FetchCheckSignatureReason::RandomRecent, 3, 1)])
+            .is_err());
+    }
+
+    #[test]
+    fn policy_allows_no_signatures() {
+        let policy = FetchPolicy {
+            allow_no_signatures: true,
+            ..Default::default()
+        };
+
+        assert!(policy.check(&[]).is_ok());
+    }
+
+    #[test]
+    fn policy_requires_publisher() {
+        let policy = FetchPolicy {
+            require_publisher: true,
+            ..Default::default()
+        };
+
+        assert!(policy
+            .check(&[report(FetchCheckSignatureReason::RandomRecent, 2, 0)])
+            .is_err());
+        assert!(policy
+            .check(&[
+                domain_report(FetchCheckSignatureReason::Publisher, 1, 0, true),
+                report(FetchCheckSignatureReason::RandomRecent, 2, 0)
+            ])
+            .is_ok());
+        // Anyone can claim to be the publisher, the key must be verified for the domain too.
+        assert!(policy
+            .check(&[
+                report(FetchCheckSignatureReason::Publisher, 1, 0),
+                report(FetchCheckSignatureReason::RandomRecent, 2, 0)
+            ])
+            .is_err());
+    }
+
+    #[test]
+    fn duplicate_assets_are_rejected() {
+        let asset = |url: &str, output: &str| ManifestAsset {
+            url: url.to_string(),
+            output: PathBuf::from(output),
+        };
+        let manifest = |assets| AssetManifest {
+            concurrency: 4,
+            policy: FetchPolicy::default(),
+            assets,
+        };
+
+        assert!(check_manifest(&manifest(vec![
+            asset("https://example.com/a.tar.gz", "vendor/a.tar.gz"),
+            asset("https://example.com/b.tar.gz", "vendor/b.tar.gz"),
+        ]))
+        .is_ok());
+        assert!(check_manifest(&manifest(vec![
+            asset("https://example.com/a.tar.gz", "vendor/a.tar.gz"),
+            asset("https://example.com/a.tar.gz", "vendor/b.tar.gz"),
+        ]))
+        .is_err());
+        assert!(check_manifest(&manifest(vec![
+            asset("https://example.com/a.tar.gz", "vendor/tool.tar.gz"),
+            asset("https://example.com/b.tar.gz", "./vendor/tool.tar.gz"),
+        ]))
+        .is_err());
+        assert!(check_manifest(&manifest(vec![asset(
+            "https://example.com/a.tar.gz",
+            "../a.tar.gz"
+        )]))
+        .is_err());
+    }
+
+    #[test]
+    fn output_must_stay_in_manifest_dir() {
+        assert!(check_output_path(Path::new("vendor/tool.tar.gz")).is_ok());
+        assert!(check_output_path(Path::new("./tool.tar.gz")).is_ok());
+
+        assert!(check_output_path(Path::new("")).is_err());
+        assert!(check_output_path(Path::new("/etc/passwd")).is_err());
+        assert!(check_output_path(Path::new("../tool.tar.gz")).is_err());
+        assert!(check_output_path(Path::new("vendor/../../tool.tar.gz")).is_err());
+    }
+}
@@ -1,10 +1,11 @@
-use crate::cli::{DistributeArgs, GenerateArgs};
+use crate::cli::{ConnectArgs, DistributeArgs, GenerateArgs};
 use crate::common::{get_signing_key_path, get_store_dir, get_verification_key_path, open_file};
-use crate::distribute::distribute;
+use crate::distribute::distribute_with_password;
 use crate::interactive::GetPassword;
 use minisign::KeyPair;
 use std::io::Write;
 use std::path::PathBuf;
+use zeroize::Zeroizing;
 
 /// Information about the result of generating a new keypair.
 #[derive(Debug)]
@@ -16,7 +17,7 @@ pub struct GenerateInfo {
 }
 
 /// Generate a new signing keypair and optionally distribute the verification (public) key on Holochain.
-pub async fn generate(generate_args: GenerateArgs) -> anyhow::Result<GenerateInfo> {
+pub async fn generate(mut generate_args: GenerateArgs) -> anyhow::Result<GenerateInfo> {
     let store_dir = get_store_dir(generate_args.config_dir.clone())?;
 
     // Signing key
@@ -33,7 +34,9 @@ pub async fn generate(generate_args: GenerateArgs) -> anyhow::Result<GenerateInf
         &mut vk_file,
         &mut sk_file,
         None,
-        Some(password.clone()),
+        // MiniSign takes ownership of the password and doesn't zeroize it. It is still needed to
+        // distribute the key, so this is the one copy that can't be avoided.
+        Some(String::from(password.as_str())),
     )?
     .pk;
 
@@ -63,14 +66,24 @@ pub async fn generate(generate_args: GenerateArgs) -> anyhow::Result<GenerateInf
     Ok(GenerateInfo { sk_path, vk_path })
 }
 
-async fn dispatch_distribute(generate_args: GenerateArgs, password: String) -> anyhow::Result<()> {
-    distribute(DistributeArgs {
-        port: Some(generate_args.admin_port().await?),
-        name: generate_args.name,
-        password: Some(password),
-        config_dir: generate_args.config_dir,
-        app_id: generate_args.app_id,
-    })
+async fn dispatch_distribute(
+    generate_args: GenerateArgs,
+    password: Zeroizing<String>,
+) -> anyhow::Result<()> {
+    distribute_with_password(
+        DistributeArgs {
+            connect: ConnectArgs {
+                port: Some(generate_args.connect.admin_port().await?),
+                ..generate_args.connect
+            },
+            name: generate_args.name,
+            password: None,
+            password_source: Default::default(),
+            config_dir: generate_args.config_dir,
+            app_id: generate_args.app_id,
+        },
+        Some(password),
+    )
     .await?;
 
     Ok(())
@@ -1,41 +1,118 @@
+use crate::cli::{ALLOW_WRITE_ENV, CREDENTIALS_PASSWORD_ENV};
 use crate::common::get_store_dir;
+use crate::credentials::{
+    load_credentials, remove_legacy_credentials, save_credentials, CredentialsKey, Passphrase,
+};
 use anyhow::Context;
 use holochain_client::{
-    AdminWebsocket, AppStatusFilter, AppWebsocket, AuthorizeSigningCredentialsPayload,
-    ClientAgentSigner, ConductorApiError, SigningCredentials,
+    AdminWebsocket, AppWebsocket, AuthorizeSigningCredentialsPayload, ClientAgentSigner,
+    ConductorApiError, SigningCredentials, ZomeCallTarget,
+};
+use holochain_conductor_api::{
+    AppInfo, CellInfo, ExternalApiWireError, IssueAppAuthenticationTokenPayload,
 };
-use holochain_conductor_api::{CellInfo, IssueAppAuthenticationTokenPayload};
-use holochain_types::prelude::{AgentPubKey, CapSecret, CellId};
+use holochain_types::prelude::{AgentPubKey, CellId, ExternIO, GrantedFunctions};
 use holochain_types::websocket::AllowedOrigins;
-use serde::{Deserialize, Serialize};
-use std::fs::File;
-use std::io::Write;
+use std::future::Future;
+use std::io::IsTerminal;
 use std::net::Ipv6Addr;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
 
 const DEFAULT_INSTALLED_APP_ID: &str = "checked";
 
+/// Zome functions that only look things up.
+const READ_FUNCTIONS: &[(&str, &str)] = &[
+    ("fetch", "prepare_fetch"),
+    ("fetch", "prepare_fetch_local"),
+    ("fetch", "get_domain_claims"),
+    ("fetch", "find_signatures_by_digest"),
+    ("signing_keys", "get_my_key_collections"),
+    ("signing_keys", "get_my_key_collections_local"),
+];
+
+/// Zome functions that publish to your source chain.
+const WRITE_FUNCTIONS: &[(&str, &str)] = &[
+    ("fetch", "create_asset_signature"),
+    ("fetch", "create_asset_signatures"),
+    ("fetch", "create_publisher_claim"),
+    ("fetch", "create_domain_claim"),
+    ("signing_keys", "distribute_verification_key"),
+];
+
+/// What a set of signing credentials is authorized to call.
+///
+/// Each scope is authorized and saved separately, so that the credentials which are used for
+/// checking assets can't publish anything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum CredentialScope {
+    /// Only the [READ_FUNCTIONS].
+    Read,
+    /// The [READ_FUNCTIONS] and the [WRITE_FUNCTIONS].
+    Write,
+}
+
+impl CredentialScope {
+    /// The narrowest scope that is allowed to call `fn_name` in `zome_name`.
+    fn required_for(zome_name: &str, fn_name: &str) -> anyhow::Result<Self> {
+        let function = (zome_name, fn_name);
+        if READ_FUNCTIONS.contains(&function) {
+            Ok(CredentialScope::Read)
+        } else if WRITE_FUNCTIONS.contains(&function) {
+            Ok(CredentialScope::Write)
+        } else {
+            anyhow::bail!(
+                "No credentials are authorized to call `{}/{}`",
+                zome_name,
+                fn_name
+            )
+        }
+    }
+
+    fn granted_functions(self) -> GrantedFunctions {
+        let functions = match self {
+            CredentialScope::Read => READ_FUNCTIONS.to_vec(),
+            CredentialScope::Write => [READ_FUNCTIONS, WRITE_FUNCTIONS].concat(),
+        };
+
+        GrantedFunctions::Listed(
+            functions
+                .into_iter()
+                .map(|(zome_name, fn_name)| (zome_name.into(), fn_name.into()))
+                .collect(),
+        )
+    }
+}
+
+/// Connect to the `checked` app with credentials for `scope`.
+///
+/// Commands should ask for the narrowest scope that they need. The client asks to switch to a wider
+/// scope if it is later used to call a function that `scope` does not cover.
 pub(crate) async fn get_authenticated_app_agent_client(
     admin_port: u16,
     path: Option<PathBuf>,
     installed_app_id: Option<String>,
-) -> anyhow::Result<AppWebsocket> {
+    connect_timeout: Duration,
+    scope: CredentialScope,
+) -> anyhow::Result<HolochainClient> {
     let app_id = installed_app_id.unwrap_or_else(|| DEFAULT_INSTALLED_APP_ID.to_string());
+    let store_dir = get_store_dir(path)?;
 
-    println!("Connecting to admin port {}", admin_port);
-    // TODO connect timeout not configurable! Really slow if Holochain is not running.
-    let mut admin_client = AdminWebsocket::connect(format!("127.0.0.1:{admin_port}"))
-        .await
-        .with_context(|| {
-            format!("Failed to connect to Holochain admin interface at {admin_port}")
-        })?;
+    remove_legacy_credentials(&store_dir)?;
 
-    let mut signer = ClientAgentSigner::new();
-    load_or_create_signing_credentials(&mut admin_client, &mut signer, path, app_id.clone())
-        .await
-        .context("Failed to load Holochain call credentials")?;
+    println!("Connecting to admin port {}", admin_port);
+    let mut admin_client = tokio::time::timeout(
+        connect_timeout,
+        AdminWebsocket::connect(format!("127.0.0.1:{admin_port}")),
+    )
+    .await
+    .map_err(|_| connect_timeout_error("admin", admin_port, connect_timeout))?
+    .with_context(|| format!("Failed to connect to Holochain admin interface at {admin_port}"))?;
 
-    let app_port = find_or_create_app_interface(&mut admin_client)
+    let app_port = find_or_create_app_interface(&mut admin_client, &app_id)
         .await
         .context("Failed to find or create an app port on Holochain")?;
 
@@ -52,220 +129,533 @@ pub(crate) async fn get_authenticated_app_agent_client(
             )
         })?;
 
-    AppWebsocket::connect(
-        (Ipv6Addr::LOCALHOST, app_port),
-        issued.token,
-        signer.into(),
+    // Credentials are added once the app's cell is known, which is what they are saved by.
+    let signer = ClientAgentSigner::new();
+    let app_client = tokio::time::timeout(
+        connect_timeout,
+        AppWebsocket::connect(
+            (Ipv6Addr::LOCALHOST, app_port),
+            issued.token,
+            signer.clone().into(),
+        ),
     )
-        .await.with_context(|| {
+        .await
+        .map_err(|_| connect_timeout_error("app", app_port, connect_timeout))?
+        .with_context(|| {
         format!(
             "Failed to connect to Holochain app interface at `127.0.0.1:{app_port}` with app_id {app_id}"
         )
-    })
+    })?;
+
+    let key = CredentialsKey {
+        cell_id: checked_cell_id(app_client.cached_app_info())?,
+        installed_app_id: app_id,
+    };
+
+    let passphrase = Passphrase::ask_when_needed(store_dir.clone());
+
+    let client = HolochainClient {
+        app_client,
+        signer,
+        admin_port,
+        connect_timeout,
+        store_dir,
+        key,
+        passphrase,
+        scope: Arc::new(Mutex::new(scope)),
+        credentials_generation: Arc::new(AtomicU64::new(0)),
+    };
+    client
+        .use_credentials(scope, false)
+        .await
+        .context("Failed to load Holochain call credentials")?;
+
+    Ok(client)
 }
 
-pub(crate) fn maybe_handle_holochain_error(
-    conductor_api_error: &ConductorApiError,
-    path: Option<PathBuf>,
-) {
-    match conductor_api_error {
-        // TODO brittle, would be nice if the errors for some important failures were more specific.
-        ConductorApiError::SignZomeCallError(e) if e == "Provenance not found" => {
-            eprintln!("Saved credentials for Holochain appear invalid, removing them. Please re-run this command");
-            if let Ok(e) = get_credentials_path(path) {
-                if std::fs::remove_file(e).is_ok() {
-                    println!("Successfully removed credentials");
-                    return;
+/// An app client for the `checked` app which manages the signing credentials for its zome calls.
+///
+/// Credentials go stale when the app is reinstalled or the conductor forgets the grant. When a zome
+/// call is refused because of its credentials, new credentials are authorized through the admin
+/// interface and saved, then the call is retried once. Calls that are refused together only renew
+/// the credentials once.
+///
+/// Calling a function that needs a wider [CredentialScope] than the client has asks to authorize
+/// it on the terminal, or needs [ALLOW_WRITE_ENV] to be set when there is no terminal.
+#[derive(Debug, Clone)]
+pub(crate) struct HolochainClient {
+    app_client: AppWebsocket,
+    // Shares its credentials with the signer that the app client was created with.
+    signer: ClientAgentSigner,
+    admin_port: u16,
+    connect_timeout: Duration,
+    store_dir: PathBuf,
+    // Which app install the credentials are saved for.
+    key: CredentialsKey,
+    passphrase: Passphrase,
+    // Held while switching scope, so that concurrent calls only ask once.
+    scope: Arc<Mutex<CredentialScope>>,
+    // Counts how many times the signer's credentials have been replaced.
+    credentials_generation: Arc<AtomicU64>,
+}
+
+impl HolochainClient {
+    /// The agent that zome calls are made as.
+    pub(crate) fn my_pub_key(&self) -> &AgentPubKey {
+        &self.app_client.my_pub_key
+    }
+
+    /// Call `fn_name` in `zome_name` on the `checked` cell.
+    pub(crate) async fn call_zome(
+        &self,
+        zome_name: &str,
+        fn_name: &str,
+        payload: ExternIO,
+    ) -> anyhow::Result<ExternIO> {
+        self.escalate(zome_name, fn_name).await?;
+
+        call_with_renewal(
+            &self.credentials_generation,
+            &self.scope,
+            || self.try_call_zome(zome_name, fn_name, payload.clone()),
+            |scope| self.use_credentials(scope, true),
+        )
+        .await
+    }
+
+    async fn try_call_zome(
+        &self,
+        zome_name: &str,
+        fn_name: &str,
+        payload: ExternIO,
+    ) -> Result<ExternIO, ConductorApiError> {
+        self.app_client
+            .call_zome(
+                ZomeCallTarget::RoleName("checked".to_string()),
+                zome_name.into(),
+                fn_name.into(),
+                payload,
+            )
+            .await
+    }
+
+    /// Switch to credentials that are allowed to call `fn_name` in `zome_name`, if the current ones
+    /// are not.
+    async fn escalate(&self, zome_name: &str, fn_name: &str) -> anyhow::Result<()> {
+        let required = CredentialScope::required_for(zome_name, fn_name)?;
+
+        let mut scope = self.scope.lock().await;
+        if *scope >= required {
+            return Ok(());
+        }
+
+        confirm_write_access(zome_name, fn_name).await?;
+        self.use_credentials(required, false)
+            .await
+            .context("Failed to load Holochain call credentials")?;
+        *scope = required;
+
+        Ok(())
+    }
+
+    /// Sign zome calls with the saved credentials for `scope`. New credentials are authorized and
+    /// saved if there are none, or if `renew` is set.
+    async fn use_credentials(&self, scope: CredentialScope, renew: bool) -> anyhow::Result<()> {
+        // Without a passphrase, such as in CI, credentials are authorized for each run instead.
+        let saving = self.passphrase.is_available();
+
+        if !renew && saving {
+            let (store_dir, key, passphrase) = self.credentials_store();
+            // Decrypting may ask for the passphrase, and derives a key from it, which is slow.
+            let loaded = tokio::task::spawn_blocking(move || {
+                load_credentials(&store_dir, &key, scope, &passphrase)
+            })
+            .await??;
+
+            if let Some(credentials) = loaded {
+                self.signer
+                    .add_credentials(self.key.cell_id.clone(), credentials);
+                self.credentials_generation.fetch_add(1, Ordering::SeqCst);
+                return Ok(());
+            }
+        }
+
+        let mut admin_client = tokio::time::timeout(
+            self.connect_timeout,
+            AdminWebsocket::connect(format!("127.0.0.1:{}", self.admin_port)),
+        )
+        .await
+        .map_err(|_| connect_timeout_error("admin", self.admin_port, self.connect_timeout))?
+        .with_context(|| {
+            format!(
+                "Failed to connect to Holochain admin interface at {}",
+                self.admin_port
+            )
+        })?;
+
+        let credentials =
+            create_new_credentials(&mut admin_client, &self.key.cell_id, scope).await?;
+        let credentials = if saving {
+            let (store_dir, key, passphrase) = self.credentials_store();
+            tokio::task::spawn_blocking(move || {
+                save_credentials(&store_dir, &key, scope, &credentials, &passphrase)
+                    .map(|_| credentials)
+            })
+            .await??
+        } else {
+            println!(
+                "Not saving the Holochain credentials, set {} to save them without a terminal",
+                CREDENTIALS_PASSWORD_ENV
+            );
+            credentials
+        };
+        self.signer
+            .add_credentials(self.key.cell_id.clone(), credentials);
+        self.credentials_generation.fetch_add(1, Ordering::SeqCst);
+
+        Ok(())
+    }
+
+    /// What is needed to load or save credentials on a blocking thread.
+    fn credentials_store(&self) -> (PathBuf, CredentialsKey, Passphrase) {
+        (
+            self.store_dir.clone(),
+            self.key.clone(),
+            self.passphrase.clone(),
+        )
+    }
+}
+
+/// Make a zome call with `call`, and if its credentials are refused, `renew` them for the current
+/// scope and retry once.
+///
+/// Renewal happens while holding the `scope` lock. A call that was refused with credentials which
+/// have since been replaced, by `renew` or by switching scope, is retried without renewing again.
+/// `renew` is expected to bump `generation` when it replaces the credentials.
+async fn call_with_renewal<T, C, CF, R, RF>(
+    generation: &AtomicU64,
+    scope: &Mutex<CredentialScope>,
+    call: C,
+    renew: R,
+) -> anyhow::Result<T>
+where
+    C: Fn() -> CF,
+    CF: Future<Output = Result<T, ConductorApiError>>,
+    R: FnOnce(CredentialScope) -> RF,
+    RF: Future<Output = anyhow::Result<()>>,
+{
+    let seen = generation.load(Ordering::SeqCst);
+
+    match call().await {
+        Err(e) if is_credential_error(&e) => {
+            {
+                let scope = scope.lock().await;
+                if generation.load(Ordering::SeqCst) == seen {
+                    eprintln!(
+                        "Saved credentials for Holochain were rejected ({}), authorizing new credentials",
+                        e
+                    );
+                    renew(*scope)
+                        .await
+                        .context("Failed to replace invalid Holochain call credentials")?;
                 }
             }
 
-            eprintln!("Failed to remove");
-        }
-        _ => {
-            // No special handling required
+            Ok(call().await?)
         }
+        result => Ok(result?),
+    }
+}
+
+/// Ask before authorizing credentials that can publish, which is needed to call `fn_name` in
+/// `zome_name`. Without a terminal to ask on, it is only authorized if [ALLOW_WRITE_ENV] is set.
+async fn confirm_write_access(zome_name: &str, fn_name: &str) -> anyhow::Result<()> {
+    if std::env::var(ALLOW_WRITE_ENV).is_ok_and(|allow| allow == "1") {
+        println!(
+            "Authorizing write access to Holochain to call `{}/{}`, allowed by {}",
+            zome_name, fn_name, ALLOW_WRITE_ENV
+        );
+        return Ok(());
     }
+
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!(
+            "Calling `{}/{}` needs write access to Holochain, set {}=1 to authorize it without a terminal",
+            zome_name,
+            fn_name,
+            ALLOW_WRITE_ENV
+        );
+    }
+
+    let prompt = format!(
+        "Calling `{}/{}` needs write access to Holochain, which allows publishing signatures, keys and claims as you. Authorize write access?",
+        zome_name, fn_name
+    );
+    let confirmed = tokio::task::spawn_blocking(move || {
+        dialoguer::Confirm::new()
+            .with_prompt(prompt)
+            .default(false)
+            .interact()
+    })
+    .await??;
+
+    if !confirmed {
+        anyhow::bail!(
+            "Write access to Holochain was refused, `{}/{}` was not called",
+            zome_name,
+            fn_name
+        );
+    }
+
+    Ok(())
+}
+
+/// Whether a zome call failed because of the credentials it was signed with, rather than because of
+/// the call itself.
+fn is_credential_error(e: &ConductorApiError) -> bool {
+    matches!(
+        e,
+        // The signer has no credentials for the cell, such as after the app was reinstalled.
+        ConductorApiError::SignZomeCallError(_)
+            | ConductorApiError::ExternalApiWireError(
+                ExternalApiWireError::ZomeCallAuthenticationFailed(_)
+                    | ExternalApiWireError::ZomeCallUnauthorized(_)
+            )
+    )
+}
+
+fn connect_timeout_error(interface: &str, port: u16, connect_timeout: Duration) -> anyhow::Error {
+    anyhow::anyhow!(
+        "Timed out after {} seconds connecting to the Holochain {} interface at port {}. Check that Holochain is running and listening on that port, or increase `--connect-timeout`.",
+        connect_timeout.as_secs_f32(),
+        interface,
+        port
+    )
 }
 
-async fn find_or_create_app_interface(admin_client: &mut AdminWebsocket) -> anyhow::Result<u16> {
+/// Find an app interface that `installed_app_id` can connect to, or attach one that is restricted to
+/// it.
+async fn find_or_create_app_interface(
+    admin_client: &mut AdminWebsocket,
+    installed_app_id: &str,
+) -> anyhow::Result<u16> {
     let app_interfaces = admin_client
         .list_app_interfaces()
         .await
         .map_err(|e| anyhow::anyhow!("Error listing app interfaces: {:?}", e))?;
 
     let matching_interfaces = app_interfaces.iter().find(|interface_info| {
-        if let Some(installed_app_id) = &interface_info.installed_app_id {
-            if installed_app_id.as_str() != DEFAULT_INSTALLED_APP_ID {
-                return false;
-            }
-        }
-
-        interface_info.allowed_origins == AllowedOrigins::Any
+        interface_info
+            .installed_app_id
+            .as_ref()
+            .is_none_or(|app_id| app_id.as_str() == installed_app_id)
+            && interface_info.allowed_origins == AllowedOrigins::Any
     });
 
     let app_port = match matching_interfaces {
         Some(interface_info) => interface_info.port,
         None => admin_client
-            .attach_app_interface(0, AllowedOrigins::Any, None)
+            .attach_app_interface(0, AllowedOrigins::Any, Some(installed_app_id.to_string()))
             .await
             .map_err(|e| anyhow::anyhow!("Error attaching app interface: {:?}", e))?,
     };
     Ok(app_port)
 }
 
-async fn load_or_create_signing_credentials(
-    admin_client: &mut AdminWebsocket,
-    signer: &mut ClientAgentSigner,
-    path: Option<PathBuf>,
-    installed_app_id: String,
-) -> anyhow::Result<()> {
-    match try_load_credentials(path.clone())? {
-        Some((cell_id, credentials)) => {
-            signer.add_credentials(cell_id, credentials);
-        }
-        None => {
-            let (cell_id, credentials) =
-                create_new_credentials(admin_client, installed_app_id).await?;
-            dump_credentials(cell_id.clone(), &credentials, path)?;
-            signer.add_credentials(cell_id, credentials);
-        }
-    }
-    Ok(())
-}
-
-async fn create_new_credentials(
-    client: &mut AdminWebsocket,
-    installed_app_id: String,
-) -> anyhow::Result<(CellId, SigningCredentials)> {
-    let apps = client
-        .list_apps(Some(AppStatusFilter::Running))
-        .await
-        .map_err(|e| anyhow::anyhow!("Error listing apps: {:?}", e))?;
-
-    let app = apps
-        .iter()
-        .find(|app| app.installed_app_id == installed_app_id)
-        .ok_or_else(|| anyhow::anyhow!("App `checked` not found"))?;
-
-    let cells = app
-        .cell_info
-        .get("checked")
-        .ok_or_else(|| anyhow::anyhow!("Role `checked` not found"))?;
+/// The provisioned `checked` cell of the app, which zome calls are made to.
+fn checked_cell_id(app_info: &AppInfo) -> anyhow::Result<CellId> {
+    let cells = app_info.cell_info.get("checked").ok_or_else(|| {
+        anyhow::anyhow!(
+            "Role `checked` not found in app `{}`",
+            app_info.installed_app_id
+        )
+    })?;
 
-    let cell = cells
+    cells
         .iter()
         .find_map(|cell| match cell {
-            CellInfo::Provisioned(cell) if cell.name == "checked" => Some(cell),
+            CellInfo::Provisioned(cell) if cell.name == "checked" => Some(cell.cell_id.clone()),
             _ => None,
         })
-        .ok_or_else(|| anyhow::anyhow!("Cell `checked` not found"))?;
+        .ok_or_else(|| anyhow::anyhow!("Cell `checked` not found"))
+}
 
-    let credentials = client
+async fn create_new_credentials(
+    client: &mut AdminWebsocket,
+    cell_id: &CellId,
+    scope: CredentialScope,
+) -> anyhow::Result<SigningCredentials> {
+    client
         .authorize_signing_credentials(AuthorizeSigningCredentialsPayload {
-            cell_id: cell.cell_id.clone(),
-            functions: None, // For all, not documented!
+            cell_id: cell_id.clone(),
+            functions: Some(scope.granted_functions()),
         })
         .await
-        .map_err(|e| anyhow::anyhow!("Error authorizing signing credentials: {:?}", e))?;
-
-    Ok((cell.cell_id.clone(), credentials))
+        .map_err(|e| anyhow::anyhow!("Error authorizing signing credentials: {:?}", e))
 }
 
-#[derive(Serialize, Deserialize)]
-struct SavedCredentials {
-    cell_id: CellId,
-    signing_agent_key: AgentPubKey,
-    keypair: Vec<u8>,
-    cap_secret: CapSecret,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn connect_times_out() {
+        // Accepts connections but never completes the websocket handshake.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let mut streams = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                streams.push(stream);
+            }
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let err = get_authenticated_app_agent_client(
+            port,
+            Some(dir.path().to_path_buf()),
+            None,
+            Duration::from_millis(200),
+            CredentialScope::Read,
+        )
+        .await
+        .unwrap_err();
 
-fn dump_credentials(
-    cell_id: CellId,
-    signing_credentials: &SigningCredentials,
-    path: Option<PathBuf>,
-) -> anyhow::Result<()> {
-    let saved = SavedCredentials {
-        cell_id: cell_id.clone(),
-        signing_agent_key: signing_credentials.signing_agent_key.clone(),
-        keypair: signing_credentials.keypair.to_keypair_bytes().to_vec(),
-        cap_secret: signing_credentials.cap_secret,
-    };
+        assert!(err.to_string().contains("Timed out"), "{:?}", err);
+    }
 
-    let serialized = serde_json::to_string(&saved)
-        .map_err(|e| anyhow::anyhow!("Error serializing credentials: {:?}", e))?;
-
-    let credentials_path = get_credentials_path(path)?;
-
-    let mut f = File::options()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open(credentials_path)
-        .map_err(|e| anyhow::anyhow!("Error opening credentials file: {:?}", e))?;
-
-    #[cfg(target_family = "unix")]
-    {
-        use std::fs::Permissions;
-        use std::os::unix::fs::PermissionsExt;
-        f.set_permissions(Permissions::from_mode(0o660))
-            .map_err(|e| {
-                anyhow::anyhow!("Error setting permissions on credentials file: {:?}", e)
-            })?;
+    #[test]
+    fn detect_credential_errors() {
+        assert!(is_credential_error(&ConductorApiError::SignZomeCallError(
+            "Provenance not found".to_string()
+        )));
+        assert!(is_credential_error(
+            &ConductorApiError::ExternalApiWireError(ExternalApiWireError::ZomeCallUnauthorized(
+                "no grant".to_string()
+            ))
+        ));
+        assert!(is_credential_error(
+            &ConductorApiError::ExternalApiWireError(
+                ExternalApiWireError::ZomeCallAuthenticationFailed("bad signature".to_string())
+            )
+        ));
+
+        assert!(!is_credential_error(
+            &ConductorApiError::ExternalApiWireError(ExternalApiWireError::RibosomeError(
+                "invalid input".to_string()
+            ))
+        ));
+        assert!(!is_credential_error(&ConductorApiError::CellNotFound));
     }
 
-    f.write_all(serialized.as_bytes())
-        .map_err(|e| anyhow::anyhow!("Error writing credentials file: {:?}", e))?;
+    #[test]
+    fn scope_for_zome_functions() {
+        assert_eq!(
+            CredentialScope::Read,
+            CredentialScope::required_for("fetch", "prepare_fetch").unwrap()
+        );
+        assert_eq!(
+            CredentialScope::Write,
+            CredentialScope::required_for("fetch", "create_asset_signature").unwrap()
+        );
+        assert!(
+            CredentialScope::required_for("signing_keys", "mark_verification_key_dist").is_err()
+        );
+
+        let GrantedFunctions::Listed(read) = CredentialScope::Read.granted_functions() else {
+            panic!("Expected listed functions");
+        };
+        let GrantedFunctions::Listed(write) = CredentialScope::Write.granted_functions() else {
+            panic!("Expected listed functions");
+        };
+        assert!(read.is_subset(&write));
+        assert!(!read.contains(&("fetch".into(), "create_asset_signature".into())));
+        assert!(write.contains(&("fetch".into(), "create_asset_signature".into())));
+    }
 
-    Ok(())
-}
+    /// A zome call that is refused until the credentials have been renewed.
+    async fn fake_call(generation: &AtomicU64) -> Result<u64, ConductorApiError> {
+        match generation.load(Ordering::SeqCst) {
+            0 => Err(ConductorApiError::SignZomeCallError(
+                "Provenance not found".to_string(),
+            )),
+            current => Ok(current),
+        }
+    }
 
-fn try_load_credentials(
-    path: Option<PathBuf>,
-) -> anyhow::Result<Option<(CellId, SigningCredentials)>> {
-    let credentials_path = get_credentials_path(path)?;
+    async fn fake_renew(generation: &AtomicU64, renewals: &AtomicUsize) -> anyhow::Result<()> {
+        // Give concurrent calls time to be refused too.
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        renewals.fetch_add(1, Ordering::SeqCst);
+        generation.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
 
-    let f = match File::open(credentials_path) {
-        Ok(f) => f,
-        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-            return Ok(None);
-        }
-        Err(e) => {
-            return Err(anyhow::anyhow!("Error reading credentials file: {:?}", e));
-        }
-    };
+    #[tokio::test]
+    async fn rejected_call_is_renewed_and_retried() {
+        let generation = AtomicU64::new(0);
+        let scope = Mutex::new(CredentialScope::Write);
+        let renewals = AtomicUsize::new(0);
+
+        let result = call_with_renewal(
+            &generation,
+            &scope,
+            || fake_call(&generation),
+            |renew_scope| {
+                assert_eq!(CredentialScope::Write, renew_scope);
+                fake_renew(&generation, &renewals)
+            },
+        )
+        .await
+        .unwrap();
 
-    let saved: SavedCredentials = match serde_json::from_reader(f) {
-        Ok(saved) => saved,
-        Err(e) => {
-            eprintln!("Saved credentials file is corrupt: {:?}", e);
-            return Ok(None);
-        }
-    };
+        assert_eq!(1, result);
+        assert_eq!(1, renewals.load(Ordering::SeqCst));
+    }
 
-    let keypair = match ed25519_dalek::SigningKey::from_keypair_bytes(
-        saved
-            .keypair
-            .as_slice()
-            .try_into()
-            .context("Could not create signing key from saved data")?,
-    ) {
-        Ok(keypair) => keypair,
-        Err(e) => {
-            eprintln!("Saved credentials file is corrupt: {:?}", e);
-            return Ok(None);
-        }
-    };
+    #[tokio::test]
+    async fn concurrent_rejected_calls_renew_once() {
+        let generation = AtomicU64::new(0);
+        let scope = Mutex::new(CredentialScope::Read);
+        let renewals = AtomicUsize::new(0);
+
+        let call = || async {
+            call_with_renewal(
+                &generation,
+                &scope,
+                || fake_call(&generation),
+                |_| fake_renew(&generation, &renewals),
+            )
+            .await
+        };
+        let (first, second) = tokio::join!(call(), call());
 
-    Ok(Some((
-        saved.cell_id,
-        SigningCredentials {
-            signing_agent_key: saved.signing_agent_key,
-            keypair,
-            cap_secret: saved.cap_secret,
-        },
-    )))
-}
+        assert_eq!(1, first.unwrap());
+        assert_eq!(1, second.unwrap());
+        assert_eq!(1, renewals.load(Ordering::SeqCst));
+    }
 
-fn get_credentials_path(path: Option<PathBuf>) -> anyhow::Result<PathBuf> {
-    Ok(get_store_dir(path)?.join("credentials.json"))
+    #[tokio::test]
+    async fn other_errors_are_not_retried() {
+        let generation = AtomicU64::new(0);
+        let scope = Mutex::new(CredentialScope::Read);
+        let calls = AtomicUsize::new(0);
+
+        let err = call_with_renewal(
+            &generation,
+            &scope,
+            || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err::<(), _>(ConductorApiError::CellNotFound)
+            },
+            |_| async { panic!("Should not renew") },
+        )
+        .await
+        .unwrap_err();
+
+        assert!(err.to_string().contains("Cell not found"), "{:?}", err);
+        assert_eq!(1, calls.load(Ordering::SeqCst));
+    }
 }
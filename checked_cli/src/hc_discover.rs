@@ -1,94 +1,80 @@
+use crate::cli::ConnectArgs;
 use holochain_client::AdminWebsocket;
-use proc_ctl::{PortQuery, ProcQuery, ProtocolPort};
+use proc_ctl::{PortQuery, ProcInfo, ProcQuery, ProtocolPort};
+use std::io::IsTerminal;
 use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+/// Use the admin port from [ConnectArgs::port], otherwise search for one.
+///
+/// With [ConnectArgs::no_discover], or when there is no terminal to prompt on, the search never
+/// prompts. It fails with the admin ports that were found instead, so that one can be picked with
+/// `--port`.
+pub(crate) async fn discover_admin_port(connect: &ConnectArgs) -> anyhow::Result<u16> {
+    if let Some(port) = connect.port {
+        return Ok(port);
+    }
 
-/// Search for a Holochain process interactively. For each process that is found check what ports it
-/// is listening on. Will attempt to return when there is only one option to select and will prompt
-/// the user to select a process and port when there are multiple options.
-pub(crate) async fn interactive_discover_holochain() -> anyhow::Result<u16> {
-    let query = ProcQuery::new().process_name("holochain");
-
-    let processes = query.list_processes()?;
-
-    let mut possible_processes_with_ports = processes
-        .into_iter()
-        .filter_map(|p| {
-            let port_query = PortQuery::new().ip_v4_only().tcp_only().process_id(p.pid);
-
-            match port_query.execute() {
-                Ok(ports) => {
-                    let tcp_ports = ports
-                        .into_iter()
-                        .filter_map(|p| match p {
-                            ProtocolPort::Tcp(p) => Some(p),
-                            _ => None,
-                        })
-                        .collect::<Vec<_>>();
-
-                    if tcp_ports.is_empty() {
-                        None
-                    } else {
-                        Some((p, tcp_ports))
-                    }
-                }
-                _ => None,
-            }
-        })
-        .collect::<Vec<_>>();
-
-    if possible_processes_with_ports.is_empty() {
-        // Handle the case that no Holochain processes were found
-        anyhow::bail!("No Holochain processes found.");
-    } else if possible_processes_with_ports
-        .iter()
-        .all(|(_, ports)| ports.is_empty())
-    {
-        // Handle the case that Holochain processes were found but all have no ports open
+    if connect.no_discover {
+        // Only used to help pick a port, so failing to search is not an error here.
+        let candidates = find_admin_ports(connect.timeout())
+            .await
+            .unwrap_or_default();
         anyhow::bail!(
-            "Found {} Holochain processes found but none have any ports open.",
-            possible_processes_with_ports.len()
+            "No admin port provided and discovery is disabled. {}",
+            describe_candidates(&candidates)
         );
-    } else if possible_processes_with_ports.len() == 1
-        && possible_processes_with_ports[0].1.len() == 1
-    {
-        // Perfect match case with one instance of Holochain and one port open
-        return Ok(possible_processes_with_ports[0].1[0]);
     }
 
-    // Ensure consistent ordering when multiple commands are run
-    possible_processes_with_ports.sort_by_key(|(proc, _)| proc.pid);
-
-    let selected = dialoguer::Select::new()
-        .with_prompt("Pick a Holochain process")
-        .items(
-            &possible_processes_with_ports
-                .iter()
-                .map(|(p, ports)| {
-                    format!(
-                        "Process ID: {}, launched with arguments: {:?}, has {} ports open",
-                        p.pid,
-                        p.cmd,
-                        ports.len()
-                    )
-                })
-                .collect::<Vec<_>>(),
-        )
-        .interact()?;
+    interactive_discover_holochain(connect.timeout(), std::io::stdin().is_terminal()).await
+}
 
-    let (_, ports) = &possible_processes_with_ports[selected];
+/// Search for a Holochain process interactively. For each process that is found check which of the
+/// ports that it is listening on are admin ports. Will return when there is only one admin port and
+/// will prompt the user to select a process and port when there are multiple options.
+async fn interactive_discover_holochain(
+    probe_timeout: Duration,
+    can_prompt: bool,
+) -> anyhow::Result<u16> {
+    let candidates = find_admin_ports(probe_timeout).await?;
+
+    if candidates.is_empty() {
+        anyhow::bail!("No Holochain processes with an admin port open were found.");
+    } else if candidates.len() == 1 && candidates[0].1.len() == 1 {
+        // Perfect match case with one instance of Holochain and one admin port open
+        return Ok(candidates[0].1[0]);
+    }
 
-    // Filter down to the ports that respond to admin requests and ignore anything that is likely
-    // to be an app port.
-    let mut admin_ports = vec![];
-    for port in ports {
-        if is_admin_port(*port).await {
-            admin_ports.push(*port);
-        }
+    if !can_prompt {
+        anyhow::bail!(
+            "Cannot prompt for a Holochain admin port without a terminal. {}",
+            describe_candidates(&candidates)
+        );
     }
 
-    if admin_ports.is_empty() {
-        anyhow::bail!("No admin ports open for selected Holochain process");
-    } else if admin_ports.len() == 1 {
+    let selected = if candidates.len() == 1 {
+        0
+    } else {
+        dialoguer::Select::new()
+            .with_prompt("Pick a Holochain process")
+            .items(
+                &candidates
+                    .iter()
+                    .map(|(p, ports)| {
+                        format!(
+                            "Process ID: {}, launched with arguments: {:?}, has {} admin ports open",
+                            p.pid,
+                            p.cmd,
+                            ports.len()
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .interact()?
+    };
+
+    let (_, admin_ports) = &candidates[selected];
+    if admin_ports.len() == 1 {
         return Ok(admin_ports[0]);
     }
 
@@ -105,14 +91,85 @@ pub(crate) async fn interactive_discover_holochain() -> anyhow::Result<u16> {
     Ok(admin_ports[port_index])
 }
 
-async fn is_admin_port(port: u16) -> bool {
+/// Find the Holochain processes that have admin ports open, with those ports, ordered by process
+/// ID so that the order is consistent when multiple commands are run.
+///
+/// Ports that don't respond to admin requests, such as app ports, are left out.
+async fn find_admin_ports(probe_timeout: Duration) -> anyhow::Result<Vec<(ProcInfo, Vec<u16>)>> {
+    let query = ProcQuery::new().process_name("holochain");
+
+    let processes = query.list_processes()?;
+    if processes.is_empty() {
+        anyhow::bail!("No Holochain processes found.");
+    }
+
+    let mut candidates = Vec::new();
+    for process in processes {
+        let port_query = PortQuery::new()
+            .ip_v4_only()
+            .tcp_only()
+            .process_id(process.pid);
+
+        let Ok(ports) = port_query.execute() else {
+            continue;
+        };
+
+        let mut admin_ports = Vec::new();
+        for port in ports {
+            if let ProtocolPort::Tcp(port) = port {
+                if is_admin_port(port, probe_timeout).await {
+                    admin_ports.push(port);
+                }
+            }
+        }
+
+        if !admin_ports.is_empty() {
+            candidates.push((process, admin_ports));
+        }
+    }
+
+    candidates.sort_by_key(|(process, _)| process.pid);
+
+    Ok(candidates)
+}
+
+/// Describe the admin ports that were found, for an error message.
+fn describe_candidates(candidates: &[(ProcInfo, Vec<u16>)]) -> String {
+    if candidates.is_empty() {
+        return "No Holochain admin ports were found.".to_string();
+    }
+
+    let mut out = "Pick one of these admin ports with `--port`:".to_string();
+    for (process, ports) in candidates {
+        out.push_str(&format!(
+            "\n  Process ID {} ({}): {}",
+            process.pid,
+            process.cmd.join(" "),
+            ports
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    out
+}
+
+async fn is_admin_port(port: u16, probe_timeout: Duration) -> bool {
     let ipv4_addr: SocketAddr = (Ipv4Addr::LOCALHOST, port).into();
     let ipv6_addr: SocketAddr = (Ipv6Addr::LOCALHOST, port).into();
 
-    let client = match AdminWebsocket::connect(vec![ipv4_addr, ipv6_addr].as_slice()).await {
-        Ok(client) => client,
-        Err(_) => return false,
+    let probe = async {
+        let client = AdminWebsocket::connect(vec![ipv4_addr, ipv6_addr].as_slice())
+            .await
+            .ok()?;
+
+        client.list_apps(None).await.ok()
     };
 
-    client.list_apps(None).await.is_ok()
+    matches!(
+        tokio::time::timeout(probe_timeout, probe).await,
+        Ok(Some(_))
+    )
 }
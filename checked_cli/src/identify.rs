@@ -0,0 +1,105 @@
+use std::fs::File;
+use std::io::BufReader;
+
+use anyhow::Context;
+use holochain_types::prelude::{ActionHash, AgentPubKey, ExternIO};
+
+use checked_types::{DigestSignature, FindSignaturesByDigestRequest};
+
+use crate::cli::IdentifyArgs;
+use crate::common::sha256_file;
+use crate::fetch::check_one_signature;
+use crate::hc_client::{self, CredentialScope};
+
+/// A signature that was found for the identified file and verified against it.
+#[derive(Debug)]
+pub struct IdentifiedSignature {
+    /// The URL that the signer fetched the asset from.
+    pub fetch_url: String,
+    /// The agent that created the signature.
+    pub author: AgentPubKey,
+    /// The address of the key that created the signature.
+    pub key_dist_address: ActionHash,
+}
+
+/// Find out where a file came from, by looking up signatures for its content.
+///
+/// The file is hashed and any signatures that were indexed under that digest are fetched from
+/// Holochain. The digest is provided by signers and isn't checked on Holochain, so each signature is
+/// verified against the file and only those that pass are returned.
+pub async fn identify(identify_args: IdentifyArgs) -> anyhow::Result<Vec<IdentifiedSignature>> {
+    if !identify_args.file.is_file() {
+        anyhow::bail!("File to identify does not exist - {:?}", identify_args.file);
+    }
+
+    let content_sha256 = sha256_file(&identify_args.file)?;
+    println!("SHA-256: {}", hex::encode(&content_sha256));
+
+    let admin_port = identify_args.connect.admin_port().await?;
+
+    let app_client = hc_client::get_authenticated_app_agent_client(
+        admin_port,
+        identify_args.config_dir.clone(),
+        identify_args.app_id.clone(),
+        identify_args.connect.timeout(),
+        CredentialScope::Read,
+    )
+    .await?;
+
+    let response = app_client
+        .call_zome(
+            "fetch",
+            "find_signatures_by_digest",
+            ExternIO::encode(FindSignaturesByDigestRequest { content_sha256 })?,
+        )
+        .await
+        .context("Failed to find signatures for the file")?;
+
+    let signatures: Vec<DigestSignature> = response.decode()?;
+
+    let mut identified = Vec::new();
+    let mut failed = 0;
+    for signature in signatures {
+        let mut reader = BufReader::new(File::open(&identify_args.file)?);
+        match check_one_signature(
+            &mut reader,
+            &signature.key_type,
+            &signature.verification_key,
+            &signature.signature,
+        ) {
+            Ok(true) => identified.push(IdentifiedSignature {
+                fetch_url: signature.fetch_url,
+                author: signature.author,
+                key_dist_address: signature.key_dist_address,
+            }),
+            Ok(false) => failed += 1,
+            Err(e) => {
+                println!("Error during verification: {:?}", e);
+                failed += 1;
+            }
+        }
+    }
+
+    if identified.is_empty() {
+        println!("No signatures were found for this file.");
+    } else {
+        println!("\nThis file has been signed at:");
+        for signature in &identified {
+            println!(
+                "{} by author {:?} with key {:?}",
+                signature.fetch_url, signature.author, signature.key_dist_address
+            );
+        }
+    }
+
+    if failed > 0 {
+        println!(
+            "\n{} signature{} claimed to be for this file but failed verification and {} been ignored.",
+            failed,
+            if failed == 1 { "" } else { "s" },
+            if failed == 1 { "has" } else { "have" }
+        );
+    }
+
+    Ok(identified)
+}
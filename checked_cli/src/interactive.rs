@@ -1,110 +1,218 @@
-use crate::cli::{DistributeArgs, FetchArgs, GenerateArgs, SignArgs};
-use crate::hc_discover::interactive_discover_holochain;
+use crate::cli::{ConnectArgs, DistributeArgs, FetchArgs, GenerateArgs, SignArgs, SignReleaseArgs};
+use crate::cli::{PasswordSource, CREDENTIALS_PASSWORD_ENV, PASSWORD_ENV};
+use crate::hc_discover::discover_admin_port;
+use anyhow::Context;
+use std::io::IsTerminal;
+use std::process::{Command, Stdio};
+use zeroize::{Zeroize, Zeroizing};
 
 /// Common trait to allow for passwords to be retrieved from the user.
 pub trait GetPassword {
-    /// Retrieve a password.
-    fn get_password(&self) -> anyhow::Result<String>;
+    /// Retrieve a password, taking it out of the arguments if it was provided there so that no
+    /// copy is left behind.
+    fn get_password(&mut self) -> anyhow::Result<Zeroizing<String>>;
 }
 
 impl GetPassword for GenerateArgs {
-    fn get_password(&self) -> anyhow::Result<String> {
-        get_password_common(self.password.as_ref(), "New password: ")
+    fn get_password(&mut self) -> anyhow::Result<Zeroizing<String>> {
+        get_password_common(
+            self.password.take(),
+            &self.password_source,
+            "New password: ",
+        )
     }
 }
 
 impl GetPassword for SignArgs {
-    fn get_password(&self) -> anyhow::Result<String> {
+    fn get_password(&mut self) -> anyhow::Result<Zeroizing<String>> {
+        get_password_common(
+            self.password.take(),
+            &self.password_source,
+            format!("Password for '{}': ", self.name),
+        )
+    }
+}
+
+impl GetPassword for SignReleaseArgs {
+    fn get_password(&mut self) -> anyhow::Result<Zeroizing<String>> {
         get_password_common(
-            self.password.as_ref(),
+            self.password.take(),
+            &self.password_source,
+            format!("Password for '{}': ", self.name),
+        )
+    }
+}
+
+#[cfg(unix)]
+impl GetPassword for crate::cli::AgentArgs {
+    fn get_password(&mut self) -> anyhow::Result<Zeroizing<String>> {
+        get_password_common(
+            self.password.take(),
+            &self.password_source,
             format!("Password for '{}': ", self.name),
         )
     }
 }
 
 impl GetPassword for DistributeArgs {
-    fn get_password(&self) -> anyhow::Result<String> {
+    fn get_password(&mut self) -> anyhow::Result<Zeroizing<String>> {
         get_password_common(
-            self.password.as_ref(),
+            self.password.take(),
+            &self.password_source,
             format!("Password for '{}': ", self.name),
         )
     }
 }
 
 impl GetPassword for FetchArgs {
-    fn get_password(&self) -> anyhow::Result<String> {
+    fn get_password(&mut self) -> anyhow::Result<Zeroizing<String>> {
         get_password_common(
-            self.password.as_ref(),
+            self.password.take(),
+            &self.password_source,
             format!("Password for '{}': ", self.name),
         )
     }
 }
 
 fn get_password_common(
-    maybe_password: Option<&String>,
+    maybe_password: Option<String>,
+    password_source: &PasswordSource,
     prompt: impl ToString,
-) -> anyhow::Result<String> {
-    match maybe_password {
-        Some(password) => Ok(password.clone()),
-        None => Ok(rpassword::prompt_password(prompt)?),
+) -> anyhow::Result<Zeroizing<String>> {
+    let env_password = std::env::var(PASSWORD_ENV).ok().map(Zeroizing::new);
+
+    match password_from_sources(maybe_password, password_source, env_password)? {
+        Some(password) => Ok(password),
+        None => Ok(Zeroizing::new(rpassword::prompt_password(prompt)?)),
     }
 }
 
-impl GenerateArgs {
-    /// The admin port for Holochain.
-    ///
-    /// If not specified, by [FetchArgs::port], then the tool will scan for a Holochain process and
-    /// open ports on that process. The user will be prompted for the process and port to use where
-    /// multiple of either are found.
-    pub async fn admin_port(&self) -> anyhow::Result<u16> {
-        match self.port {
-            Some(port) => Ok(port),
-            None => interactive_discover_holochain().await,
+/// Find a password without prompting, following the precedence described on [PasswordSource].
+fn password_from_sources(
+    maybe_password: Option<String>,
+    password_source: &PasswordSource,
+    env_password: Option<Zeroizing<String>>,
+) -> anyhow::Result<Option<Zeroizing<String>>> {
+    if let Some(password) = maybe_password {
+        return Ok(Some(Zeroizing::new(password)));
+    }
+
+    // Reading through `/dev/fd` avoids taking ownership of the descriptor, which would need unsafe.
+    #[cfg(unix)]
+    if let Some(fd) = password_source.password_fd {
+        let content = std::fs::read_to_string(format!("/dev/fd/{}", fd))
+            .with_context(|| format!("Could not read a password from file descriptor {}", fd))?;
+        return Ok(Some(first_line(Zeroizing::new(content))));
+    }
+
+    if let Some(path) = &password_source.password_file {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read a password from {:?}", path))?;
+        return Ok(Some(first_line(Zeroizing::new(content))));
+    }
+
+    if let Some(command) = &password_source.password_command {
+        let mut child = if cfg!(windows) {
+            let mut child = Command::new("cmd");
+            child.arg("/C").arg(command);
+            child
+        } else {
+            let mut child = Command::new("sh");
+            child.arg("-c").arg(command);
+            child
+        };
+        // Only the password is captured, so that the command can prompt and report errors.
+        let output = child
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .and_then(|child| child.wait_with_output())
+            .with_context(|| format!("Could not run password command `{}`", command))?;
+
+        let mut stdout = Zeroizing::new(output.stdout);
+        if !output.status.success() {
+            anyhow::bail!(
+                "Password command `{}` failed with {}",
+                command,
+                output.status
+            );
         }
+
+        // Move the output into the password rather than copying it.
+        let content = String::from_utf8(std::mem::take(&mut *stdout)).map_err(|e| {
+            e.into_bytes().zeroize();
+            anyhow::anyhow!("Password is not valid UTF-8")
+        })?;
+        return Ok(Some(first_line(Zeroizing::new(content))));
     }
+
+    Ok(env_password.map(first_line))
 }
 
-impl SignArgs {
-    /// The admin port for Holochain.
-    ///
-    /// If not specified, by [FetchArgs::port], then the tool will scan for a Holochain process and
-    /// open ports on that process. The user will be prompted for the process and port to use where
-    /// multiple of either are found.
-    pub async fn admin_port(&self) -> anyhow::Result<u16> {
-        match self.port {
-            Some(port) => Ok(port),
-            None => interactive_discover_holochain().await,
-        }
+/// Keep only the first line of `content`, without its line ending.
+fn first_line(mut content: Zeroizing<String>) -> Zeroizing<String> {
+    if let Some(end) = content.find('\n') {
+        content.truncate(end);
+    }
+    if content.ends_with('\r') {
+        content.pop();
     }
+
+    content
 }
 
-impl DistributeArgs {
-    /// The admin port for Holochain.
-    ///
-    /// If not specified, by [FetchArgs::port], then the tool will scan for a Holochain process and
-    /// open ports on that process. The user will be prompted for the process and port to use where
-    /// multiple of either are found.
-    pub async fn admin_port(&self) -> anyhow::Result<u16> {
-        match self.port {
-            Some(port) => Ok(port),
-            None => interactive_discover_holochain().await,
+/// Whether the passphrase for saved Holochain credentials can be found with
+/// [get_credentials_password], without failing for a lack of a terminal.
+pub(crate) fn credentials_password_available() -> bool {
+    std::env::var(CREDENTIALS_PASSWORD_ENV).is_ok_and(|password| !password.is_empty())
+        || std::io::stdin().is_terminal()
+}
+
+/// Get the passphrase that saved Holochain credentials are encrypted with, from
+/// [CREDENTIALS_PASSWORD_ENV] or by prompting. Set `confirm` when the passphrase is being chosen.
+pub(crate) fn get_credentials_password(confirm: bool) -> anyhow::Result<Zeroizing<String>> {
+    if let Ok(password) = std::env::var(CREDENTIALS_PASSWORD_ENV) {
+        if !password.is_empty() {
+            return Ok(first_line(Zeroizing::new(password)));
         }
     }
+
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!(
+            "Saved Holochain credentials are encrypted, set {} to provide the passphrase without a terminal",
+            CREDENTIALS_PASSWORD_ENV
+        );
+    }
+
+    let password = Zeroizing::new(rpassword::prompt_password(if confirm {
+        "Choose a passphrase for your Holochain credentials: "
+    } else {
+        "Passphrase for your Holochain credentials: "
+    })?);
+    if password.is_empty() {
+        anyhow::bail!("The passphrase for your Holochain credentials cannot be empty");
+    }
+
+    if confirm {
+        let repeated = Zeroizing::new(rpassword::prompt_password("Repeat the passphrase: ")?);
+        if password != repeated {
+            anyhow::bail!("The passphrases did not match");
+        }
+    }
+
+    Ok(password)
 }
 
-impl FetchArgs {
-    /// The admin port for Holochain.
-    ///
-    /// If not specified, by [FetchArgs::port], then the tool will scan for a Holochain process and
-    /// open ports on that process. The user will be prompted for the process and port to use where
-    /// multiple of either are found.
+impl ConnectArgs {
+    /// The admin port for Holochain, from [ConnectArgs::port] or found by searching for a Holochain
+    /// process.
     pub async fn admin_port(&self) -> anyhow::Result<u16> {
-        match self.port {
-            Some(port) => Ok(port),
-            None => interactive_discover_holochain().await,
-        }
+        discover_admin_port(self).await
     }
+}
 
+impl FetchArgs {
     /// Whether the asset should be downloaded even if no signatures are found. This is primarily
     /// an interactive prompt but can be forced with [FetchArgs::allow_no_signatures].
     pub fn allow_no_signatures(&self) -> anyhow::Result<bool> {
@@ -138,3 +246,75 @@ impl FetchArgs {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn password_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let password_file = dir.path().join("password");
+        std::fs::write(&password_file, "from-file\nignored\n").unwrap();
+
+        let source = PasswordSource {
+            password_file: Some(password_file),
+            password_command: Some("echo from-command".to_string()),
+            ..Default::default()
+        };
+        let env = || Some(Zeroizing::new("from-env".to_string()));
+
+        let password =
+            password_from_sources(Some("from-flag".to_string()), &source, env()).unwrap();
+        assert_eq!("from-flag", password.unwrap().as_str());
+
+        let password = password_from_sources(None, &source, env()).unwrap();
+        assert_eq!("from-file", password.unwrap().as_str());
+
+        let source = PasswordSource {
+            password_file: None,
+            ..source
+        };
+        let password = password_from_sources(None, &source, env()).unwrap();
+        assert_eq!("from-command", password.unwrap().as_str());
+
+        let password = password_from_sources(None, &PasswordSource::default(), env()).unwrap();
+        assert_eq!("from-env", password.unwrap().as_str());
+
+        let password = password_from_sources(None, &PasswordSource::default(), None).unwrap();
+        assert!(password.is_none());
+    }
+
+    #[test]
+    fn failed_password_command() {
+        let source = PasswordSource {
+            password_command: Some("exit 1".to_string()),
+            ..Default::default()
+        };
+
+        assert!(password_from_sources(None, &source, None).is_err());
+    }
+
+    #[test]
+    fn password_without_line_ending() {
+        assert_eq!(
+            "test",
+            first_line(Zeroizing::new("test\r\n".to_string())).as_str()
+        );
+        assert_eq!("", first_line(Zeroizing::new(String::new())).as_str());
+    }
+
+    #[test]
+    fn password_sources_conflict() {
+        use crate::cli::Cli;
+        use clap::Parser;
+
+        let parse = |args: &[&str]| {
+            Cli::try_parse_from(["checked", "sign", "--file", "a"].iter().chain(args))
+        };
+
+        assert!(parse(&["--password-file", "p"]).is_ok());
+        assert!(parse(&["--password", "p", "--password-file", "p"]).is_err());
+        assert!(parse(&["--password-file", "p", "--password-command", "c"]).is_err());
+    }
+}
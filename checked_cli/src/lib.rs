@@ -11,23 +11,56 @@
 
 mod common;
 
+#[cfg(unix)]
+mod agent;
+mod checksums;
+mod claim_publisher;
 mod cli;
+mod config;
+mod credentials;
+mod daemon;
 mod distribute;
+mod domain;
 mod fetch;
+mod fetch_manifest;
 mod generate;
 pub(crate) mod hc_client;
 mod hc_discover;
+mod identify;
 mod interactive;
+mod proxy;
+mod sidecar;
 mod sign;
+mod sign_release;
 mod verify;
 
 /// Flattened exports for public use.
 pub mod prelude {
+    #[cfg(unix)]
+    pub use crate::agent::{agent, AGENT_SOCKET_ENV};
+    pub use crate::checksums::{ChecksumsReport, CHECKSUMS_FILE_NAME};
+    pub use crate::claim_publisher::claim_publisher;
     pub use crate::cli::*;
+    pub use crate::config::{config, parse_cli, Config, Profile, CONFIG_FILE_NAME, PROFILE_ENV};
+    pub use crate::credentials::credentials;
+    pub use crate::daemon::{daemon, DAEMON_TOKEN_FILE_NAME};
     pub use crate::distribute::distribute;
+    pub use crate::domain::{
+        claim_domain, verify_domain, VerifiedDomainClaim, WellKnownChecked, WellKnownCheckedKey,
+    };
     pub use crate::fetch::{fetch, FetchInfo};
+    pub use crate::fetch_manifest::{
+        fetch_manifest, AssetLock, AssetManifest, FetchManifestInfo, FetchPolicy, LockedAsset,
+        LockedSignature, ManifestAsset,
+    };
     pub use crate::generate::{generate, GenerateInfo};
+    pub use crate::identify::{identify, IdentifiedSignature};
     pub use crate::interactive::GetPassword;
+    pub use crate::proxy::proxy;
+    pub use crate::sidecar::{
+        SidecarKey, SidecarKeySource, SidecarReport, DEFAULT_SIDECAR_PATTERN,
+    };
     pub use crate::sign::sign;
+    pub use crate::sign_release::sign_release;
     pub use crate::verify::verify;
 }
@@ -0,0 +1,446 @@
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::io::{Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use bytes::Bytes;
+use futures::TryStreamExt;
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Full, StreamBody};
+use hyper::body::{Frame, Incoming};
+use hyper::header::HeaderValue;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tempfile::NamedTempFile;
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+use tokio_util::io::ReaderStream;
+
+use crate::cli::ProxyArgs;
+use crate::common::{get_store_dir, unix_timestamp};
+use crate::fetch::SignatureCheckReport;
+use crate::fetch_manifest::{fetch_and_check, FetchCheckOptions, FetchPolicy};
+use crate::hc_client::{self, CredentialScope, HolochainClient};
+
+const INDEX_FILE_NAME: &str = "index.json";
+
+type ProxyBody = BoxBody<Bytes, std::io::Error>;
+
+struct ProxyState {
+    app_client: HolochainClient,
+    options: FetchCheckOptions,
+    policy: FetchPolicy,
+    cache: ProxyCache,
+    cache_ttl: Duration,
+}
+
+/// Run an HTTP forward proxy that only serves assets whose signatures pass the policy.
+///
+/// Each GET is downloaded to a temporary file and checked, without prompting, in the same way as
+/// [fetch_manifest](crate::fetch_manifest::fetch_manifest). The content is only sent to the client
+/// once it has been accepted. Rejected assets get a 403 response with the reason in the
+/// `X-Checked-Report` header and a report in the body.
+///
+/// Accepted assets are cached by URL and digest, so they are served again without being fetched or
+/// checked until [ProxyArgs::cache_ttl] has passed. Only plain HTTP can be proxied, HTTPS tunnels
+/// are refused because the proxy would not be able to see the content that it is vouching for.
+///
+/// The proxy fetches any URL that it is asked for, so it only listens on a loopback address unless
+/// [ProxyArgs::allow_remote] is set.
+pub async fn proxy(proxy_args: ProxyArgs) -> anyhow::Result<()> {
+    if !proxy_args.listen.ip().is_loopback() && !proxy_args.allow_remote {
+        anyhow::bail!(
+            "Refusing to listen on {}, which is not a loopback address. Anyone who can reach the proxy could use it to fetch through this machine. Use --allow-remote if that is intended",
+            proxy_args.listen
+        );
+    }
+
+    let admin_port = proxy_args.connect.admin_port().await?;
+
+    let app_client = hc_client::get_authenticated_app_agent_client(
+        admin_port,
+        proxy_args.config_dir.clone(),
+        proxy_args.app_id.clone(),
+        proxy_args.connect.timeout(),
+        CredentialScope::Read,
+    )
+    .await?;
+
+    let cache_dir = match proxy_args.cache_dir {
+        Some(cache_dir) => cache_dir,
+        None => get_store_dir(proxy_args.config_dir.clone())?.join("proxy_cache"),
+    };
+
+    let state = Arc::new(ProxyState {
+        app_client,
+        options: FetchCheckOptions {
+            offline: proxy_args.offline,
+            include_aliases: proxy_args.include_aliases,
+            config_dir: proxy_args.config_dir.clone(),
+        },
+        policy: proxy_args.policy,
+        cache: ProxyCache::load(cache_dir)?,
+        cache_ttl: Duration::from_secs(proxy_args.cache_ttl),
+    });
+
+    let listener = TcpListener::bind(proxy_args.listen)
+        .await
+        .with_context(|| format!("Could not listen on {}", proxy_args.listen))?;
+    println!("Proxy listening on http://{}", proxy_args.listen);
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let state = state.clone();
+
+        tokio::spawn(async move {
+            let service = service_fn(move |request| handle_request(state.clone(), request));
+            if let Err(e) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                eprintln!("Proxy connection failed: {:?}", e);
+            }
+        });
+    }
+}
+
+async fn handle_request(
+    state: Arc<ProxyState>,
+    request: Request<Incoming>,
+) -> Result<Response<ProxyBody>, Infallible> {
+    if request.method() == Method::CONNECT {
+        return Ok(text_response(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "HTTPS tunnels are not supported, the proxy cannot check content that it cannot see\n",
+        ));
+    }
+
+    if request.method() != Method::GET {
+        return Ok(text_response(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "Only GET requests can be checked\n",
+        ));
+    }
+
+    let uri = request.uri();
+    if uri.scheme().is_none() || uri.host().is_none() {
+        return Ok(text_response(
+            StatusCode::BAD_REQUEST,
+            "Requests must use an absolute URL, configure this server as an HTTP proxy\n",
+        ));
+    }
+    let url = uri.to_string();
+
+    match serve_asset(&state, &url).await {
+        Ok(response) => Ok(response),
+        Err(e) => {
+            eprintln!("Failed to serve {}: {:?}", url, e);
+            Ok(text_response(
+                StatusCode::BAD_GATEWAY,
+                format!("Could not fetch {}: {:#}\n", url, e),
+            ))
+        }
+    }
+}
+
+async fn serve_asset(state: &ProxyState, url: &str) -> anyhow::Result<Response<ProxyBody>> {
+    if let Some((sha256, file)) = state.cache.get(url, state.cache_ttl).await? {
+        println!("Serving {} from the cache", url);
+        return Ok(asset_response(file, &sha256, true));
+    }
+
+    println!("Checking {}", url);
+    let fetched = fetch_and_check(&state.app_client, url, &state.options).await?;
+
+    if let Err(reason) = state.policy.check(&fetched.reports) {
+        println!("Rejected {}: {}", url, reason);
+
+        let mut response = text_response(
+            StatusCode::FORBIDDEN,
+            format_report(url, &reason, &fetched.reports),
+        );
+        response
+            .headers_mut()
+            .insert("X-Checked-Report", header_value(&reason));
+        return Ok(response);
+    }
+
+    println!("Accepted {}", url);
+    let path = state
+        .cache
+        .insert(url, &fetched.sha256, fetched.tmp_file.path())
+        .await?;
+    let file = tokio::fs::File::open(&path)
+        .await
+        .with_context(|| format!("Could not read {:?}", path))?;
+
+    Ok(asset_response(file, &fetched.sha256, false))
+}
+
+/// Stream `file` to the client, rather than reading it into memory.
+fn asset_response(file: tokio::fs::File, sha256: &str, cached: bool) -> Response<ProxyBody> {
+    let body = StreamBody::new(ReaderStream::new(file).map_ok(Frame::data));
+
+    let mut response = Response::new(BodyExt::boxed(body));
+    response
+        .headers_mut()
+        .insert("X-Checked-Sha256", header_value(sha256));
+    response.headers_mut().insert(
+        "X-Checked-Cache",
+        HeaderValue::from_static(if cached { "hit" } else { "miss" }),
+    );
+
+    response
+}
+
+fn text_response(status: StatusCode, body: impl Into<String>) -> Response<ProxyBody> {
+    let body = Full::new(Bytes::from(body.into())).map_err(|never| match never {});
+    let mut response = Response::new(BodyExt::boxed(body));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        HeaderValue::from_static("text/plain; charset=utf-8"),
+    );
+
+    response
+}
+
+/// Make a header value from `value`, replacing anything that isn't allowed in a header.
+fn header_value(value: &str) -> HeaderValue {
+    let sanitized = value
+        .chars()
+        .map(|c| if c.is_ascii_graphic() { c } else { ' ' })
+        .collect::<String>();
+
+    HeaderValue::from_str(sanitized.trim()).unwrap_or_else(|_| HeaderValue::from_static("invalid"))
+}
+
+fn format_report(url: &str, reason: &str, reports: &[SignatureCheckReport]) -> String {
+    let mut out = format!("Rejected {}: {}\n", url, reason);
+    for report in reports {
+        out.push_str(&format!(
+            "{:?}: {} passed, {} failed\n",
+            report.reason,
+            report.passed_signatures.len(),
+            report.failed_signatures.len()
+        ));
+    }
+
+    out
+}
+
+/// Assets that have been accepted by the proxy, stored by digest with an index from URL to
+/// digest.
+///
+/// The index lock is only held to read or update the index in memory, never while reading files,
+/// so that serving one asset doesn't hold up the others.
+#[derive(Debug)]
+struct ProxyCache {
+    dir: PathBuf,
+    index: Mutex<BTreeMap<String, CachedAsset>>,
+    /// Held while the index is written, so that the last write is always the latest index.
+    index_write: Mutex<()>,
+}
+
+/// An entry in the [ProxyCache] index.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CachedAsset {
+    /// The hex encoded SHA-256 digest of the asset.
+    sha256: String,
+    /// When the asset was accepted, as a Unix timestamp in seconds.
+    accepted_at: u64,
+}
+
+impl ProxyCache {
+    fn load(dir: PathBuf) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Could not create cache directory {:?}", dir))?;
+
+        let index = match std::fs::read_to_string(dir.join(INDEX_FILE_NAME)) {
+            Ok(content) => serde_json::from_str(&content).context("Invalid proxy cache index")?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e).context("Could not read the proxy cache index"),
+        };
+
+        Ok(ProxyCache {
+            dir,
+            index: Mutex::new(index),
+            index_write: Mutex::new(()),
+        })
+    }
+
+    /// Find the cached asset for `url`, opened for reading.
+    ///
+    /// Assets that were accepted more than `ttl` ago are treated as missing, so that they are
+    /// checked again in case signatures have changed since. So is content that no longer matches
+    /// the digest it was accepted with. The digest is checked on the same handle that is returned,
+    /// so the file can't be swapped between checking and serving it.
+    async fn get(
+        &self,
+        url: &str,
+        ttl: Duration,
+    ) -> anyhow::Result<Option<(String, tokio::fs::File)>> {
+        let Some(entry) = self.index.lock().await.get(url).cloned() else {
+            return Ok(None);
+        };
+
+        if unix_timestamp().saturating_sub(entry.accepted_at) > ttl.as_secs() {
+            return Ok(None);
+        }
+
+        let path = self.dir.join(&entry.sha256);
+        let sha256 = entry.sha256.clone();
+        let file = tokio::task::spawn_blocking(move || -> anyhow::Result<Option<std::fs::File>> {
+            let mut file = match std::fs::File::open(&path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+                Err(e) => return Err(e).with_context(|| format!("Could not read {:?}", path)),
+            };
+
+            let mut hasher = Sha256::new();
+            std::io::copy(&mut file, &mut hasher)?;
+            if hex::encode(hasher.finalize()) != sha256 {
+                return Ok(None);
+            }
+
+            file.seek(SeekFrom::Start(0))?;
+            Ok(Some(file))
+        })
+        .await??;
+
+        Ok(file.map(|file| (entry.sha256, tokio::fs::File::from_std(file))))
+    }
+
+    /// Copy `content` into the cache as the accepted asset for `url`, giving the path that it was
+    /// stored at.
+    async fn insert(&self, url: &str, sha256: &str, content: &Path) -> anyhow::Result<PathBuf> {
+        let path = self.dir.join(sha256);
+
+        // Copy to a temporary file and then move it into place, so that an asset that is being
+        // served is never partially overwritten.
+        let (dir, content, cached_path) = (self.dir.clone(), content.to_path_buf(), path.clone());
+        tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+            let tmp_file = NamedTempFile::new_in(&dir)?;
+            std::fs::copy(&content, tmp_file.path())
+                .with_context(|| format!("Could not cache {:?}", cached_path))?;
+            tmp_file
+                .persist(&cached_path)
+                .with_context(|| format!("Could not cache {:?}", cached_path))?;
+            Ok(())
+        })
+        .await??;
+
+        let _index_write = self.index_write.lock().await;
+        let index = {
+            let mut index = self.index.lock().await;
+            index.insert(
+                url.to_string(),
+                CachedAsset {
+                    sha256: sha256.to_string(),
+                    accepted_at: unix_timestamp(),
+                },
+            );
+            serde_json::to_string_pretty(&*index)?
+        };
+        tokio::fs::write(self.dir.join(INDEX_FILE_NAME), index)
+            .await
+            .context("Could not write the proxy cache index")?;
+
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::sha256_file;
+    use tokio::io::AsyncReadExt;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    #[tokio::test]
+    async fn cache_by_url_and_digest() {
+        let dir = tempfile::tempdir().unwrap();
+        let content = dir.path().join("content");
+        std::fs::write(&content, "test").unwrap();
+        let sha256 = hex::encode(sha256_file(&content).unwrap());
+
+        let cache = ProxyCache::load(dir.path().join("cache")).unwrap();
+        assert!(cache
+            .get("http://example.com/a", TTL)
+            .await
+            .unwrap()
+            .is_none());
+
+        let path = cache
+            .insert("http://example.com/a", &sha256, &content)
+            .await
+            .unwrap();
+
+        let cache = ProxyCache::load(dir.path().join("cache")).unwrap();
+        let (cached_sha256, mut file) = cache
+            .get("http://example.com/a", TTL)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(sha256, cached_sha256);
+        let mut cached = String::new();
+        file.read_to_string(&mut cached).await.unwrap();
+        assert_eq!("test", cached);
+
+        std::fs::write(&path, "modified").unwrap();
+        assert!(cache
+            .get("http://example.com/a", TTL)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn cache_expires() {
+        let dir = tempfile::tempdir().unwrap();
+        let content = dir.path().join("content");
+        std::fs::write(&content, "test").unwrap();
+        let sha256 = hex::encode(sha256_file(&content).unwrap());
+
+        let cache = ProxyCache::load(dir.path().join("cache")).unwrap();
+        cache
+            .insert("http://example.com/a", &sha256, &content)
+            .await
+            .unwrap();
+        assert!(cache
+            .get("http://example.com/a", TTL)
+            .await
+            .unwrap()
+            .is_some());
+
+        cache
+            .index
+            .lock()
+            .await
+            .get_mut("http://example.com/a")
+            .unwrap()
+            .accepted_at -= TTL.as_secs() + 1;
+        assert!(cache
+            .get("http://example.com/a", TTL)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn sanitize_header_value() {
+        assert_eq!(
+            "No signatures found",
+            header_value("No signatures found\n").to_str().unwrap()
+        );
+        assert_eq!("a b", header_value("a\r\nb").to_str().unwrap());
+    }
+}
@@ -0,0 +1,311 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use anyhow::Context;
+use holochain_types::prelude::{ActionHash, AgentPubKey, ExternIO};
+use minisign::{PublicKeyBox, SignatureBox};
+use reqwest::StatusCode;
+use signing_keys_types::{KeyCollectionWithKeys, MarkVfKeyDistOpt};
+use url::Url;
+
+use checked_types::{FetchCheckSignature, FetchCheckSignatureReason, VerificationKeyType};
+
+use crate::domain::VerifiedDomainClaim;
+use crate::hc_client::HolochainClient;
+
+/// The default location of a MiniSign signature published next to an asset.
+pub const DEFAULT_SIDECAR_PATTERN: &str = "{url}.minisig";
+
+/// The result of checking a MiniSign signature that was published next to an asset.
+#[derive(Debug)]
+pub struct SidecarReport {
+    /// Where the published signature was looked for.
+    pub sidecar_url: Url,
+    /// Whether a signature was found at [SidecarReport::sidecar_url].
+    pub found: bool,
+    /// Trusted keys that the published signature was verified with.
+    pub passed_keys: Vec<SidecarKey>,
+    /// Trusted keys that the published signature claims to be made by, but which failed to verify
+    /// the asset.
+    pub failed_keys: Vec<SidecarKey>,
+}
+
+/// A key that was used to check a published signature.
+#[derive(Debug, Clone)]
+pub struct SidecarKey {
+    /// The address of the key on Holochain.
+    pub key_dist_address: ActionHash,
+    /// The agent that distributed the key.
+    pub author: AgentPubKey,
+    /// Why the key is trusted.
+    pub source: SidecarKeySource,
+    verification_key: String,
+}
+
+#[cfg(test)]
+impl SidecarKey {
+    pub(crate) fn for_test() -> Self {
+        SidecarKey {
+            key_dist_address: ActionHash::from_raw_36(vec![0; 36]),
+            author: AgentPubKey::from_raw_36(vec![0; 36]),
+            source: SidecarKeySource::Domain,
+            verification_key: String::new(),
+        }
+    }
+}
+
+/// Why a key is trusted to check a published signature.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SidecarKeySource {
+    /// The key is in one of your key collections, with the given name.
+    KeyCollection(String),
+    /// The key has been claimed by the publisher of the asset's URL, and verified for its domain.
+    Publisher,
+    /// The key has been verified as belonging to the owner of the asset's domain.
+    Domain,
+}
+
+/// Find the URL of the signature published next to `fetch_url`, by replacing `{url}` in `pattern`.
+pub(crate) fn make_sidecar_url(pattern: &str, fetch_url: &Url) -> anyhow::Result<Url> {
+    Url::parse(&pattern.replace("{url}", fetch_url.as_str()))
+        .map_err(|e| anyhow::anyhow!("Invalid signature URL from pattern '{}': {}", pattern, e))
+}
+
+/// Gather the keys that a published signature can be trusted from.
+///
+/// These are the keys in your key collections, the keys of publishers which were found while
+/// preparing the fetch, and keys that have been verified for the asset's domain. Publisher keys are
+/// only used if they are also verified for the domain, because anyone can claim a URL prefix.
+/// Compromised keys in your key collections are skipped, the zomes already skip them for the other
+/// sources.
+pub(crate) async fn get_sidecar_keys(
+    app_client: &HolochainClient,
+    local_only: bool,
+    signatures: &[FetchCheckSignature],
+    domain_claims: &[VerifiedDomainClaim],
+) -> anyhow::Result<Vec<SidecarKey>> {
+    let response = app_client
+        .call_zome(
+            "signing_keys",
+            if local_only {
+                "get_my_key_collections_local"
+            } else {
+                "get_my_key_collections"
+            },
+            ExternIO::encode(())?,
+        )
+        .await
+        .context("Failed to get your key collections")?;
+    let key_collections: Vec<KeyCollectionWithKeys> = response.decode()?;
+
+    let mut keys: Vec<SidecarKey> = Vec::new();
+    let mut add_key = |key: SidecarKey| {
+        if !keys
+            .iter()
+            .any(|k| k.key_dist_address == key.key_dist_address)
+        {
+            keys.push(key);
+        }
+    };
+
+    for key_collection in key_collections {
+        for key in key_collection.verification_keys {
+            if key
+                .verification_key_dist
+                .marks
+                .iter()
+                .any(|m| matches!(m, MarkVfKeyDistOpt::Compromised { .. }))
+            {
+                continue;
+            }
+
+            match key.verification_key_dist.key_type {
+                VerificationKeyType::MiniSignEd25519 => add_key(SidecarKey {
+                    key_dist_address: key.key_dist_address,
+                    author: key.author,
+                    source: SidecarKeySource::KeyCollection(key_collection.name.clone()),
+                    verification_key: key.verification_key_dist.verification_key,
+                }),
+            }
+        }
+    }
+
+    let domain_verified = |key_dist_address: &ActionHash| {
+        domain_claims
+            .iter()
+            .any(|c| c.verified && &c.key_dist_address == key_dist_address)
+    };
+    for sig in signatures.iter().filter(|s| {
+        s.reason == FetchCheckSignatureReason::Publisher && domain_verified(&s.key_dist_address)
+    }) {
+        match sig.key_type {
+            VerificationKeyType::MiniSignEd25519 => add_key(SidecarKey {
+                key_dist_address: sig.key_dist_address.clone(),
+                author: sig.author.clone(),
+                source: SidecarKeySource::Publisher,
+                verification_key: sig.verification_key.clone(),
+            }),
+        }
+    }
+
+    for claim in domain_claims.iter().filter(|c| c.verified) {
+        match claim.key_type {
+            VerificationKeyType::MiniSignEd25519 => add_key(SidecarKey {
+                key_dist_address: claim.key_dist_address.clone(),
+                author: claim.author.clone(),
+                source: SidecarKeySource::Domain,
+                verification_key: claim.verification_key.clone(),
+            }),
+        }
+    }
+
+    Ok(keys)
+}
+
+/// Download the signature published at `sidecar_url` and check `check_file` against it with each
+/// of the `keys` that it claims to be made by.
+///
+/// A missing signature is reported rather than treated as an error, since most assets won't have
+/// one.
+pub(crate) async fn check_sidecar(
+    sidecar_url: Url,
+    check_file: &Path,
+    keys: &[SidecarKey],
+) -> anyhow::Result<SidecarReport> {
+    let Some(signature) = download_signature(&sidecar_url).await? else {
+        return Ok(SidecarReport {
+            sidecar_url,
+            found: false,
+            passed_keys: vec![],
+            failed_keys: vec![],
+        });
+    };
+
+    let (passed_keys, failed_keys) = verify_with_keys(&signature, check_file, keys)?;
+
+    Ok(SidecarReport {
+        sidecar_url,
+        found: true,
+        passed_keys,
+        failed_keys,
+    })
+}
+
+/// Download a MiniSign signature, returning `None` if nothing has been published at `url`.
+pub(crate) async fn download_signature(url: &Url) -> anyhow::Result<Option<SignatureBox>> {
+    println!("Fetching published signature from {}", url);
+    let response = reqwest::get(url.clone()).await?;
+
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    if !response.status().is_success() {
+        anyhow::bail!(
+            "Failed to fetch published signature: {:?}",
+            response.status()
+        );
+    }
+
+    let signature = SignatureBox::from_string(&response.text().await?)
+        .map_err(|e| anyhow::anyhow!("Invalid published signature: {}", e))?;
+
+    Ok(Some(signature))
+}
+
+/// Check `check_file` against `signature` with each of the `keys` that have the key id that the
+/// signature claims to be made by. Returns the keys that passed and the keys that failed.
+pub(crate) fn verify_with_keys(
+    signature: &SignatureBox,
+    check_file: &Path,
+    keys: &[SidecarKey],
+) -> anyhow::Result<(Vec<SidecarKey>, Vec<SidecarKey>)> {
+    let mut passed_keys = Vec::new();
+    let mut failed_keys = Vec::new();
+
+    for key in keys {
+        let public_key = match PublicKeyBox::from_string(&key.verification_key)
+            .and_then(|b| b.into_public_key())
+        {
+            Ok(public_key) => public_key,
+            Err(e) => {
+                println!("Skipping invalid key {:?}: {:?}", key.key_dist_address, e);
+                continue;
+            }
+        };
+
+        if public_key.keynum() != signature.keynum() {
+            continue;
+        }
+
+        let mut reader = BufReader::new(File::open(check_file)?);
+        match minisign::verify(&public_key, signature, &mut reader, true, false, false) {
+            Ok(()) => passed_keys.push(key.clone()),
+            Err(_) => failed_keys.push(key.clone()),
+        }
+    }
+
+    Ok((passed_keys, failed_keys))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use holochain_types::prelude::{ActionHash, AgentPubKey};
+    use std::io::{Cursor, Write};
+
+    #[test]
+    fn sidecar_url_from_pattern() {
+        let fetch_url = Url::parse("https://example.com/tools/tool.tar.gz").unwrap();
+
+        assert_eq!(
+            "https://example.com/tools/tool.tar.gz.minisig",
+            make_sidecar_url(DEFAULT_SIDECAR_PATTERN, &fetch_url)
+                .unwrap()
+                .as_str()
+        );
+        assert_eq!(
+            "https://example.com/tools/tool.tar.gz.sig",
+            make_sidecar_url("{url}.sig", &fetch_url).unwrap().as_str()
+        );
+        assert!(make_sidecar_url("not a url {url}", &fetch_url).is_err());
+    }
+
+    #[test]
+    fn verify_only_with_matching_keys() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"test").unwrap();
+
+        let signer = minisign::KeyPair::generate_unencrypted_keypair().unwrap();
+        let other = minisign::KeyPair::generate_unencrypted_keypair().unwrap();
+
+        let signature = minisign::sign(None, &signer.sk, Cursor::new(b"test"), None, None).unwrap();
+
+        let make_key = |pk: &minisign::PublicKey, n: u8| SidecarKey {
+            key_dist_address: ActionHash::from_raw_36(vec![n; 36]),
+            author: AgentPubKey::from_raw_36(vec![n; 36]),
+            source: SidecarKeySource::Publisher,
+            verification_key: pk.to_box().unwrap().into_string(),
+        };
+
+        let (passed, failed) = verify_with_keys(
+            &signature,
+            file.path(),
+            &[make_key(&signer.pk, 1), make_key(&other.pk, 2)],
+        )
+        .unwrap();
+        assert_eq!(1, passed.len());
+        assert_eq!(
+            ActionHash::from_raw_36(vec![1; 36]),
+            passed[0].key_dist_address
+        );
+        assert!(failed.is_empty());
+
+        file.write_all(b"modified").unwrap();
+        let (passed, failed) =
+            verify_with_keys(&signature, file.path(), &[make_key(&signer.pk, 1)]).unwrap();
+        assert!(passed.is_empty());
+        assert_eq!(1, failed.len());
+    }
+}
@@ -1,63 +1,175 @@
+#[cfg(unix)]
+use crate::agent::AgentClient;
+use crate::checksums::{write_checksums_manifest, CHECKSUMS_FILE_NAME};
 use crate::cli::SignArgs;
 use crate::common::{
-    get_signing_key_path, get_store_dir, get_verification_key_path, open_file, unix_timestamp,
+    get_signing_key_path, get_store_dir, get_verification_key_path, open_file, sha256_file,
+    unix_timestamp,
 };
-use crate::hc_client;
+use crate::hc_client::{self, CredentialScope, HolochainClient};
 use crate::interactive::GetPassword;
 use anyhow::Context;
+use checked_types::canonical_url::canonicalize_url;
 use checked_types::{CreateAssetSignature, VerificationKeyType};
-use holochain_client::ZomeCallTarget;
 use holochain_types::prelude::{ActionHash, ExternIO};
-use minisign::{PublicKey, SecretKey};
+use minisign::{PublicKey, SecretKey, SignatureBox};
 use std::io::{BufReader, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
 
 /// Sign a file and optionally distribute the signature on Holochain.
+///
+/// If a directory is provided, then a [CHECKSUMS_FILE_NAME] manifest is created for the files in it
+/// and the manifest is signed instead. If the URL is for a directory, ending with `/`, then the
+/// signature is distributed for the manifest's URL in that directory.
 pub async fn sign(sign_args: SignArgs) -> anyhow::Result<PathBuf> {
+    sign_with_client(sign_args, None).await
+}
+
+/// [sign], distributing with a client that is already connected to Holochain if there is one.
+/// Otherwise a client is connected with the arguments.
+pub(crate) async fn sign_with_client(
+    mut sign_args: SignArgs,
+    app_client: Option<HolochainClient>,
+) -> anyhow::Result<PathBuf> {
     if !sign_args.file.exists() {
         anyhow::bail!("File to sign does not exist - {:?}", sign_args.file);
     }
 
-    if !sign_args.file.metadata()?.is_file() {
-        anyhow::bail!(
-            "Value provided for `--file` is not a file - {:?}",
-            sign_args.file
-        );
-    }
+    let signing_dir = sign_args.file.is_dir();
+    let (file, url) = if signing_dir {
+        let url = sign_args.url.clone().map(|url| {
+            if url.ends_with('/') {
+                format!("{}{}", url, CHECKSUMS_FILE_NAME)
+            } else {
+                url
+            }
+        });
 
-    let mut data_reader = BufReader::new(std::fs::File::open(&sign_args.file)?);
+        (sign_args.file.join(CHECKSUMS_FILE_NAME), url)
+    } else {
+        (sign_args.file.clone(), sign_args.url.clone())
+    };
 
-    let sig_path = sign_args.output.clone().unwrap_or_else(|| {
-        let p = sign_args.file.clone();
-        p.with_extension(format!(
-            "{}.minisig",
-            sign_args
-                .file
-                .extension()
-                .unwrap_or_default()
-                .to_str()
-                .unwrap_or_default()
-        ))
-    });
+    let sig_path = sign_args
+        .output
+        .clone()
+        .unwrap_or_else(|| default_signature_path(&file));
+    // Checked before a manifest is written, so that it can't be left with a stale signature.
+    if sig_path.exists() {
+        anyhow::bail!("Signature file already exists - {:?}", sig_path);
+    }
 
-    let mut sig_file = open_file(&sig_path)
-        .with_context(|| anyhow::anyhow!("Failed to open signature file: {:?}", &sig_path))?;
+    if signing_dir {
+        write_checksums_manifest(&sign_args.file, sign_args.overwrite_manifest)?;
+    }
+
+    if !file.metadata()?.is_file() {
+        anyhow::bail!("Value provided for `--file` is not a file - {:?}", file);
+    }
 
     let store_dir = get_store_dir(sign_args.config_dir.clone())?;
+    let name = sign_args.name.clone();
+    let mut signer = Signer::new(&store_dir, &name, || sign_args.get_password()).await?;
+    let vk_path = get_verification_key_path(&store_dir, &sign_args.name);
+
+    signer.sign_file(&file, &sig_path).await?;
+
+    println!("Signature created and saved in: {}", sig_path.display());
+
+    if !sign_args.distribute {
+        return Ok(sig_path);
+    }
+
+    let app_client = match app_client {
+        Some(app_client) => app_client,
+        None => {
+            let admin_port = sign_args.connect.admin_port().await?;
+
+            hc_client::get_authenticated_app_agent_client(
+                admin_port,
+                sign_args.config_dir.clone(),
+                sign_args.app_id,
+                sign_args.connect.timeout(),
+                CredentialScope::Write,
+            )
+            .await?
+        }
+    };
 
-    // Signing key
-    let sk_path = get_signing_key_path(&store_dir, &sign_args.name);
-    let sk = SecretKey::from_file(sk_path.clone(), Some(sign_args.get_password()?)).with_context(
-        || {
+    println!(
+        "Distributing signature to Holochain: {:?}",
+        std::fs::read_to_string(&sig_path)?
+    );
+
+    let fetch_url = url.ok_or_else(|| anyhow::anyhow!("URL is required for distribution"))?;
+
+    let response = app_client
+        .call_zome(
+            "fetch",
+            "create_asset_signature",
+            ExternIO::encode(make_create_asset_signature(
+                &file, &sig_path, &fetch_url, &vk_path,
+            )?)?,
+        )
+        .await
+        .context("Failed to report signature to Holochain")?;
+
+    let asset_signature_address: ActionHash = response.decode()?;
+    println!(
+        "Signature stored on Holochain at: {:?}",
+        asset_signature_address
+    );
+
+    Ok(sig_path)
+}
+
+/// The default location for the signature of `file`, which is `file` with `.minisig` appended.
+pub(crate) fn default_signature_path(file: &Path) -> PathBuf {
+    let mut p = file.to_path_buf().into_os_string();
+    p.push(".minisig");
+    PathBuf::from(p)
+}
+
+/// Prepare the signature at `sig_path`, for `file` published at `fetch_url`, to be distributed on
+/// Holochain.
+pub(crate) fn make_create_asset_signature(
+    file: &Path,
+    sig_path: &Path,
+    fetch_url: &str,
+    vk_path: &Path,
+) -> anyhow::Result<CreateAssetSignature> {
+    Ok(CreateAssetSignature {
+        // The zome canonicalizes the URL too, but store the canonical form so that it matches what
+        // other agents look up.
+        fetch_url: canonicalize_url(fetch_url)
+            .context("Invalid URL")?
+            .to_string(),
+        signature: std::fs::read_to_string(sig_path)?,
+        key_type: VerificationKeyType::MiniSignEd25519,
+        verification_key: std::fs::read_to_string(vk_path)?,
+        content_sha256: Some(sha256_file(file)?),
+    })
+}
+
+/// Load the signing key called `name`, and its verification key if it can be found.
+pub(crate) fn load_signing_keys(
+    store_dir: &Path,
+    name: &str,
+    mut password: Zeroizing<String>,
+) -> anyhow::Result<(SecretKey, Option<PublicKey>)> {
+    let sk_path = get_signing_key_path(store_dir, name);
+    // MiniSign takes ownership of the password and doesn't zeroize it, so hand over this buffer
+    // rather than making a copy.
+    let sk =
+        SecretKey::from_file(sk_path, Some(std::mem::take(&mut *password))).with_context(|| {
             anyhow::anyhow!(
                 "Signing key '{}' not found, maybe you need to create it first?",
-                sign_args.name
+                name
             )
-        },
-    )?;
+        })?;
 
-    // Verification key
-    let vk_path = get_verification_key_path(&store_dir, &sign_args.name);
+    let vk_path = get_verification_key_path(store_dir, name);
     let vk = match PublicKey::from_file(&vk_path) {
         Ok(vk) => Some(vk),
         Err(e) => {
@@ -66,70 +178,103 @@ pub async fn sign(sign_args: SignArgs) -> anyhow::Result<PathBuf> {
         }
     };
 
+    Ok((sk, vk))
+}
+
+/// Sign `file` with `sk` and save the signature to `sig_path`.
+pub(crate) fn sign_file(
+    file: &Path,
+    sig_path: &Path,
+    sk: &SecretKey,
+    vk: Option<&PublicKey>,
+) -> anyhow::Result<()> {
+    let sig = make_signature(file, sk, vk)?;
+    write_signature(sig_path, &sig.to_bytes())
+}
+
+/// Sign `file` with `sk`, with the file name and the time in the trusted comment.
+pub(crate) fn make_signature(
+    file: &Path,
+    sk: &SecretKey,
+    vk: Option<&PublicKey>,
+) -> anyhow::Result<SignatureBox> {
+    make_named_signature(file, file, sk, vk)
+}
+
+/// Sign `file` with `sk`, naming it as `name` in the trusted comment. This is for signing a copy of
+/// the file that was made elsewhere.
+pub(crate) fn make_named_signature(
+    file: &Path,
+    name: &Path,
+    sk: &SecretKey,
+    vk: Option<&PublicKey>,
+) -> anyhow::Result<SignatureBox> {
+    let mut data_reader = BufReader::new(std::fs::File::open(file)?);
+
     let trusted_comment = format!(
         "timestamp:{}\tfile:{}\tprehashed",
         unix_timestamp(),
-        sign_args
-            .file
-            .file_name()
-            .unwrap_or_default()
-            .to_string_lossy()
+        name.file_name().unwrap_or_default().to_string_lossy()
     );
 
-    let sig = minisign::sign(
-        vk.as_ref(),
-        &sk,
+    minisign::sign(
+        vk,
+        sk,
         &mut data_reader,
         Some(trusted_comment.as_str()),
         None,
     )
-    .with_context(|| anyhow::anyhow!("Failed to sign file: {:?}", sign_args.file))?;
-
-    sig_file.write_all(&sig.to_bytes())?;
-    sig_file.flush()?;
-
-    println!("Signature created and saved in: {}", sig_path.display());
+    .with_context(|| anyhow::anyhow!("Failed to sign file: {:?}", name))
+}
 
-    if !sign_args.distribute {
-        return Ok(sig_path);
-    }
+/// Save a signature to `sig_path`, which must not already exist.
+pub(crate) fn write_signature(sig_path: &Path, signature: &[u8]) -> anyhow::Result<()> {
+    let mut sig_file = open_file(sig_path)
+        .with_context(|| anyhow::anyhow!("Failed to open signature file: {:?}", sig_path))?;
 
-    let admin_port = sign_args.admin_port().await?;
+    sig_file.write_all(signature)?;
+    sig_file.flush()?;
 
-    let app_client = hc_client::get_authenticated_app_agent_client(
-        admin_port,
-        sign_args.config_dir.clone(),
-        sign_args.app_id,
-    )
-    .await?;
+    Ok(())
+}
 
-    println!(
-        "Distributing signature to Holochain: {:?}",
-        std::fs::read_to_string(&sig_path)?
-    );
+/// Signs files either with a key that has been loaded in this process, or by asking a running
+/// [agent](crate::agent::agent) that holds the key.
+pub(crate) enum Signer {
+    /// A key that was unlocked with its password.
+    Key(Box<SecretKey>, Option<PublicKey>),
+    /// An agent that holds the key.
+    #[cfg(unix)]
+    Agent(AgentClient),
+}
 
-    let response = app_client
-        .call_zome(
-            ZomeCallTarget::RoleName("checked".to_string()),
-            "fetch".into(),
-            "create_asset_signature".into(),
-            ExternIO::encode(CreateAssetSignature {
-                fetch_url: sign_args
-                    .url
-                    .ok_or_else(|| anyhow::anyhow!("URL is required for distribution"))?,
-                signature: std::fs::read_to_string(&sig_path)?,
-                key_type: VerificationKeyType::MiniSignEd25519,
-                verification_key: std::fs::read_to_string(vk_path)?,
-            })?,
-        )
-        .await
-        .map_err(|e| anyhow::anyhow!("Failed to report signature to Holochain: {:?}", e))?;
+impl Signer {
+    /// Use the agent if one is running with the key called `name`, otherwise load the key with
+    /// the password from `get_password`.
+    pub(crate) async fn new(
+        store_dir: &Path,
+        name: &str,
+        get_password: impl FnOnce() -> anyhow::Result<Zeroizing<String>>,
+    ) -> anyhow::Result<Self> {
+        #[cfg(unix)]
+        if let Some(agent_client) = AgentClient::connect(store_dir, name).await {
+            println!("Signing with the key '{}' held by the agent", name);
+            return Ok(Signer::Agent(agent_client));
+        }
 
-    let asset_signature_address: ActionHash = response.decode()?;
-    println!(
-        "Signature stored on Holochain at: {:?}",
-        asset_signature_address
-    );
+        let (sk, vk) = load_signing_keys(store_dir, name, get_password()?)?;
+        Ok(Signer::Key(Box::new(sk), vk))
+    }
 
-    Ok(sig_path)
+    /// Sign `file` and save the signature to `sig_path`.
+    pub(crate) async fn sign_file(&mut self, file: &Path, sig_path: &Path) -> anyhow::Result<()> {
+        match self {
+            Signer::Key(sk, vk) => sign_file(file, sig_path, sk, vk.as_ref()),
+            #[cfg(unix)]
+            Signer::Agent(agent_client) => {
+                let signature = agent_client.sign(file).await?;
+                write_signature(sig_path, signature.as_bytes())
+            }
+        }
+    }
 }
@@ -0,0 +1,226 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use holochain_types::prelude::{ActionHash, ExternIO};
+use url::Url;
+
+use crate::checksums::list_release_files;
+use crate::cli::SignReleaseArgs;
+use crate::common::{get_store_dir, get_verification_key_path};
+use crate::hc_client::{get_authenticated_app_agent_client, CredentialScope};
+use crate::interactive::GetPassword;
+use crate::sign::{default_signature_path, make_create_asset_signature, Signer};
+
+/// Sign every file in a release directory and optionally distribute all the signatures on Holochain.
+///
+/// The password for the signing key is only asked for once. Each file is expected to be published
+/// at the base URL joined with its file name, and the signatures are distributed together so that
+/// either all of them are stored or none of them are.
+///
+/// Returns the paths of the signatures that were created.
+pub async fn sign_release(mut sign_release_args: SignReleaseArgs) -> anyhow::Result<Vec<PathBuf>> {
+    if !sign_release_args.dir.is_dir() {
+        anyhow::bail!(
+            "Value provided for `--dir` is not a directory - {:?}",
+            sign_release_args.dir
+        );
+    }
+
+    let base_url = Url::parse(&sign_release_args.base_url).context("Invalid base URL")?;
+
+    let files = list_release_files(&sign_release_args.dir)?
+        .into_iter()
+        .filter(|name| match &sign_release_args.include {
+            Some(pattern) => wildcard_match(pattern, name),
+            None => true,
+        })
+        .collect::<Vec<_>>();
+
+    if files.is_empty() {
+        anyhow::bail!("No files to sign found in {:?}", sign_release_args.dir);
+    }
+
+    let mut releases = Vec::new();
+    for name in files {
+        let file = sign_release_args.dir.join(&name);
+        let sig_path = default_signature_path(&file);
+        let fetch_url = release_file_url(&base_url, &name)?;
+
+        releases.push((name, file, sig_path, fetch_url));
+    }
+
+    // Checked before anything is signed, so that a failure doesn't leave part of the release signed.
+    let existing = releases
+        .iter()
+        .filter(|(_, _, sig_path, _)| sig_path.exists())
+        .map(|(_, _, sig_path, _)| sig_path.display().to_string())
+        .collect::<Vec<_>>();
+    if !existing.is_empty() {
+        anyhow::bail!(
+            "Signatures already exist, remove them to sign the release again: {}",
+            existing.join(", ")
+        );
+    }
+
+    let store_dir = get_store_dir(sign_release_args.config_dir.clone())?;
+    let key_name = sign_release_args.name.clone();
+    let mut signer =
+        Signer::new(&store_dir, &key_name, || sign_release_args.get_password()).await?;
+
+    let mut signed = Vec::new();
+    for (name, file, sig_path, fetch_url) in releases {
+        signer.sign_file(&file, &sig_path).await?;
+        println!("Signed {} in: {}", name, sig_path.display());
+
+        signed.push((file, sig_path, fetch_url));
+    }
+
+    if !sign_release_args.distribute {
+        return Ok(signed
+            .into_iter()
+            .map(|(_, sig_path, _)| sig_path)
+            .collect());
+    }
+
+    let vk_path = get_verification_key_path(&store_dir, &sign_release_args.name);
+
+    let create_asset_signatures = signed
+        .iter()
+        .map(|(file, sig_path, fetch_url)| {
+            make_create_asset_signature(file, sig_path, fetch_url.as_str(), &vk_path)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let admin_port = sign_release_args.connect.admin_port().await?;
+
+    let app_client = get_authenticated_app_agent_client(
+        admin_port,
+        sign_release_args.config_dir.clone(),
+        sign_release_args.app_id.clone(),
+        sign_release_args.connect.timeout(),
+        CredentialScope::Write,
+    )
+    .await?;
+
+    println!(
+        "Distributing {} signatures to Holochain",
+        create_asset_signatures.len()
+    );
+
+    let response = app_client
+        .call_zome(
+            "fetch",
+            "create_asset_signatures",
+            ExternIO::encode(create_asset_signatures)?,
+        )
+        .await
+        .context("Failed to report signatures to Holochain")?;
+
+    let asset_signature_addresses: Vec<ActionHash> = response.decode()?;
+    for ((_, _, fetch_url), address) in signed.iter().zip(&asset_signature_addresses) {
+        println!(
+            "Signature for {} stored on Holochain at: {:?}",
+            fetch_url, address
+        );
+    }
+
+    Ok(signed
+        .into_iter()
+        .map(|(_, sig_path, _)| sig_path)
+        .collect())
+}
+
+/// The URL that the release file `name` is published at, which is `base_url` with `name` added as a
+/// path segment. Characters in `name` that have a meaning in URLs, such as `?`, `#` and `%`, are
+/// percent-encoded.
+fn release_file_url(base_url: &Url, name: &str) -> anyhow::Result<Url> {
+    let mut fetch_url = base_url.clone();
+    fetch_url
+        .path_segments_mut()
+        .map_err(|_| anyhow::anyhow!("Invalid base URL, it can't have a path - {}", base_url))?
+        .pop_if_empty()
+        .push(name);
+
+    Ok(fetch_url)
+}
+
+/// Match `name` against a `pattern` where `*` matches any run of characters and `?` matches any
+/// single character.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+
+    let (mut p, mut n) = (0, 0);
+    // The position of the last `*` in the pattern, and the position in the name that it matched up to.
+    let mut backtrack = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = backtrack {
+            p = star_p + 1;
+            n = star_n + 1;
+            backtrack = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{release_file_url, wildcard_match};
+    use url::Url;
+
+    #[test]
+    fn release_file_urls() {
+        for base_url in ["https://example.com/v1", "https://example.com/v1/"] {
+            let base_url = Url::parse(base_url).unwrap();
+
+            assert_eq!(
+                "https://example.com/v1/checked.tar.gz",
+                release_file_url(&base_url, "checked.tar.gz")
+                    .unwrap()
+                    .as_str()
+            );
+            assert_eq!(
+                "https://example.com/v1/a%23b%3Fc%25d%20e",
+                release_file_url(&base_url, "a#b?c%d e").unwrap().as_str()
+            );
+            assert_eq!(
+                "https://example.com/v1/c:foo",
+                release_file_url(&base_url, "c:foo").unwrap().as_str()
+            );
+        }
+
+        assert!(release_file_url(&Url::parse("mailto:a@example.com").unwrap(), "a").is_err());
+    }
+
+    #[test]
+    fn match_wildcards() {
+        assert!(wildcard_match("*", "checked-linux.tar.gz"));
+        assert!(wildcard_match("*.tar.gz", "checked-linux.tar.gz"));
+        assert!(wildcard_match("checked-*.tar.gz", "checked-linux.tar.gz"));
+        assert!(wildcard_match(
+            "checked-?????.tar.gz",
+            "checked-linux.tar.gz"
+        ));
+        assert!(wildcard_match(
+            "checked-linux.tar.gz",
+            "checked-linux.tar.gz"
+        ));
+
+        assert!(!wildcard_match("*.zip", "checked-linux.tar.gz"));
+        assert!(!wildcard_match(
+            "checked-????.tar.gz",
+            "checked-linux.tar.gz"
+        ));
+        assert!(!wildcard_match("checked", "checked-linux.tar.gz"));
+    }
+}
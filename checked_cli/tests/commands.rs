@@ -1,6 +1,8 @@
 //! Tests the commands that are exposed through the CLI.
 
-use checked_cli::prelude::{generate, sign, verify, GenerateArgs, SignArgs, VerifyArgs};
+use checked_cli::prelude::{
+    generate, sign, verify, GenerateArgs, SignArgs, VerifyArgs, CHECKSUMS_FILE_NAME,
+};
 use std::fs::File;
 use std::io::Write;
 
@@ -11,8 +13,9 @@ async fn generate_signing_keypair() -> anyhow::Result<()> {
 
     let info = generate(GenerateArgs {
         name: "test_generate".to_string(),
-        port: None,
+        connect: Default::default(),
         password: Some("test".to_string()),
+        password_source: Default::default(),
         distribute: Some(false),
         config_dir: Some(dir.as_ref().to_path_buf()),
         app_id: None,
@@ -33,8 +36,9 @@ async fn sign_file() -> anyhow::Result<()> {
     let name = "test_generate".to_string();
     generate(GenerateArgs {
         name: name.clone(),
-        port: None,
+        connect: Default::default(),
         password: Some("test".to_string()),
+        password_source: Default::default(),
         distribute: Some(false),
         config_dir: Some(dir.as_ref().to_path_buf()),
         app_id: None,
@@ -51,11 +55,13 @@ async fn sign_file() -> anyhow::Result<()> {
     let sig_path = sign(SignArgs {
         url: None,
         name: name.clone(),
-        port: None,
+        connect: Default::default(),
         password: Some("test".to_string()),
+        password_source: Default::default(),
         config_dir: Some(dir.as_ref().to_path_buf()),
         file: test_file.clone(),
         output: None,
+        overwrite_manifest: false,
         distribute: false,
         app_id: None,
     })
@@ -70,6 +76,82 @@ async fn sign_file() -> anyhow::Result<()> {
     Ok(())
 }
 
+// Sign a directory, which creates and signs a checksums manifest for the files in it.
+#[tokio::test(flavor = "multi_thread")]
+async fn sign_directory() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+
+    let name = "test_generate".to_string();
+    let info = generate(GenerateArgs {
+        name: name.clone(),
+        connect: Default::default(),
+        password: Some("test".to_string()),
+        password_source: Default::default(),
+        distribute: Some(false),
+        config_dir: Some(dir.as_ref().to_path_buf()),
+        app_id: None,
+    })
+    .await?;
+
+    let release_dir = dir.path().join("release");
+    std::fs::create_dir(&release_dir)?;
+    std::fs::write(release_dir.join("test.txt"), b"test")?;
+    std::fs::write(release_dir.join("other.txt"), b"other")?;
+
+    let sig_path = sign(SignArgs {
+        url: None,
+        name: name.clone(),
+        connect: Default::default(),
+        password: Some("test".to_string()),
+        password_source: Default::default(),
+        config_dir: Some(dir.as_ref().to_path_buf()),
+        file: release_dir.clone(),
+        output: None,
+        overwrite_manifest: false,
+        distribute: false,
+        app_id: None,
+    })
+    .await?;
+
+    let manifest_path = release_dir.join(CHECKSUMS_FILE_NAME);
+    assert_eq!(
+        format!("{}.minisig", manifest_path.to_str().unwrap()),
+        sig_path.to_str().unwrap()
+    );
+
+    let manifest = std::fs::read_to_string(&manifest_path)?;
+    assert_eq!(2, manifest.lines().count());
+    assert!(manifest
+        .contains("9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08  test.txt"));
+
+    verify(VerifyArgs {
+        file: manifest_path.clone(),
+        verification_key: info.vk_path,
+        signature: None,
+    })?;
+
+    // Signing again would leave the existing signature next to a new manifest.
+    std::fs::write(release_dir.join("new.txt"), b"new")?;
+    let resign = sign(SignArgs {
+        url: None,
+        name: name.clone(),
+        connect: Default::default(),
+        password: Some("test".to_string()),
+        password_source: Default::default(),
+        config_dir: Some(dir.as_ref().to_path_buf()),
+        file: release_dir.clone(),
+        output: None,
+        overwrite_manifest: true,
+        distribute: false,
+        app_id: None,
+    })
+    .await;
+    assert!(resign.is_err());
+    assert_eq!(manifest, std::fs::read_to_string(&manifest_path)?);
+
+    Ok(())
+}
+
 // Sign a file then check the signature is valid against the same file.
 #[tokio::test(flavor = "multi_thread")]
 async fn verify_signed_file() -> anyhow::Result<()> {
@@ -78,8 +160,9 @@ async fn verify_signed_file() -> anyhow::Result<()> {
     let name = "test_generate".to_string();
     let info = generate(GenerateArgs {
         name: name.clone(),
-        port: None,
+        connect: Default::default(),
         password: Some("test".to_string()),
+        password_source: Default::default(),
         distribute: Some(false),
         config_dir: Some(dir.as_ref().to_path_buf()),
         app_id: None,
@@ -96,11 +179,13 @@ async fn verify_signed_file() -> anyhow::Result<()> {
     sign(SignArgs {
         url: None,
         name: name.clone(),
-        port: None,
+        connect: Default::default(),
         password: Some("test".to_string()),
+        password_source: Default::default(),
         config_dir: Some(dir.as_ref().to_path_buf()),
         file: test_file.clone(),
         output: None,
+        overwrite_manifest: false,
         distribute: false,
         app_id: None,
     })
@@ -2,7 +2,11 @@
 
 use anyhow::Context;
 use checked_cli::prelude::{
-    distribute, fetch, generate, sign, DistributeArgs, FetchArgs, GenerateArgs, SignArgs,
+    claim_domain, daemon, distribute, fetch, fetch_manifest, generate, proxy, sign, sign_release,
+    verify_domain, ClaimDomainArgs, ConnectArgs, DaemonArgs, DistributeArgs, FetchArgs,
+    FetchPolicy, GenerateArgs, ProxyArgs, SignArgs, SignReleaseArgs, VerifyDomainArgs,
+    WellKnownChecked, WellKnownCheckedKey, ALLOW_WRITE_ENV, CREDENTIALS_PASSWORD_ENV,
+    DAEMON_TOKEN_FILE_NAME, DEFAULT_SIDECAR_PATTERN,
 };
 use checked_types::{AssetSignatureResponse, FetchCheckSignatureReason};
 use holochain::core::AgentPubKey;
@@ -13,9 +17,11 @@ use holochain_types::app::InstallAppPayload;
 use holochain_types::prelude::AppBundleSource;
 use holochain_types::websocket::AllowedOrigins;
 use signing_keys_types::VfKeyResponse;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::Write;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use tokio::task::AbortHandle;
 
 // Generate a signing keypair, distribute it on Holochain
@@ -30,8 +36,12 @@ async fn generate_signing_keypair() -> anyhow::Result<()> {
 
     generate(GenerateArgs {
         name: "test_generate".to_string(),
-        port: Some(admin_port),
+        connect: ConnectArgs {
+            port: Some(admin_port),
+            ..Default::default()
+        },
         password: Some("test".to_string()),
+        password_source: Default::default(),
         distribute: Some(true),
         config_dir: Some(dir.as_ref().to_path_buf()),
         app_id: None,
@@ -62,8 +72,9 @@ async fn distribute_existing_keypair() -> anyhow::Result<()> {
     let name = "test_generate".to_string();
     generate(GenerateArgs {
         name: name.clone(),
-        port: None,
+        connect: Default::default(),
         password: Some("test".to_string()),
+        password_source: Default::default(),
         distribute: Some(false),
         config_dir: Some(dir.as_ref().to_path_buf()),
         app_id: None,
@@ -79,9 +90,13 @@ async fn distribute_existing_keypair() -> anyhow::Result<()> {
     assert_eq!(0, keys.len());
 
     distribute(DistributeArgs {
-        port: Some(admin_port),
+        connect: ConnectArgs {
+            port: Some(admin_port),
+            ..Default::default()
+        },
         name,
         password: Some("test".to_string()),
+        password_source: Default::default(),
         config_dir: Some(dir.as_ref().to_path_buf()),
         app_id: None,
     })
@@ -111,8 +126,12 @@ async fn create_first_asset_signature() -> anyhow::Result<()> {
     let name = "test_generate".to_string();
     generate(GenerateArgs {
         name: name.clone(),
-        port: Some(admin_port),
+        connect: ConnectArgs {
+            port: Some(admin_port),
+            ..Default::default()
+        },
         password: Some("test".to_string()),
+        password_source: Default::default(),
         distribute: Some(true),
         config_dir: Some(dir.as_ref().to_path_buf()),
         app_id: None,
@@ -132,11 +151,16 @@ async fn create_first_asset_signature() -> anyhow::Result<()> {
     let signature_path = sign(SignArgs {
         url: Some(url.clone()),
         name: name.clone(),
-        port: Some(admin_port),
+        connect: ConnectArgs {
+            port: Some(admin_port),
+            ..Default::default()
+        },
         password: Some("test".to_string()),
+        password_source: Default::default(),
         config_dir: Some(dir.as_ref().to_path_buf()),
         file: content_path,
         output: None,
+        overwrite_manifest: false,
         distribute: true,
         app_id: None,
     })
@@ -158,6 +182,80 @@ async fn create_first_asset_signature() -> anyhow::Result<()> {
     Ok(())
 }
 
+// Sign every file in a release directory and distribute all the signatures together.
+#[tokio::test(flavor = "multi_thread")]
+async fn sign_and_distribute_release() -> anyhow::Result<()> {
+    let conductor = SweetConductor::from_standard_config().await;
+
+    install_checked_app(conductor.sweet_handle(), "checked").await?;
+    let admin_port = add_admin_port(conductor.sweet_handle()).await?;
+
+    let dir = tempfile::tempdir()?;
+
+    let name = "test_generate".to_string();
+    generate(GenerateArgs {
+        name: name.clone(),
+        connect: ConnectArgs {
+            port: Some(admin_port),
+            ..Default::default()
+        },
+        password: Some("test".to_string()),
+        password_source: Default::default(),
+        distribute: Some(true),
+        config_dir: Some(dir.as_ref().to_path_buf()),
+        app_id: None,
+    })
+    .await?;
+
+    let release_dir = dir.as_ref().join("release");
+    std::fs::create_dir(&release_dir)?;
+    std::fs::write(release_dir.join("tool-linux.tar.gz"), b"linux")?;
+    std::fs::write(release_dir.join("tool-macos.tar.gz"), b"macos")?;
+    std::fs::write(release_dir.join("notes.txt"), b"notes")?;
+
+    let signature_paths = sign_release(SignReleaseArgs {
+        dir: release_dir.clone(),
+        base_url: "https://example.com/releases/v1.2.3".to_string(),
+        include: Some("*.tar.gz".to_string()),
+        name,
+        connect: ConnectArgs {
+            port: Some(admin_port),
+            ..Default::default()
+        },
+        password: Some("test".to_string()),
+        password_source: Default::default(),
+        config_dir: Some(dir.as_ref().to_path_buf()),
+        distribute: true,
+        app_id: None,
+    })
+    .await?;
+
+    assert_eq!(2, signature_paths.len());
+    assert!(release_dir.join("tool-linux.tar.gz.minisig").exists());
+    assert!(!release_dir.join("notes.txt.minisig").exists());
+
+    let zome = get_zome_handle(&conductor, "checked", "fetch").await;
+
+    let signatures: Vec<AssetSignatureResponse> = conductor
+        .call_fallible(&zome, "get_my_asset_signatures", ())
+        .await?;
+
+    let mut fetch_urls = signatures
+        .iter()
+        .map(|s| s.fetch_url.as_str())
+        .collect::<Vec<_>>();
+    fetch_urls.sort();
+    assert_eq!(
+        vec![
+            "https://example.com/releases/v1.2.3/tool-linux.tar.gz",
+            "https://example.com/releases/v1.2.3/tool-macos.tar.gz",
+        ],
+        fetch_urls
+    );
+
+    Ok(())
+}
+
 // Given an asset that has already been signed by other agents, fetch the asset and use those
 // signatures to verify the asset.
 #[tokio::test(flavor = "multi_thread")]
@@ -188,8 +286,12 @@ async fn fetch_asset_signed_by_others() -> anyhow::Result<()> {
     let name = "test_generate".to_string();
     generate(GenerateArgs {
         name: name.clone(),
-        port: Some(admin_port),
+        connect: ConnectArgs {
+            port: Some(admin_port),
+            ..Default::default()
+        },
         password: Some("test".to_string()),
+        password_source: Default::default(),
         distribute: Some(true),
         config_dir: Some(dir.as_ref().to_path_buf()),
         app_id: None,
@@ -197,16 +299,26 @@ async fn fetch_asset_signed_by_others() -> anyhow::Result<()> {
     .await?;
 
     let fetch_info = fetch(FetchArgs {
-        url: url.clone(),
-        port: Some(admin_port),
+        url: Some(url.clone()),
+        manifest: None,
+        connect: ConnectArgs {
+            port: Some(admin_port),
+            ..Default::default()
+        },
         name,
         output: Some(dir.as_ref().to_path_buf()),
         password: Some("test".to_string()),
+        password_source: Default::default(),
         config_dir: Some(dir.as_ref().to_path_buf()),
         allow_no_signatures: Some(false),
         sign: Some(true),
         app_id: None,
         approve: Some(true),
+        offline: false,
+        include_aliases: false,
+        check_sidecar: false,
+        sidecar_pattern: DEFAULT_SIDECAR_PATTERN.to_string(),
+        checksums: None,
     })
     .await?;
 
@@ -277,8 +389,12 @@ async fn fetch_asset_signed_by_others_with_mismatches() -> anyhow::Result<()> {
     let name = "test_generate".to_string();
     generate(GenerateArgs {
         name: name.clone(),
-        port: Some(admin_port),
+        connect: ConnectArgs {
+            port: Some(admin_port),
+            ..Default::default()
+        },
         password: Some("test".to_string()),
+        password_source: Default::default(),
         distribute: Some(true),
         config_dir: Some(dir.as_ref().to_path_buf()),
         app_id: None,
@@ -286,16 +402,26 @@ async fn fetch_asset_signed_by_others_with_mismatches() -> anyhow::Result<()> {
     .await?;
 
     let fetch_info = fetch(FetchArgs {
-        url: url.clone(),
-        port: Some(admin_port),
+        url: Some(url.clone()),
+        manifest: None,
+        connect: ConnectArgs {
+            port: Some(admin_port),
+            ..Default::default()
+        },
         name,
         output: Some(dir.as_ref().to_path_buf()),
         password: Some("test".to_string()),
+        password_source: Default::default(),
         config_dir: Some(dir.as_ref().to_path_buf()),
         allow_no_signatures: Some(false),
         sign: Some(true),
         app_id: None,
         approve: Some(true),
+        offline: false,
+        include_aliases: false,
+        check_sidecar: false,
+        sidecar_pattern: DEFAULT_SIDECAR_PATTERN.to_string(),
+        checksums: None,
     })
     .await?;
 
@@ -351,8 +477,12 @@ async fn fetch_asset_download_error() -> anyhow::Result<()> {
     let name = "test_generate".to_string();
     generate(GenerateArgs {
         name: name.clone(),
-        port: Some(admin_port),
+        connect: ConnectArgs {
+            port: Some(admin_port),
+            ..Default::default()
+        },
         password: Some("test".to_string()),
+        password_source: Default::default(),
         distribute: Some(true),
         config_dir: Some(dir.as_ref().to_path_buf()),
         app_id: None,
@@ -360,16 +490,26 @@ async fn fetch_asset_download_error() -> anyhow::Result<()> {
     .await?;
 
     let fetch_err = fetch(FetchArgs {
-        url: url.clone() + ".nonexistent",
-        port: Some(admin_port),
+        url: Some(url.clone() + ".nonexistent"),
+        manifest: None,
+        connect: ConnectArgs {
+            port: Some(admin_port),
+            ..Default::default()
+        },
         name,
         output: Some(dir.as_ref().to_path_buf()),
         password: Some("test".to_string()),
+        password_source: Default::default(),
         config_dir: Some(dir.as_ref().to_path_buf()),
         allow_no_signatures: Some(true),
         sign: Some(true),
         app_id: None,
         approve: Some(true),
+        offline: false,
+        include_aliases: false,
+        check_sidecar: false,
+        sidecar_pattern: DEFAULT_SIDECAR_PATTERN.to_string(),
+        checksums: None,
     })
     .await
     .unwrap_err();
@@ -379,6 +519,433 @@ async fn fetch_asset_download_error() -> anyhow::Result<()> {
     Ok(())
 }
 
+// Fetch an asset that has no signature published next to it. The missing signature should be
+// reported without stopping the fetch.
+#[tokio::test(flavor = "multi_thread")]
+async fn fetch_asset_without_published_signature() -> anyhow::Result<()> {
+    let conductor = SweetConductor::from_standard_config().await;
+
+    let (addr, _fs_abort_handle) = start_sample_file_server().await;
+    let url = format!("http://{}:{}/test.txt", addr.ip(), addr.port());
+
+    let admin_port = add_admin_port(conductor.sweet_handle()).await?;
+
+    install_checked_app(conductor.sweet_handle(), "checked").await?;
+
+    let dir = tempfile::tempdir()?;
+
+    let name = "test_generate".to_string();
+    generate(GenerateArgs {
+        name: name.clone(),
+        connect: ConnectArgs {
+            port: Some(admin_port),
+            ..Default::default()
+        },
+        password: Some("test".to_string()),
+        password_source: Default::default(),
+        distribute: Some(true),
+        config_dir: Some(dir.as_ref().to_path_buf()),
+        app_id: None,
+    })
+    .await?;
+
+    let fetch_info = fetch(FetchArgs {
+        url: Some(url.clone()),
+        manifest: None,
+        connect: ConnectArgs {
+            port: Some(admin_port),
+            ..Default::default()
+        },
+        name,
+        output: Some(dir.as_ref().to_path_buf()),
+        password: Some("test".to_string()),
+        password_source: Default::default(),
+        config_dir: Some(dir.as_ref().to_path_buf()),
+        allow_no_signatures: Some(true),
+        sign: Some(false),
+        app_id: None,
+        approve: Some(true),
+        offline: false,
+        include_aliases: false,
+        check_sidecar: true,
+        sidecar_pattern: DEFAULT_SIDECAR_PATTERN.to_string(),
+        checksums: None,
+    })
+    .await?;
+
+    assert!(fetch_info.output_path.is_some());
+
+    let sidecar_report = fetch_info.sidecar_report.unwrap();
+    assert_eq!(
+        format!("{}.minisig", url),
+        sidecar_report.sidecar_url.as_str()
+    );
+    assert!(!sidecar_report.found);
+
+    Ok(())
+}
+
+// Fetch the assets listed in a manifest and write a lock file. A second fetch must fail if the
+// asset no longer matches the digest in the lock file.
+#[tokio::test(flavor = "multi_thread")]
+async fn fetch_assets_from_manifest() -> anyhow::Result<()> {
+    let conductor = SweetConductor::from_standard_config().await;
+
+    let (addr, _fs_abort_handle) = start_sample_file_server().await;
+    let url = format!("http://{}:{}/test.txt", addr.ip(), addr.port());
+
+    let admin_port = add_admin_port(conductor.sweet_handle()).await?;
+
+    install_checked_app(conductor.sweet_handle(), "checked").await?;
+
+    let dir = tempfile::tempdir()?;
+
+    let manifest_path = dir.as_ref().join("assets.toml");
+    std::fs::write(
+        &manifest_path,
+        format!(
+            r#"
+[policy]
+allow_no_signatures = true
+
+[[asset]]
+url = "{url}"
+output = "vendor/test.txt"
+"#
+        ),
+    )?;
+
+    let fetch_args = || FetchArgs {
+        url: None,
+        manifest: Some(manifest_path.clone()),
+        connect: ConnectArgs {
+            port: Some(admin_port),
+            ..Default::default()
+        },
+        name: "test_generate".to_string(),
+        output: None,
+        password: None,
+        password_source: Default::default(),
+        config_dir: Some(dir.as_ref().to_path_buf()),
+        allow_no_signatures: None,
+        sign: None,
+        app_id: None,
+        approve: None,
+        offline: false,
+        include_aliases: false,
+        check_sidecar: false,
+        sidecar_pattern: DEFAULT_SIDECAR_PATTERN.to_string(),
+        checksums: None,
+    };
+
+    let info = fetch_manifest(fetch_args()).await?;
+
+    assert_eq!(dir.as_ref().join("assets.lock"), info.lock_path);
+    assert_eq!(1, info.lock.assets.len());
+    assert_eq!(
+        "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08",
+        info.lock.assets[0].sha256
+    );
+    assert_eq!(
+        "test",
+        std::fs::read_to_string(dir.as_ref().join("vendor/test.txt"))?
+    );
+
+    // Pretend that the asset was different when it was locked.
+    let mut lock = info.lock.clone();
+    lock.assets[0].sha256 = "0".repeat(64);
+    std::fs::write(&info.lock_path, toml::to_string(&lock)?)?;
+
+    let err = fetch_manifest(fetch_args()).await.unwrap_err();
+    assert!(err.to_string().contains("does not match"));
+
+    Ok(())
+}
+
+// Fetch an asset through the proxy, which only serves it once its signatures pass the policy, and
+// then serves it again from the cache.
+#[tokio::test(flavor = "multi_thread")]
+async fn fetch_asset_through_proxy() -> anyhow::Result<()> {
+    let conductor = SweetConductor::from_standard_config().await;
+
+    let (addr, _fs_abort_handle) = start_sample_file_server().await;
+    let url = format!("http://{}:{}/test.txt", addr.ip(), addr.port());
+
+    let admin_port = add_admin_port(conductor.sweet_handle()).await?;
+
+    for i in 0..2 {
+        publish_asset_signature(
+            conductor.sweet_handle(),
+            &url,
+            admin_port,
+            format!("checked-{i}"),
+            false,
+        )
+        .await
+        .context("Couldn't publish asset signature")?;
+    }
+
+    install_checked_app(conductor.sweet_handle(), "checked").await?;
+
+    let dir = tempfile::tempdir()?;
+
+    let listen = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+    let proxy_handle = tokio::task::spawn(proxy(ProxyArgs {
+        listen,
+        allow_remote: false,
+        connect: ConnectArgs {
+            port: Some(admin_port),
+            ..Default::default()
+        },
+        config_dir: Some(dir.as_ref().to_path_buf()),
+        cache_dir: None,
+        cache_ttl: 3600,
+        offline: false,
+        include_aliases: false,
+        policy: FetchPolicy::default(),
+        app_id: None,
+    }));
+    let _proxy_abort_handle = DropAbortHandle(proxy_handle.abort_handle());
+
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://{}", listen))?)
+        .build()?;
+
+    // The proxy connects to Holochain before it starts listening.
+    let mut attempts = 0;
+    let response = loop {
+        match client.get(&url).send().await {
+            Ok(response) => break response,
+            Err(e) if e.is_connect() && attempts < 100 => {
+                attempts += 1;
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    };
+
+    assert_eq!(reqwest::StatusCode::OK, response.status());
+    assert_eq!("miss", response.headers()["X-Checked-Cache"]);
+    assert_eq!(
+        "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08",
+        response.headers()["X-Checked-Sha256"]
+    );
+    assert_eq!("test", response.text().await?);
+
+    let response = client.get(&url).send().await?;
+    assert_eq!(reqwest::StatusCode::OK, response.status());
+    assert_eq!("hit", response.headers()["X-Checked-Cache"]);
+    assert_eq!("test", response.text().await?);
+
+    Ok(())
+}
+
+// Check and fetch an asset through the daemon's HTTP API, which only accepts requests with its
+// token and only saves assets inside its output directory.
+#[tokio::test(flavor = "multi_thread")]
+async fn fetch_asset_through_daemon() -> anyhow::Result<()> {
+    let conductor = SweetConductor::from_standard_config().await;
+
+    let (addr, _fs_abort_handle) = start_sample_file_server().await;
+    let url = format!("http://{}:{}/test.txt", addr.ip(), addr.port());
+
+    let admin_port = add_admin_port(conductor.sweet_handle()).await?;
+
+    publish_asset_signature(
+        conductor.sweet_handle(),
+        &url,
+        admin_port,
+        "checked-0".to_string(),
+        false,
+    )
+    .await
+    .context("Couldn't publish asset signature")?;
+
+    install_checked_app(conductor.sweet_handle(), "checked").await?;
+
+    let dir = tempfile::tempdir()?;
+    let output_dir = tempfile::tempdir()?;
+
+    let listen = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+    let daemon_handle = tokio::task::spawn(daemon(DaemonArgs {
+        listen,
+        #[cfg(unix)]
+        socket: None,
+        connect: ConnectArgs {
+            port: Some(admin_port),
+            ..Default::default()
+        },
+        config_dir: Some(dir.as_ref().to_path_buf()),
+        key_timeout: 900,
+        output_dir: Some(output_dir.as_ref().to_path_buf()),
+        policy: FetchPolicy::default(),
+        app_id: None,
+    }));
+    let _daemon_abort_handle = DropAbortHandle(daemon_handle.abort_handle());
+
+    let client = reqwest::Client::new();
+    let api_url = |path: &str| format!("http://{}{}", listen, path);
+
+    // The daemon connects to Holochain before it starts listening.
+    let mut attempts = 0;
+    let response = loop {
+        match client.get(api_url("/keys")).send().await {
+            Ok(response) => break response,
+            Err(e) if e.is_connect() && attempts < 100 => {
+                attempts += 1;
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    };
+    assert_eq!(reqwest::StatusCode::UNAUTHORIZED, response.status());
+
+    let token = std::fs::read_to_string(dir.as_ref().join(DAEMON_TOKEN_FILE_NAME))?;
+
+    let response = client
+        .get(api_url("/keys"))
+        .bearer_auth(&token)
+        .header("Origin", "https://example.com")
+        .send()
+        .await?;
+    assert_eq!(reqwest::StatusCode::FORBIDDEN, response.status());
+
+    let response = client
+        .post(api_url("/check"))
+        .bearer_auth(&token)
+        .body(serde_json::json!({ "url": url }).to_string())
+        .send()
+        .await?;
+    assert_eq!(
+        reqwest::StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        response.status()
+    );
+
+    let response = client
+        .post(api_url("/check"))
+        .bearer_auth(&token)
+        .header("Content-Type", "application/json")
+        .body(serde_json::json!({ "url": url }).to_string())
+        .send()
+        .await?;
+    assert_eq!(reqwest::StatusCode::OK, response.status());
+    let check: serde_json::Value = serde_json::from_str(&response.text().await?)?;
+    assert_eq!(true, check["accepted"]);
+    assert_eq!(
+        "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08",
+        check["sha256"]
+    );
+
+    let response = client
+        .post(api_url("/fetch"))
+        .bearer_auth(&token)
+        .header("Content-Type", "application/json")
+        .body(serde_json::json!({ "url": url, "output": "../test.txt" }).to_string())
+        .send()
+        .await?;
+    assert_eq!(reqwest::StatusCode::BAD_REQUEST, response.status());
+
+    let response = client
+        .post(api_url("/fetch"))
+        .bearer_auth(&token)
+        .header("Content-Type", "application/json")
+        .body(serde_json::json!({ "url": url, "output": "vendor/test.txt" }).to_string())
+        .send()
+        .await?;
+    assert_eq!(reqwest::StatusCode::OK, response.status());
+    assert_eq!(
+        "test",
+        std::fs::read_to_string(output_dir.as_ref().join("vendor/test.txt"))?
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn verify_domain_against_well_known() -> anyhow::Result<()> {
+    let conductor = SweetConductor::from_standard_config().await;
+
+    let agent = install_checked_app(conductor.sweet_handle(), "checked").await?;
+    let admin_port = add_admin_port(conductor.sweet_handle()).await?;
+
+    let dir = tempfile::tempdir()?;
+
+    generate(GenerateArgs {
+        name: "test_generate".to_string(),
+        connect: ConnectArgs {
+            port: Some(admin_port),
+            ..Default::default()
+        },
+        password: Some("test".to_string()),
+        password_source: Default::default(),
+        distribute: Some(true),
+        config_dir: Some(dir.as_ref().to_path_buf()),
+        app_id: None,
+    })
+    .await?;
+
+    claim_domain(ClaimDomainArgs {
+        domain: "example.com".to_string(),
+        connect: ConnectArgs {
+            port: Some(admin_port),
+            ..Default::default()
+        },
+        name: "test_generate".to_string(),
+        config_dir: Some(dir.as_ref().to_path_buf()),
+        app_id: None,
+    })
+    .await?;
+
+    let verification_key = std::fs::read_to_string(dir.as_ref().join("test_generate.pub"))?;
+    let well_known = |agent: String| {
+        serde_json::to_string(&WellKnownChecked {
+            keys: vec![WellKnownCheckedKey {
+                agent,
+                verification_key: verification_key.clone(),
+            }],
+        })
+        .unwrap()
+    };
+
+    let other_agent = AgentPubKey::from_raw_36(vec![1; 36]);
+    let (addr, _abort_handle) = start_well_known_server(HashMap::from([
+        (
+            "/verified/.well-known/checked.json".to_string(),
+            well_known(agent.to_string()),
+        ),
+        (
+            "/mismatched/.well-known/checked.json".to_string(),
+            well_known(other_agent.to_string()),
+        ),
+    ]))
+    .await;
+
+    let verify_domain_args = |path: &str| VerifyDomainArgs {
+        domain: "example.com".to_string(),
+        connect: ConnectArgs {
+            port: Some(admin_port),
+            ..Default::default()
+        },
+        config_dir: Some(dir.as_ref().to_path_buf()),
+        app_id: None,
+        refresh: true,
+        well_known_url: Some(
+            url::Url::parse(&format!("http://{}{}/.well-known/checked.json", addr, path)).unwrap(),
+        ),
+    };
+
+    let claims = verify_domain(verify_domain_args("/verified")).await?;
+    assert_eq!(1, claims.len());
+    assert_eq!(agent, claims[0].author);
+    assert!(claims[0].verified);
+
+    let claims = verify_domain(verify_domain_args("/mismatched")).await?;
+    assert_eq!(1, claims.len());
+    assert!(!claims[0].verified);
+
+    Ok(())
+}
+
 async fn install_checked_app(
     conductor: SweetConductorHandle,
     app_id: &str,
@@ -427,6 +994,12 @@ async fn install_checked_app(
 }
 
 async fn add_admin_port(conductor: SweetConductorHandle) -> anyhow::Result<u16> {
+    // Every test uses the same passphrase for its saved Holochain credentials, so that they don't
+    // prompt for one.
+    std::env::set_var(CREDENTIALS_PASSWORD_ENV, "test-credentials-password");
+    // Tests have no terminal to confirm write access on.
+    std::env::set_var(ALLOW_WRITE_ENV, "1");
+
     let admin_port = conductor
         .clone()
         .add_admin_interfaces(vec![AdminInterfaceConfig {
@@ -487,6 +1060,49 @@ async fn start_sample_file_server() -> (SocketAddr, DropAbortHandle) {
     (addr, DropAbortHandle(join_handle.abort_handle()))
 }
 
+/// Serve each document in `documents` at its path, like a domain serving its `.well-known` files.
+async fn start_well_known_server(
+    documents: HashMap<String, String>,
+) -> (SocketAddr, DropAbortHandle) {
+    use bytes::Bytes;
+    use http_body_util::Full;
+    use hyper::server::conn::http1;
+    use hyper::service::service_fn;
+    use hyper::{Response, StatusCode};
+    use hyper_util::rt::TokioIo;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let documents = Arc::new(documents);
+
+    let join_handle = tokio::task::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let documents = documents.clone();
+            tokio::spawn(async move {
+                let service = service_fn(move |request: hyper::Request<hyper::body::Incoming>| {
+                    let response = match documents.get(request.uri().path()) {
+                        Some(document) => Response::new(Full::new(Bytes::from(document.clone()))),
+                        None => {
+                            let mut response = Response::new(Full::new(Bytes::new()));
+                            *response.status_mut() = StatusCode::NOT_FOUND;
+                            response
+                        }
+                    };
+                    async move { Ok::<_, std::convert::Infallible>(response) }
+                });
+                if let Err(e) = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await
+                {
+                    eprintln!("Well-known connection failed: {:?}", e);
+                }
+            });
+        }
+    });
+
+    (addr, DropAbortHandle(join_handle.abort_handle()))
+}
+
 struct DropAbortHandle(AbortHandle);
 
 impl Drop for DropAbortHandle {
@@ -511,8 +1127,12 @@ async fn publish_asset_signature(
     let name = "test_generate".to_string();
     generate(GenerateArgs {
         name: name.clone(),
-        port: Some(admin_port),
+        connect: ConnectArgs {
+            port: Some(admin_port),
+            ..Default::default()
+        },
         password: Some("test".to_string()),
+        password_source: Default::default(),
         distribute: Some(true),
         config_dir: Some(dir.as_ref().to_path_buf()),
         app_id: Some(app_id.clone()),
@@ -521,16 +1141,26 @@ async fn publish_asset_signature(
     .context("Generating signing keypair failed")?;
 
     let fetch_info = fetch(FetchArgs {
-        url: url.to_string(),
-        port: Some(admin_port),
+        url: Some(url.to_string()),
+        manifest: None,
+        connect: ConnectArgs {
+            port: Some(admin_port),
+            ..Default::default()
+        },
         name: name.clone(),
         output: Some(dir.as_ref().to_path_buf()),
         password: Some("test".to_string()),
+        password_source: Default::default(),
         config_dir: Some(dir.as_ref().to_path_buf()),
         allow_no_signatures: Some(true),
         sign: Some(!bad_signature),
         app_id: Some(app_id.clone()),
         approve: Some(true),
+        offline: false,
+        include_aliases: false,
+        check_sidecar: false,
+        sidecar_pattern: DEFAULT_SIDECAR_PATTERN.to_string(),
+        checksums: None,
     })
     .await
     .context("Fetch failed")?;
@@ -547,11 +1177,16 @@ async fn publish_asset_signature(
         sign(SignArgs {
             url: Some(url.to_string()),
             name: name.clone(),
-            port: Some(admin_port),
+            connect: ConnectArgs {
+                port: Some(admin_port),
+                ..Default::default()
+            },
             password: Some("test".to_string()),
+            password_source: Default::default(),
             config_dir: Some(dir.as_ref().to_path_buf()),
             file: output_path,
             output: None,
+            overwrite_manifest: false,
             distribute: true,
             app_id: Some(app_id.clone()),
         })
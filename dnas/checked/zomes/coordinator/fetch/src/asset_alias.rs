@@ -0,0 +1,165 @@
+use hdk::prelude::*;
+
+use checked_types::*;
+use fetch_integrity::prelude::*;
+
+use crate::asset_signature::find_my_existing_signature;
+
+/// Assert that two URLs, which you have both signed with the same key, serve the same asset.
+///
+/// Once the alias exists, anyone fetching from either URL can ask for signatures made for the other
+/// URL to be included too.
+#[hdk_extern]
+pub fn create_asset_alias(request: CreateAssetAlias) -> ExternResult<ActionHash> {
+    let fetch_url_signature = find_my_existing_signature(&make_asset_url_addresses(
+        &request.fetch_url,
+    )?)?
+    .ok_or_else(|| {
+        wasm_error!(WasmErrorInner::Guest(format!(
+            "You have not signed the asset at: {}",
+            request.fetch_url
+        )))
+    })?;
+
+    let alias_url_signature = find_my_existing_signature(&make_asset_url_addresses(
+        &request.alias_url,
+    )?)?
+    .ok_or_else(|| {
+        wasm_error!(WasmErrorInner::Guest(format!(
+            "You have not signed the asset at: {}",
+            request.alias_url
+        )))
+    })?;
+
+    let alias_address = create_entry(EntryTypes::AssetAlias(AssetAlias {
+        fetch_url: request.fetch_url.clone(),
+        alias_url: request.alias_url.clone(),
+        fetch_url_signature_address: fetch_url_signature.signature_address,
+        alias_url_signature_address: alias_url_signature.signature_address,
+    }))?;
+
+    for url in [&request.fetch_url, &request.alias_url] {
+        create_link(
+            make_asset_url_address(url)?,
+            alias_address.clone(),
+            LinkTypes::AssetUrlToAlias,
+            (),
+        )?;
+    }
+
+    Ok(alias_address)
+}
+
+/// Find the URLs that have been asserted to serve the same asset as the URL with the given
+/// `asset_bases`.
+///
+/// Each URL is returned once, however many aliases point to it.
+pub(crate) fn get_aliased_urls(
+    asset_bases: &[ExternalHash],
+    get_options: GetOptions,
+) -> ExternResult<Vec<String>> {
+    let mut links = Vec::new();
+    for asset_base in asset_bases {
+        links.extend(get_links(
+            GetLinksInputBuilder::try_new(asset_base.clone(), LinkTypes::AssetUrlToAlias)?
+                .get_options(get_options.strategy)
+                .build(),
+        )?);
+    }
+
+    let get_inputs = links
+        .into_iter()
+        .filter_map(|link| link.target.into_action_hash())
+        .map(|address| GetInput::new(address.into(), get_options.clone()))
+        .collect::<Vec<_>>();
+
+    let records = HDK.with(|h| h.borrow().get(get_inputs))?;
+
+    let mut seen_bases = asset_bases.iter().cloned().collect::<HashSet<_>>();
+    let mut aliased_urls = Vec::new();
+    for record in records.into_iter().flatten() {
+        let asset_alias: AssetAlias = match record.entry.to_app_option() {
+            Ok(Some(asset_alias)) => asset_alias,
+            _ => {
+                warn!("Ignoring alias link to an invalid asset alias");
+                continue;
+            }
+        };
+
+        // The alias could have been linked from either of its URLs, we want the other one.
+        let other_url = if make_asset_url_addresses(&asset_alias.fetch_url)?
+            .iter()
+            .any(|base| asset_bases.contains(base))
+        {
+            asset_alias.alias_url
+        } else {
+            asset_alias.fetch_url
+        };
+
+        if seen_bases.insert(make_asset_url_address(&other_url)?) {
+            aliased_urls.push(other_url);
+        }
+    }
+
+    Ok(aliased_urls)
+}
+
+/// Delete any of the calling agent's aliases that were linked from the given `asset_bases`, along
+/// with the links to them from both of their URLs.
+///
+/// An alias is only valid while both of the signatures behind it are, so this must be done when
+/// either signature is deleted.
+pub(crate) fn delete_my_asset_aliases(asset_bases: &[ExternalHash]) -> ExternResult<()> {
+    let my_agent = agent_info()?.agent_initial_pubkey;
+
+    let mut alias_addresses = HashSet::new();
+    for asset_base in asset_bases {
+        for link in get_links(
+            GetLinksInputBuilder::try_new(asset_base.clone(), LinkTypes::AssetUrlToAlias)?
+                .get_options(GetStrategy::Local)
+                .build(),
+        )? {
+            if link.author != my_agent {
+                continue;
+            }
+
+            if let Some(address) = link.target.into_action_hash() {
+                alias_addresses.insert(address);
+            }
+        }
+    }
+
+    for alias_address in alias_addresses {
+        let asset_alias: AssetAlias = match get(alias_address.clone(), GetOptions::local())? {
+            Some(record) => record
+                .entry
+                .to_app_option()
+                .map_err(|e| wasm_error!(WasmErrorInner::Guest(e.to_string())))?
+                .ok_or_else(|| {
+                    wasm_error!(WasmErrorInner::Guest("Asset alias not found".to_string()))
+                })?,
+            None => continue,
+        };
+
+        for url in [&asset_alias.fetch_url, &asset_alias.alias_url] {
+            for link in get_links(
+                GetLinksInputBuilder::try_new(
+                    make_asset_url_address(url)?,
+                    LinkTypes::AssetUrlToAlias,
+                )?
+                .get_options(GetStrategy::Local)
+                .build(),
+            )? {
+                if link.author == my_agent
+                    && link.target.clone().into_action_hash().as_ref() == Some(&alias_address)
+                {
+                    delete_link(link.create_link_hash)?;
+                }
+            }
+        }
+
+        delete(alias_address)?;
+    }
+
+    Ok(())
+}
@@ -1,56 +1,110 @@
 use std::cmp::min;
+use std::collections::HashMap;
 use std::ops::{Add, Deref, Sub};
 use std::time::Duration;
 
 use hdk::prelude::hash_type::AnyLinkable;
 use hdk::prelude::*;
-use rand::prelude::{IteratorRandom, SliceRandom};
+use rand::prelude::SliceRandom;
 
 use checked_types::*;
 use fetch_integrity::prelude::*;
 use signing_keys_types::*;
 
+use crate::asset_alias::{delete_my_asset_aliases, get_aliased_urls};
+use crate::publisher_claim::get_publisher_key_dist_addresses;
+use crate::vf_key_dist_fetcher::{CachingVfKeyDistFetcher, VfKeyDistFetcher, ZomeVfKeyDistFetcher};
+
 #[hdk_extern]
 fn prepare_fetch(request: PrepareFetchRequest) -> ExternResult<Vec<FetchCheckSignature>> {
-    let asset_base = make_asset_url_address(&request.fetch_url)?;
+    // We're online anyway to do a download so go looking for new data.
+    prepare_fetch_with_get_options(request, GetOptions::network())
+}
+
+/// Like [prepare_fetch], but only considers data that this node already holds. Useful when the
+/// conductor has no peers, at the cost of possibly missing recent signatures and key marks.
+#[hdk_extern]
+fn prepare_fetch_local(request: PrepareFetchRequest) -> ExternResult<Vec<FetchCheckSignature>> {
+    prepare_fetch_with_get_options(request, GetOptions::local())
+}
+
+fn prepare_fetch_with_get_options(
+    request: PrepareFetchRequest,
+    get_options: GetOptions,
+) -> ExternResult<Vec<FetchCheckSignature>> {
+    let asset_bases = make_asset_url_addresses(&request.fetch_url)?;
 
     info!(
-        "Fetching signatures for: {}, using as base: {:?}",
-        request.fetch_url, asset_base
+        "Fetching signatures for: {}, using as bases: {:?}",
+        request.fetch_url, asset_bases
     );
 
-    // We're online anyway to do a download so go looking for new data.
-    let links = get_links(
-        GetLinksInputBuilder::try_new(asset_base.clone(), LinkTypes::AssetUrlToSignature)?
-            .get_options(GetStrategy::Network)
-            .build(),
-    )?;
+    let mut links = Vec::new();
+    for asset_base in &asset_bases {
+        links.extend(get_links(
+            GetLinksInputBuilder::try_new(asset_base.clone(), LinkTypes::AssetUrlToSignature)?
+                .get_options(get_options.strategy)
+                .build(),
+        )?);
+    }
 
     info!("Found {} signature links", links.len());
 
-    let mut signatures = Vec::new();
-    for link in links {
-        let signature_action: ActionHash = link.target.try_into().map_err(
-            |e: HashConversionError<AnyLinkable, hash_type::Action>| {
-                wasm_error!(WasmErrorInner::Guest(format!(
-                    "Signature target is not an action: {:?}",
-                    e
-                )))
-            },
-        )?;
+    // The links tell us who created each signature, when, and with which key. That's enough to
+    // figure out which ones we want to keep before fetching any of the signatures themselves.
+    let mut possible_signatures = links
+        .into_iter()
+        .filter_map(|link| match SignatureCandidate::try_from(link) {
+            Ok(candidate) => Some(candidate),
+            Err(e) => {
+                warn!("Ignoring invalid signature link: {:?}", e);
+                None
+            }
+        })
+        .collect::<Vec<_>>();
 
-        if let Some(r) = get(signature_action, GetOptions::network())? {
-            info!("Got a signature record");
-            signatures.push(r);
+    // Signatures found through an alias, keyed by signature address, with the URL they were made for.
+    let mut alias_urls = HashMap::new();
+    if request.include_aliases {
+        for alias_url in get_aliased_urls(&asset_bases, get_options.clone())? {
+            for alias_base in make_asset_url_addresses(&alias_url)? {
+                for link in get_links(
+                    GetLinksInputBuilder::try_new(alias_base, LinkTypes::AssetUrlToSignature)?
+                        .get_options(get_options.strategy)
+                        .build(),
+                )? {
+                    let candidate = match SignatureCandidate::try_from(link) {
+                        Ok(candidate) => candidate,
+                        Err(e) => {
+                            warn!("Ignoring invalid signature link: {:?}", e);
+                            continue;
+                        }
+                    };
+
+                    if possible_signatures
+                        .iter()
+                        .any(|c| c.signature_address == candidate.signature_address)
+                    {
+                        continue;
+                    }
+
+                    alias_urls.insert(candidate.signature_address.clone(), alias_url.clone());
+                    possible_signatures.push(candidate);
+                }
+            }
         }
-    }
 
-    // Now we have a list of signatures, we know who created them and when. Next is figuring out which ones we want to keep.
+        info!("Found {} signatures through aliases", alias_urls.len());
+    }
 
     let key_collections: Vec<KeyCollectionWithKeys> = match call(
         CallTargetCell::Local,
         "signing_keys".to_string(),
-        "get_my_key_collections".into(),
+        match get_options.strategy {
+            GetStrategy::Network => "get_my_key_collections",
+            GetStrategy::Local => "get_my_key_collections_local",
+        }
+        .into(),
         None,
         (),
     )? {
@@ -66,73 +120,90 @@ fn prepare_fetch(request: PrepareFetchRequest) -> ExternResult<Vec<FetchCheckSig
 
     info!("Found {} key collections", key_collections.len());
 
-    let signatures: Vec<(Action, AssetSignature)> = signatures
-        .into_iter()
-        .filter_map(|record| {
-            let action = record.signed_action.action().clone();
-
-            record
-                .entry
-                .to_app_option()
-                .ok()
-                .flatten()
-                .map(|sig| (action, sig))
-        })
-        .collect();
-
     let my_agent = agent_info()?.agent_initial_pubkey;
 
-    let my_existing_signature = find_my_existing_signature(asset_base)?;
+    let my_existing_signature = find_my_existing_signature(&asset_bases)?;
 
-    Ok(pick_signatures(
-        signatures,
+    let publisher_key_dist_addresses =
+        get_publisher_key_dist_addresses(&request.fetch_url, get_options.clone())?;
+
+    let picked_signatures = pick_signatures(
+        possible_signatures,
         key_collections,
         my_existing_signature,
-        get_vf_key_dist,
+        &publisher_key_dist_addresses,
+        &mut CachingVfKeyDistFetcher::new(ZomeVfKeyDistFetcher::new(&get_options)),
         sys_time()?,
         &my_agent,
-    ))
+    );
+
+    resolve_picked_signatures(picked_signatures, get_options, &alias_urls)
 }
 
 #[hdk_extern]
 pub fn create_asset_signature(
     create_asset_signature: CreateAssetSignature,
 ) -> ExternResult<ActionHash> {
-    let my_keys: Vec<VfKeyResponse> = match call(
-        CallTargetCell::Local,
-        "signing_keys".to_string(),
-        "get_my_verification_key_distributions".into(),
-        None,
-        (),
-    )? {
-        ZomeCallResponse::Ok(response) => response
-            .decode()
-            .map_err(|e| wasm_error!(WasmErrorInner::Guest(e.to_string())))?,
-        _ => {
-            return Err(wasm_error!(WasmErrorInner::Guest(
-                "Unexpected response from signing_keys".into()
-            )))
-        }
-    };
+    let key_dist_address = get_my_key_dist_address(
+        create_asset_signature.key_type.clone(),
+        &create_asset_signature.verification_key,
+    )?;
 
-    let key_dist_address = match create_asset_signature.key_type {
-        VerificationKeyType::MiniSignEd25519 => {
-            let verification_key =
-                minisign_verify::PublicKey::decode(create_asset_signature.verification_key.trim())
-                    .map_err(|e| wasm_error!(WasmErrorInner::Guest(e.to_string())))?;
-            find_key_address(create_asset_signature.key_type, verification_key, &my_keys)
-        }
+    create_asset_signature_for_key(create_asset_signature, key_dist_address)
+}
+
+/// Create many asset signatures in a single commit, so that either all of them are stored or none
+/// of them are.
+///
+/// Every signature counts towards the asset signature rate limit, so a batch that is larger than
+/// the limit is rejected up front rather than failing validation part way through.
+#[hdk_extern]
+pub fn create_asset_signatures(
+    create_asset_signatures: Vec<CreateAssetSignature>,
+) -> ExternResult<Vec<ActionHash>> {
+    let rate_limit = get_rate_limits()?.asset_signature;
+    if create_asset_signatures.len() > rate_limit.max_count as usize {
+        return Err(wasm_error!(WasmErrorInner::Guest(format!(
+            "Too many asset signatures in one batch, at most {} are allowed every {} seconds",
+            rate_limit.max_count, rate_limit.window_seconds
+        ))));
     }
-    .ok_or_else(|| {
-        wasm_error!(WasmErrorInner::Guest(
-            "Key not found in your distributed keys".to_string()
-        ))
-    })?;
 
+    // A release is normally signed with a single key, so only look it up once.
+    let mut key_dist_addresses = HashMap::<String, ActionHash>::new();
+
+    create_asset_signatures
+        .into_iter()
+        .map(|create_asset_signature| {
+            let key_dist_address =
+                match key_dist_addresses.get(&create_asset_signature.verification_key) {
+                    Some(key_dist_address) => key_dist_address.clone(),
+                    None => {
+                        let key_dist_address = get_my_key_dist_address(
+                            create_asset_signature.key_type.clone(),
+                            &create_asset_signature.verification_key,
+                        )?;
+                        key_dist_addresses.insert(
+                            create_asset_signature.verification_key.clone(),
+                            key_dist_address.clone(),
+                        );
+                        key_dist_address
+                    }
+                };
+
+            create_asset_signature_for_key(create_asset_signature, key_dist_address)
+        })
+        .collect()
+}
+
+fn create_asset_signature_for_key(
+    create_asset_signature: CreateAssetSignature,
+    key_dist_address: ActionHash,
+) -> ExternResult<ActionHash> {
     let asset_sig_address = create_entry(EntryTypes::AssetSignature(AssetSignature {
         fetch_url: create_asset_signature.fetch_url.clone(),
         signature: create_asset_signature.signature,
-        key_dist_address,
+        key_dist_address: key_dist_address.clone(),
     }))?;
 
     info!(
@@ -144,12 +215,92 @@ pub fn create_asset_signature(
         make_asset_url_address(&create_asset_signature.fetch_url)?,
         asset_sig_address.clone(),
         LinkTypes::AssetUrlToSignature,
-        (),
+        AssetUrlToSignatureTag { key_dist_address }.to_link_tag()?,
     )?;
 
+    if let Some(content_sha256) = &create_asset_signature.content_sha256 {
+        create_link(
+            make_asset_digest_address(content_sha256)?,
+            asset_sig_address.clone(),
+            LinkTypes::AssetDigestToSignature,
+            (),
+        )?;
+    }
+
     Ok(asset_sig_address)
 }
 
+/// Find signatures by the SHA-256 digest of the content that they sign, regardless of the URL that
+/// the content was fetched from.
+///
+/// Signatures made with keys that have been marked as compromised are ignored.
+#[hdk_extern]
+pub fn find_signatures_by_digest(
+    request: FindSignaturesByDigestRequest,
+) -> ExternResult<Vec<DigestSignature>> {
+    let links = get_links(
+        GetLinksInputBuilder::try_new(
+            make_asset_digest_address(&request.content_sha256)?,
+            LinkTypes::AssetDigestToSignature,
+        )?
+        .build(),
+    )?;
+
+    let get_inputs = links
+        .into_iter()
+        .filter_map(|link| link.target.into_action_hash())
+        .map(|address| GetInput::new(address.into(), GetOptions::network()))
+        .collect::<Vec<_>>();
+
+    let records = HDK.with(|h| h.borrow().get(get_inputs))?;
+
+    let mut fetcher =
+        CachingVfKeyDistFetcher::new(ZomeVfKeyDistFetcher::new(&GetOptions::network()));
+
+    let mut signatures = Vec::new();
+    for record in records.into_iter().flatten() {
+        let asset_signature: AssetSignature = match record.entry.to_app_option() {
+            Ok(Some(asset_signature)) => asset_signature,
+            _ => {
+                warn!("Ignoring digest link to an invalid asset signature");
+                continue;
+            }
+        };
+
+        let vf_key_dist = match fetcher.fetch(&asset_signature.key_dist_address)? {
+            Some(vf_key_dist) => vf_key_dist,
+            None => {
+                warn!(
+                    "Discarding signature because the key distribution could not be fetched: {:?}",
+                    asset_signature.key_dist_address
+                );
+                continue;
+            }
+        };
+
+        if vf_key_dist
+            .verification_key_dist
+            .marks
+            .iter()
+            .any(|mark| matches!(mark, MarkVfKeyDistOpt::Compromised { .. }))
+        {
+            continue;
+        }
+
+        signatures.push(DigestSignature {
+            fetch_url: asset_signature.fetch_url,
+            signature: asset_signature.signature,
+            key_type: vf_key_dist.verification_key_dist.key_type,
+            verification_key: vf_key_dist.verification_key_dist.verification_key,
+            author: record.signed_action.hashed.author().clone(),
+            key_dist_address: asset_signature.key_dist_address,
+            created_at: record.signed_action.hashed.timestamp(),
+        });
+    }
+
+    Ok(signatures)
+}
+
 #[hdk_extern]
 pub fn get_my_asset_signatures() -> ExternResult<Vec<AssetSignatureResponse>> {
     let signatures = query(
@@ -208,18 +359,42 @@ pub fn get_my_asset_signatures() -> ExternResult<Vec<AssetSignatureResponse>> {
 
 #[hdk_extern]
 pub fn delete_asset_signature(request: DeleteAssetSignatureRequest) -> ExternResult<()> {
-    let asset_base = make_asset_url_address(&request.fetch_url)?;
-
-    let links = get_links(
-        GetLinksInputBuilder::try_new(asset_base, LinkTypes::AssetUrlToSignature)?
-            .get_options(GetStrategy::Local)
-            .build(),
-    )?;
+    let asset_bases = make_asset_url_addresses(&request.fetch_url)?;
+
+    // Aliases are only valid while the signatures behind them are live, so they go first.
+    delete_my_asset_aliases(&asset_bases)?;
+
+    let mut links = Vec::new();
+    for asset_base in asset_bases {
+        links.extend(get_links(
+            GetLinksInputBuilder::try_new(asset_base, LinkTypes::AssetUrlToSignature)?
+                .get_options(GetStrategy::Local)
+                .build(),
+        )?);
+    }
 
     trace!("Found {} links from the asset fetch url", links.len());
 
     let my_agent = agent_info()?.agent_initial_pubkey;
 
+    // Digest links can't be found from the fetch URL, so look for the ones we created on our chain.
+    let digest_link_type: ScopedLinkType = LinkTypes::AssetDigestToSignature.try_into()?;
+    let my_digest_links = query(ChainQueryFilter::new().action_type(ActionType::CreateLink))?
+        .into_iter()
+        .filter_map(|record| match record.action() {
+            Action::CreateLink(create_link)
+                if create_link.zome_index == digest_link_type.zome_index
+                    && create_link.link_type == digest_link_type.zome_type =>
+            {
+                Some((
+                    record.action_address().clone(),
+                    create_link.target_address.clone(),
+                ))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
     // This will fail if multiple links exist for the same fetch_url which shouldn't have been
     // allowed by validation.
     for link in links {
@@ -233,6 +408,12 @@ pub fn delete_asset_signature(request: DeleteAssetSignatureRequest) -> ExternRes
             wasm_error!(WasmErrorInner::Guest("Target is not an action".to_string()))
         })?;
 
+        for (digest_link_address, _) in my_digest_links.iter().filter(|(_, digest_target)| {
+            digest_target.clone().into_action_hash() == Some(target.clone())
+        }) {
+            delete_link(digest_link_address.clone())?;
+        }
+
         // Delete the asset signature and the link to it
         delete(target)?;
         delete_link(link.create_link_hash)?;
@@ -243,6 +424,42 @@ pub fn delete_asset_signature(request: DeleteAssetSignatureRequest) -> ExternRes
     Ok(())
 }
 
+/// Find the address of the calling agent's distribution of `verification_key`.
+pub(crate) fn get_my_key_dist_address(
+    key_type: VerificationKeyType,
+    verification_key: &str,
+) -> ExternResult<ActionHash> {
+    let my_keys: Vec<VfKeyResponse> = match call(
+        CallTargetCell::Local,
+        "signing_keys".to_string(),
+        "get_my_verification_key_distributions".into(),
+        None,
+        (),
+    )? {
+        ZomeCallResponse::Ok(response) => response
+            .decode()
+            .map_err(|e| wasm_error!(WasmErrorInner::Guest(e.to_string())))?,
+        _ => {
+            return Err(wasm_error!(WasmErrorInner::Guest(
+                "Unexpected response from signing_keys".into()
+            )))
+        }
+    };
+
+    match key_type {
+        VerificationKeyType::MiniSignEd25519 => {
+            let verification_key = minisign_verify::PublicKey::decode(verification_key.trim())
+                .map_err(|e| wasm_error!(WasmErrorInner::Guest(e.to_string())))?;
+            find_key_address(key_type, verification_key, &my_keys)
+        }
+    }
+    .ok_or_else(|| {
+        wasm_error!(WasmErrorInner::Guest(
+            "Key not found in your distributed keys".to_string()
+        ))
+    })
+}
+
 fn find_key_address<'a, K>(
     key_type: VerificationKeyType,
     verification_key: K,
@@ -266,14 +483,84 @@ where
 pub const MAX_SIGNATURES_FROM_CATEGORY: usize = 5;
 pub const MIN_SIGNATURES: usize = 30;
 
+/// A signature that could be picked for checking, built from its [LinkTypes::AssetUrlToSignature]
+/// link without fetching the [AssetSignature] itself.
+#[derive(Debug, Clone)]
+pub(crate) struct SignatureCandidate {
+    /// The address of the [AssetSignature] that the link points to.
+    pub(crate) signature_address: ActionHash,
+    author: AgentPubKey,
+    timestamp: Timestamp,
+    /// Taken from the [AssetUrlToSignatureTag] on the link.
+    key_dist_address: ActionHash,
+}
+
+impl SignatureCandidate {
+    fn try_from_link_parts(
+        target: AnyLinkableHash,
+        tag: &LinkTag,
+        author: AgentPubKey,
+        timestamp: Timestamp,
+    ) -> ExternResult<Self> {
+        let signature_address: ActionHash = target.try_into().map_err(
+            |e: HashConversionError<AnyLinkable, hash_type::Action>| {
+                wasm_error!(WasmErrorInner::Guest(format!(
+                    "Signature target is not an action: {:?}",
+                    e
+                )))
+            },
+        )?;
+
+        let tag = AssetUrlToSignatureTag::try_from_link_tag(tag)?;
+
+        Ok(Self {
+            signature_address,
+            author,
+            timestamp,
+            key_dist_address: tag.key_dist_address,
+        })
+    }
+}
+
+impl TryFrom<Link> for SignatureCandidate {
+    type Error = WasmError;
+
+    fn try_from(link: Link) -> ExternResult<Self> {
+        Self::try_from_link_parts(link.target, &link.tag, link.author, link.timestamp)
+    }
+}
+
+impl TryFrom<CreateLink> for SignatureCandidate {
+    type Error = WasmError;
+
+    fn try_from(create_link: CreateLink) -> ExternResult<Self> {
+        Self::try_from_link_parts(
+            create_link.target_address,
+            &create_link.tag,
+            create_link.author,
+            create_link.timestamp,
+        )
+    }
+}
+
+/// A signature that has been picked for checking, along with the key that is needed to check it.
+#[derive(Debug)]
+struct PickedSignature {
+    candidate: SignatureCandidate,
+    key_type: VerificationKeyType,
+    verification_key: String,
+    reason: FetchCheckSignatureReason,
+}
+
 fn pick_signatures(
-    mut possible_signatures: Vec<(Action, AssetSignature)>,
+    mut possible_signatures: Vec<SignatureCandidate>,
     key_collections: Vec<KeyCollectionWithKeys>,
-    my_existing_signature: Option<AssetSignature>,
-    fetcher: VfKeyDistFetcher,
+    my_existing_signature: Option<SignatureCandidate>,
+    publisher_key_dist_addresses: &HashSet<ActionHash>,
+    fetcher: &mut impl VfKeyDistFetcher,
     current_time: Timestamp,
     my_agent: &AgentPubKey,
-) -> Vec<FetchCheckSignature> {
+) -> Vec<PickedSignature> {
     info!(
         "Selecting from {} possible signatures",
         possible_signatures.len()
@@ -281,35 +568,48 @@ fn pick_signatures(
 
     let mut picked_signatures = Vec::new();
 
-    let filter_picked = |possible_signatures: &mut Vec<(Action, AssetSignature)>,
-                         picked_signatures: &Vec<FetchCheckSignature>| {
-        possible_signatures.retain(|(_, asset_signature)| {
-            !picked_signatures
-                .iter()
-                .any(|p: &FetchCheckSignature| p.signature == asset_signature.signature)
+    let filter_picked = |possible_signatures: &mut Vec<SignatureCandidate>,
+                         picked_signatures: &Vec<PickedSignature>| {
+        possible_signatures.retain(|candidate| {
+            !picked_signatures.iter().any(|p: &PickedSignature| {
+                p.candidate.signature_address == candidate.signature_address
+            })
         });
     };
 
-    if let Some(sig) = my_existing_signature {
-        match fetcher(&sig.key_dist_address) {
+    if let Some(candidate) = my_existing_signature {
+        match fetcher.fetch(&candidate.key_dist_address) {
             Ok(Some(vf_key_dist)) => {
-                picked_signatures.push(FetchCheckSignature {
-                    signature: sig.signature.clone(),
+                picked_signatures.push(PickedSignature {
+                    candidate,
                     key_type: vf_key_dist.verification_key_dist.key_type,
                     verification_key: vf_key_dist.verification_key_dist.verification_key,
-                    author: my_agent.clone(),
-                    key_dist_address: sig.key_dist_address.clone(),
                     reason: FetchCheckSignatureReason::Mine,
                 });
 
                 filter_picked(&mut possible_signatures, &picked_signatures);
             }
             _ => {
-                warn!("Discarding my existing asset signature signature because the key distribution could not be fetched: {:?}", sig.key_dist_address);
+                warn!("Discarding my existing asset signature signature because the key distribution could not be fetched: {:?}", candidate.key_dist_address);
             }
         }
     }
 
+    picked_signatures.extend(select_publisher_signatures(
+        &possible_signatures,
+        publisher_key_dist_addresses,
+        my_agent,
+        fetcher,
+    ));
+
+    debug!(
+        "Picked {} signatures for publishers",
+        picked_signatures.len()
+    );
+
+    // Drop signatures that we've already picked from the possible set.
+    filter_picked(&mut possible_signatures, &picked_signatures);
+
     picked_signatures.extend(select_pinned_signatures(
         &possible_signatures,
         key_collections,
@@ -326,7 +626,7 @@ fn pick_signatures(
         possible_signatures.len()
     );
 
-    possible_signatures.sort_by(|(a, _), (b, _)| a.timestamp().cmp(&b.timestamp()));
+    possible_signatures.sort_by_key(|candidate| candidate.timestamp);
 
     picked_signatures.extend(select_historical_signatures(
         &possible_signatures,
@@ -363,62 +663,152 @@ fn pick_signatures(
     picked_signatures
 }
 
-/// If the calling agent has signed this asset before then this function will find and return that
-/// signature.
+/// Fetches the [AssetSignature] for each picked signature, using a single host call, and combines
+/// them into the [FetchCheckSignature]s that are returned to the caller.
 ///
-/// Unlike the main signature lookup, this will return the signature even if it has been deleted.
-/// This allows a client application to avoid creating a duplicate signature even if the previous
-/// one has been deleted for some reason.
-fn find_my_existing_signature(asset_base: ExternalHash) -> ExternResult<Option<AssetSignature>> {
+/// Any signature that can't be fetched is discarded. Signatures that were found through an alias
+/// are labelled with their URL from `alias_urls`.
+fn resolve_picked_signatures(
+    picked_signatures: Vec<PickedSignature>,
+    get_options: GetOptions,
+    alias_urls: &HashMap<ActionHash, String>,
+) -> ExternResult<Vec<FetchCheckSignature>> {
+    let get_inputs = picked_signatures
+        .iter()
+        .map(|picked| {
+            GetInput::new(
+                picked.candidate.signature_address.clone().into(),
+                get_options.clone(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let records = HDK.with(|h| h.borrow().get(get_inputs))?;
+
+    Ok(picked_signatures
+        .into_iter()
+        .zip(records)
+        .filter_map(|(picked, record)| {
+            let asset_signature: Option<AssetSignature> =
+                record.and_then(|r| r.entry.to_app_option().ok().flatten());
+
+            match asset_signature {
+                Some(asset_signature) => Some(FetchCheckSignature {
+                    signature: asset_signature.signature,
+                    key_type: picked.key_type,
+                    verification_key: picked.verification_key,
+                    author: picked.candidate.author,
+                    key_dist_address: picked.candidate.key_dist_address,
+                    reason: picked.reason,
+                    alias_url: alias_urls.get(&picked.candidate.signature_address).cloned(),
+                }),
+                None => {
+                    warn!(
+                        "Discarding picked signature because it could not be fetched: {:?}",
+                        picked.candidate.signature_address
+                    );
+                    None
+                }
+            }
+        })
+        .collect())
+}
+
+/// If the calling agent has a live signature for this asset then this function will find and return
+/// that signature.
+///
+/// Signatures whose link has been deleted are ignored, because validation allows the asset to be
+/// signed again once the previous signature has been deleted.
+pub(crate) fn find_my_existing_signature(
+    asset_bases: &[ExternalHash],
+) -> ExternResult<Option<SignatureCandidate>> {
     let my_agent = agent_info()?.agent_initial_pubkey;
 
-    let mut my_link_creates = get_link_details(
-        asset_base,
-        LinkTypes::AssetUrlToSignature,
-        None,
-        GetOptions::local(),
-    )?
-    .into_inner()
-    .into_iter()
-    .filter_map(|(create, _)| match &create.hashed.content {
-        Action::CreateLink(create_link @ CreateLink { author, .. }) if author == &my_agent => {
-            Some(create_link.clone())
+    let mut link_details = Vec::new();
+    for asset_base in asset_bases {
+        link_details.extend(
+            get_link_details(
+                asset_base.clone(),
+                LinkTypes::AssetUrlToSignature,
+                None,
+                GetOptions::local(),
+            )?
+            .into_inner(),
+        );
+    }
+
+    // Validation only permits one live signature per fetch URL, but prefer the latest just in case.
+    let latest_live_create = link_details
+        .into_iter()
+        .filter_map(|(create, deletes)| match &create.hashed.content {
+            Action::CreateLink(create_link @ CreateLink { author, .. })
+                if author == &my_agent && deletes.is_empty() =>
+            {
+                Some(create_link.clone())
+            }
+            _ => None,
+        })
+        .max_by_key(|create| create.timestamp);
+
+    latest_live_create.map(TryInto::try_into).transpose()
+}
+
+/// Selects up to [MAX_SIGNATURES_FROM_CATEGORY] signatures that were created with keys that have been
+/// claimed as publisher keys for the fetch URL, newest first. These are always included when present.
+///
+/// Any keys that have been marked as compromised by their owner will be ignored.
+///
+/// The reason on the [PickedSignature] will be [FetchCheckSignatureReason::Publisher].
+fn select_publisher_signatures(
+    possible_signatures: &[SignatureCandidate],
+    publisher_key_dist_addresses: &HashSet<ActionHash>,
+    my_agent: &AgentPubKey,
+    fetcher: &mut impl VfKeyDistFetcher,
+) -> Vec<PickedSignature> {
+    let mut candidates = possible_signatures
+        .iter()
+        .filter(|candidate| {
+            &candidate.author != my_agent
+                && publisher_key_dist_addresses.contains(&candidate.key_dist_address)
+        })
+        .collect::<Vec<_>>();
+    candidates.sort_by_key(|candidate| std::cmp::Reverse(candidate.timestamp));
+
+    let mut picked_signatures = Vec::new();
+    for candidate in candidates {
+        if picked_signatures.len() >= MAX_SIGNATURES_FROM_CATEGORY {
+            break;
         }
-        _ => None,
-    })
-    .collect::<Vec<_>>();
 
-    if my_link_creates.is_empty() {
-        return Ok(None);
-    }
+        let vf_key_dist = match fetcher.fetch(&candidate.key_dist_address) {
+            Ok(Some(vf_key_dist)) => vf_key_dist,
+            _ => {
+                warn!(
+                    "Discarding publisher signature because the key distribution could not be fetched: {:?}",
+                    candidate.key_dist_address
+                );
+                continue;
+            }
+        };
 
-    my_link_creates.sort_by_key(|create| create.timestamp);
-    let first_create = my_link_creates.first().unwrap();
-
-    let target_address_action_hash: ActionHash = first_create
-        .target_address
-        .clone()
-        .try_into()
-        .map_err(|_| wasm_error!(WasmErrorInner::Guest("Target is not an action".to_string())))?;
-    let asset_signature_record =
-        get(target_address_action_hash, GetOptions::local())?.ok_or_else(|| {
-            wasm_error!(WasmErrorInner::Guest(
-                "No asset signature found".to_string()
-            ))
-        })?;
+        if vf_key_dist
+            .verification_key_dist
+            .marks
+            .iter()
+            .any(|mark| matches!(mark, MarkVfKeyDistOpt::Compromised { .. }))
+        {
+            continue;
+        }
 
-    let asset_signature: AssetSignature = asset_signature_record
-        .entry
-        .to_app_option()
-        .map_err(|e| {
-            wasm_error!(WasmErrorInner::Guest(format!(
-                "Failed to deserialize asset signature: {:?}",
-                e
-            )))
-        })?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("No entry found".to_string())))?;
+        picked_signatures.push(PickedSignature {
+            candidate: candidate.clone(),
+            key_type: vf_key_dist.verification_key_dist.key_type,
+            verification_key: vf_key_dist.verification_key_dist.verification_key,
+            reason: FetchCheckSignatureReason::Publisher,
+        });
+    }
 
-    Ok(Some(asset_signature))
+    picked_signatures
 }
 
 /// Searches for signatures that were created by keys that are found in the key collections. It will
@@ -426,12 +816,12 @@ fn find_my_existing_signature(asset_base: ExternalHash) -> ExternResult<Option<A
 ///
 /// Any keys that have been marked as compromised by their owner will be ignored.
 ///
-/// The reason on the [FetchCheckSignature] will be [FetchCheckSignatureReason::Pinned].
+/// The reason on the [PickedSignature] will be [FetchCheckSignatureReason::Pinned].
 fn select_pinned_signatures(
-    possible_signatures: &[(Action, AssetSignature)],
+    possible_signatures: &[SignatureCandidate],
     key_collections: Vec<KeyCollectionWithKeys>,
     my_agent: &AgentPubKey,
-) -> Vec<FetchCheckSignature> {
+) -> Vec<PickedSignature> {
     let mut picked_signatures = Vec::new();
 
     let mut rng = &mut rand::thread_rng();
@@ -462,17 +852,15 @@ fn select_pinned_signatures(
             // Look for a signature produced by this key and additionally check the author even though
             // that really should match anyway. One person could appear as two agents using
             // the same signing key, so it makes sense to check.
-            let matched_signature = possible_signatures.iter().find(|(action, sig)| {
-                action.author() == &key.author && sig.key_dist_address == key.key_dist_address
+            let matched_signature = possible_signatures.iter().find(|candidate| {
+                candidate.author == key.author && candidate.key_dist_address == key.key_dist_address
             });
 
-            if let Some((action, sig)) = matched_signature {
-                picked_signatures.push(FetchCheckSignature {
-                    signature: sig.signature.clone(),
+            if let Some(candidate) = matched_signature {
+                picked_signatures.push(PickedSignature {
+                    candidate: candidate.clone(),
                     key_type: key.verification_key_dist.key_type,
                     verification_key: key.verification_key_dist.verification_key,
-                    author: action.author().clone(),
-                    key_dist_address: sig.key_dist_address.clone(),
                     reason: FetchCheckSignatureReason::Pinned(FetchCheckSignaturePinned {
                         key_collection: key_collection.name.clone(),
                         key_name: key.verification_key_dist.name.clone(),
@@ -490,16 +878,16 @@ fn select_pinned_signatures(
 /// If there were fewer than [MIN_SIGNATURES] signatures in the first week it defaults to selecting from the first [MIN_SIGNATURES].
 /// If any signatures in the expanded set of signatures would overlap with recent signatures then those will be filtered out.
 ///
-/// This function assumes that the input is sorted by the [Action] timestamp.
+/// This function assumes that the input is sorted by timestamp.
 ///
-/// The reason on the [FetchCheckSignature] will be [FetchCheckSignatureReason::RandomHistorical].
+/// The reason on the [PickedSignature] will be [FetchCheckSignatureReason::RandomHistorical].
 fn select_historical_signatures(
-    possible_signatures: &[(Action, AssetSignature)],
+    possible_signatures: &[SignatureCandidate],
     current_time: Timestamp,
     my_agent: &AgentPubKey,
-    fetcher: VfKeyDistFetcher,
-) -> Vec<FetchCheckSignature> {
-    let earliest = match possible_signatures.first().map(|(a, _)| a.timestamp()) {
+    fetcher: &mut impl VfKeyDistFetcher,
+) -> Vec<PickedSignature> {
+    let earliest = match possible_signatures.first().map(|c| c.timestamp) {
         Some(earliest) => earliest,
         None => return Vec::with_capacity(0),
     };
@@ -508,7 +896,7 @@ fn select_historical_signatures(
 
     let take_many = match possible_signatures
         .iter()
-        .position(|(a, _)| a.timestamp() > take_before)
+        .position(|c| c.timestamp > take_before)
     {
         // None means all are within the time period, take all
         None => possible_signatures.len(),
@@ -527,51 +915,33 @@ fn select_historical_signatures(
         .sub(Duration::from_secs(60 * 60 * 24 * 7)) // 1 week
         .unwrap();
 
-    let mut rng = &mut rand::thread_rng();
-    possible_signatures
+    let mut candidates = possible_signatures
         .iter()
-        .filter_map(|(action, sig)| {
-            if action.timestamp() > ignore_after {
-                return None;
-            }
+        .filter(|c| c.timestamp <= ignore_after && &c.author != my_agent)
+        .take(take_many)
+        .collect::<Vec<_>>();
 
-            if action.author() == my_agent {
-                return None;
-            }
+    candidates.shuffle(&mut rand::thread_rng());
 
-            match fetcher(&sig.key_dist_address) {
-                Ok(Some(vf_key_dist)) => {
-                    Some(FetchCheckSignature {
-                        signature: sig.signature.clone(),
-                        key_type: vf_key_dist.verification_key_dist.key_type,
-                        verification_key: vf_key_dist.verification_key_dist.verification_key,
-                        author: action.author().clone(),
-                        key_dist_address: sig.key_dist_address.clone(),
-                        reason: FetchCheckSignatureReason::RandomHistorical,
-                    })
-                },
-                _ => {
-                    warn!("Discarding possible signature because the key distribution could not be fetched: {:?}", sig.key_dist_address);
-                    None
-                },
-            }
-        })
-        .take(take_many)
-        .choose_multiple(&mut rng, MAX_SIGNATURES_FROM_CATEGORY)
+    resolve_sampled_signatures(
+        candidates,
+        fetcher,
+        FetchCheckSignatureReason::RandomHistorical,
+    )
 }
 
 /// Tries to select up to [MAX_SIGNATURES_FROM_CATEGORY] random signatures from the last week of signatures.
 /// If there were fewer than [MIN_SIGNATURES] signatures in the last week it defaults to selecting from the last [MIN_SIGNATURES].
 ///
-/// This function assumes that the input is sorted by the [Action] timestamp.
+/// This function assumes that the input is sorted by timestamp.
 ///
-/// The reason on the [FetchCheckSignature] will be [FetchCheckSignatureReason::RandomRecent].
+/// The reason on the [PickedSignature] will be [FetchCheckSignatureReason::RandomRecent].
 fn select_recent_signatures(
-    possible_signatures: &[(Action, AssetSignature)],
+    possible_signatures: &[SignatureCandidate],
     current_time: Timestamp,
     my_agent: &AgentPubKey,
-    fetcher: VfKeyDistFetcher,
-) -> Vec<FetchCheckSignature> {
+    fetcher: &mut impl VfKeyDistFetcher,
+) -> Vec<PickedSignature> {
     let take_after = current_time
         .sub(Duration::from_secs(60 * 60 * 24 * 7)) // 1 week
         .unwrap();
@@ -579,7 +949,7 @@ fn select_recent_signatures(
     let take_many = match possible_signatures
         .iter()
         .rev()
-        .position(|(a, _)| a.timestamp() < take_after)
+        .position(|c| c.timestamp < take_after)
     {
         // None or too few found, then default to 30
         None => min(MIN_SIGNATURES, possible_signatures.len()),
@@ -587,65 +957,54 @@ fn select_recent_signatures(
         Some(x) => x,
     };
 
-    let mut rng = &mut rand::thread_rng();
-
     info!(
         "Selecting up to {} signatures randomly from {} possible recent signatures",
         MAX_SIGNATURES_FROM_CATEGORY, take_many
     );
 
-    possible_signatures
+    let mut candidates = possible_signatures
         .iter()
         .rev()
-        .filter_map(|(action, sig)| {
-            if action.author() == my_agent {
-                return None;
-            }
-
-            match fetcher(&sig.key_dist_address) {
-                Ok(Some(vf_key_dist)) => Some(FetchCheckSignature {
-                    signature: sig.signature.clone(),
-                    key_type: vf_key_dist.verification_key_dist.key_type,
-                    verification_key: vf_key_dist.verification_key_dist.verification_key,
-                    author: action.author().clone(),
-                    key_dist_address: sig.key_dist_address.clone(),
-                    reason: FetchCheckSignatureReason::RandomRecent,
-                }),
-                _ => {
-                    warn!("Discarding possible signature because the key distribution could not be fetched: {:?}", sig.key_dist_address);
-                    None
-                },
-            }
-        })
+        .filter(|c| &c.author != my_agent)
         .take(take_many)
-        .choose_multiple(&mut rng, MAX_SIGNATURES_FROM_CATEGORY)
+        .collect::<Vec<_>>();
+
+    candidates.shuffle(&mut rand::thread_rng());
+
+    resolve_sampled_signatures(candidates, fetcher, FetchCheckSignatureReason::RandomRecent)
 }
 
-type VfKeyDistFetcher = fn(&ActionHash) -> ExternResult<Option<VfKeyResponse>>;
+/// Works through the already shuffled `candidates` and looks up the key distribution for each one
+/// until [MAX_SIGNATURES_FROM_CATEGORY] signatures have been resolved.
+///
+/// Key distributions are only fetched for the signatures that are actually picked, plus any that
+/// had to be skipped because their key distribution could not be fetched.
+fn resolve_sampled_signatures(
+    candidates: Vec<&SignatureCandidate>,
+    fetcher: &mut impl VfKeyDistFetcher,
+    reason: FetchCheckSignatureReason,
+) -> Vec<PickedSignature> {
+    let mut picked_signatures = Vec::new();
 
-fn get_vf_key_dist(vf_key_dist_address: &ActionHash) -> ExternResult<Option<VfKeyResponse>> {
-    let response = call(
-        CallTargetCell::Local,
-        "signing_keys".to_string(),
-        "get_verification_key_dist".into(),
-        None,
-        vf_key_dist_address.clone(),
-    )?;
+    for candidate in candidates {
+        if picked_signatures.len() >= MAX_SIGNATURES_FROM_CATEGORY {
+            break;
+        }
 
-    match response {
-        ZomeCallResponse::Ok(response) => {
-            let response: Option<VfKeyResponse> = response.decode().map_err(|e| {
-                wasm_error!(WasmErrorInner::Guest(format!(
-                    "Failed to decode get_verification_key_dist response: {:?}",
-                    e
-                )))
-            })?;
-            Ok(response)
+        match fetcher.fetch(&candidate.key_dist_address) {
+            Ok(Some(vf_key_dist)) => picked_signatures.push(PickedSignature {
+                candidate: candidate.clone(),
+                key_type: vf_key_dist.verification_key_dist.key_type,
+                verification_key: vf_key_dist.verification_key_dist.verification_key,
+                reason: reason.clone(),
+            }),
+            _ => {
+                warn!("Discarding possible signature because the key distribution could not be fetched: {:?}", candidate.key_dist_address);
+            }
         }
-        _ => Err(wasm_error!(WasmErrorInner::Guest(
-            "Unexpected response from get_verification_key_dist".to_string()
-        ))),
     }
+
+    picked_signatures
 }
 
 struct KeyConvertible<T>(Option<T>);
@@ -670,21 +1029,81 @@ mod tests {
     use std::ops::{Add, Sub};
     use std::time::Duration;
 
-    use hdk::prelude::{
-        Action, ActionHash, AgentPubKey, AppEntryDef, Create, EntryHash, EntryRateWeight,
-        EntryType, EntryVisibility, Timestamp,
-    };
+    use hdk::prelude::{ActionHash, AgentPubKey, Timestamp};
 
     use checked_types::{FetchCheckSignatureReason, VerificationKeyType};
-    use fetch_types::AssetSignature;
     use signing_keys_types::{
         KeyCollectionWithKeys, MarkVfKeyDistOpt, VerificationKeyDistResponse, VfKeyResponse,
     };
 
     use super::{
         pick_signatures, select_historical_signatures, select_pinned_signatures,
-        select_recent_signatures, MAX_SIGNATURES_FROM_CATEGORY,
+        select_publisher_signatures, select_recent_signatures, SignatureCandidate,
+        MAX_SIGNATURES_FROM_CATEGORY,
     };
+    use crate::vf_key_dist_fetcher::VfKeyDistFetcher;
+
+    #[test]
+    fn select_publisher_signatures_by_key() {
+        let possible_signatures = vec![
+            candidate(10, 0, 1, 0),
+            candidate(20, 1, 2, 1),
+            candidate(30, 2, 3, 2),
+            candidate(40, 3, 4, 1),
+        ];
+
+        let publisher_key_dist_addresses = [ActionHash::from_raw_36(vec![1; 36])]
+            .into_iter()
+            .collect::<HashSet<_>>();
+
+        let selected = select_publisher_signatures(
+            &possible_signatures,
+            &publisher_key_dist_addresses,
+            &AgentPubKey::from_raw_36(vec![130; 36]),
+            &mut TestFetcher,
+        );
+
+        // Newest first
+        assert_eq!(
+            vec![4, 2],
+            selected
+                .iter()
+                .map(|s| s.candidate.signature_address.get_raw_36()[0])
+                .collect::<Vec<_>>()
+        );
+        assert!(selected
+            .iter()
+            .all(|s| s.reason == FetchCheckSignatureReason::Publisher));
+    }
+
+    #[test]
+    fn ignore_publisher_mine_and_compromised() {
+        let possible_signatures = vec![candidate(10, 0, 1, 0), candidate(20, 1, 2, 1)];
+
+        let publisher_key_dist_addresses = [
+            ActionHash::from_raw_36(vec![0; 36]),
+            ActionHash::from_raw_36(vec![1; 36]),
+        ]
+        .into_iter()
+        .collect::<HashSet<_>>();
+
+        let selected = select_publisher_signatures(
+            &possible_signatures,
+            &publisher_key_dist_addresses,
+            &AgentPubKey::from_raw_36(vec![0; 36]),
+            &mut CompromisedFetcher,
+        );
+        assert_eq!(0, selected.len());
+
+        let selected = select_publisher_signatures(
+            &possible_signatures,
+            &publisher_key_dist_addresses,
+            &AgentPubKey::from_raw_36(vec![0; 36]),
+            &mut TestFetcher,
+        );
+        assert_eq!(1, selected.len());
+        assert_eq!(2, selected[0].candidate.signature_address.get_raw_36()[0]);
+    }
 
     #[test]
     fn select_pinned_empty() {
@@ -696,30 +1115,9 @@ mod tests {
     #[test]
     fn select_matching_pinned() {
         let possible_signatures = vec![
-            (
-                action_at_time(0, 0),
-                AssetSignature {
-                    fetch_url: "http://example.com".to_string(),
-                    signature: "1".to_string(),
-                    key_dist_address: ActionHash::from_raw_36(vec![0; 36]),
-                },
-            ),
-            (
-                action_at_time(0, 1),
-                AssetSignature {
-                    fetch_url: "http://example.com".to_string(),
-                    signature: "2".to_string(),
-                    key_dist_address: ActionHash::from_raw_36(vec![1; 36]),
-                },
-            ),
-            (
-                action_at_time(0, 2),
-                AssetSignature {
-                    fetch_url: "http://example.com".to_string(),
-                    signature: "3".to_string(),
-                    key_dist_address: ActionHash::from_raw_36(vec![2; 36]),
-                },
-            ),
+            candidate(0, 0, 1, 0),
+            candidate(0, 1, 2, 1),
+            candidate(0, 2, 3, 2),
         ];
 
         let key_collections = vec![KeyCollectionWithKeys {
@@ -737,10 +1135,10 @@ mod tests {
 
         let picked = selected
             .iter()
-            .map(|s| s.signature.as_str())
+            .map(|s| s.candidate.signature_address.get_raw_36()[0])
             .collect::<HashSet<_>>();
-        assert!(picked.contains("1"));
-        assert!(picked.contains("3"));
+        assert!(picked.contains(&1));
+        assert!(picked.contains(&3));
     }
 
     // Not supposed to happen, you are supposed to pin other keys rather than your own. But best
@@ -748,30 +1146,9 @@ mod tests {
     #[test]
     fn ignore_pinned_mine() {
         let possible_signatures = vec![
-            (
-                action_at_time(0, 0),
-                AssetSignature {
-                    fetch_url: "http://example.com".to_string(),
-                    signature: "1".to_string(),
-                    key_dist_address: ActionHash::from_raw_36(vec![0; 36]),
-                },
-            ),
-            (
-                action_at_time(0, 1),
-                AssetSignature {
-                    fetch_url: "http://example.com".to_string(),
-                    signature: "2".to_string(),
-                    key_dist_address: ActionHash::from_raw_36(vec![1; 36]),
-                },
-            ),
-            (
-                action_at_time(0, 2),
-                AssetSignature {
-                    fetch_url: "http://example.com".to_string(),
-                    signature: "3".to_string(),
-                    key_dist_address: ActionHash::from_raw_36(vec![2; 36]),
-                },
-            ),
+            candidate(0, 0, 1, 0),
+            candidate(0, 1, 2, 1),
+            candidate(0, 2, 3, 2),
         ];
 
         let key_collections = vec![KeyCollectionWithKeys {
@@ -793,39 +1170,18 @@ mod tests {
 
         let picked = selected
             .iter()
-            .map(|s| s.signature.as_str())
+            .map(|s| s.candidate.signature_address.get_raw_36()[0])
             .collect::<HashSet<_>>();
-        assert!(picked.contains("1"));
-        assert!(picked.contains("3"));
+        assert!(picked.contains(&1));
+        assert!(picked.contains(&3));
     }
 
     #[test]
     fn ignore_compromised_pinned() {
         let possible_signatures = vec![
-            (
-                action_at_time(0, 0),
-                AssetSignature {
-                    fetch_url: "http://example.com".to_string(),
-                    signature: "1".to_string(),
-                    key_dist_address: ActionHash::from_raw_36(vec![0; 36]),
-                },
-            ),
-            (
-                action_at_time(0, 1),
-                AssetSignature {
-                    fetch_url: "http://example.com".to_string(),
-                    signature: "2".to_string(),
-                    key_dist_address: ActionHash::from_raw_36(vec![1; 36]),
-                },
-            ),
-            (
-                action_at_time(0, 2),
-                AssetSignature {
-                    fetch_url: "http://example.com".to_string(),
-                    signature: "3".to_string(),
-                    key_dist_address: ActionHash::from_raw_36(vec![2; 36]),
-                },
-            ),
+            candidate(0, 0, 1, 0),
+            candidate(0, 1, 2, 1),
+            candidate(0, 2, 3, 2),
         ];
 
         let key_collections = vec![KeyCollectionWithKeys {
@@ -847,63 +1203,21 @@ mod tests {
 
         let picked = selected
             .iter()
-            .map(|s| s.signature.as_str())
+            .map(|s| s.candidate.signature_address.get_raw_36()[0])
             .collect::<HashSet<_>>();
-        assert!(picked.contains("1"));
-        assert!(picked.contains("3"));
+        assert!(picked.contains(&1));
+        assert!(picked.contains(&3));
     }
 
     #[test]
     fn select_limited_pinned() {
         let possible_signatures = vec![
-            (
-                action_at_time(0, 0),
-                AssetSignature {
-                    fetch_url: "http://example.com".to_string(),
-                    signature: "1".to_string(),
-                    key_dist_address: ActionHash::from_raw_36(vec![0; 36]),
-                },
-            ),
-            (
-                action_at_time(0, 1),
-                AssetSignature {
-                    fetch_url: "http://example.com".to_string(),
-                    signature: "2".to_string(),
-                    key_dist_address: ActionHash::from_raw_36(vec![1; 36]),
-                },
-            ),
-            (
-                action_at_time(0, 2),
-                AssetSignature {
-                    fetch_url: "http://example.com".to_string(),
-                    signature: "3".to_string(),
-                    key_dist_address: ActionHash::from_raw_36(vec![2; 36]),
-                },
-            ),
-            (
-                action_at_time(0, 3),
-                AssetSignature {
-                    fetch_url: "http://example.com".to_string(),
-                    signature: "3".to_string(),
-                    key_dist_address: ActionHash::from_raw_36(vec![3; 36]),
-                },
-            ),
-            (
-                action_at_time(0, 4),
-                AssetSignature {
-                    fetch_url: "http://example.com".to_string(),
-                    signature: "3".to_string(),
-                    key_dist_address: ActionHash::from_raw_36(vec![4; 36]),
-                },
-            ),
-            (
-                action_at_time(0, 5),
-                AssetSignature {
-                    fetch_url: "http://example.com".to_string(),
-                    signature: "3".to_string(),
-                    key_dist_address: ActionHash::from_raw_36(vec![5; 36]),
-                },
-            ),
+            candidate(0, 0, 1, 0),
+            candidate(0, 1, 2, 1),
+            candidate(0, 2, 3, 2),
+            candidate(0, 3, 4, 3),
+            candidate(0, 4, 5, 4),
+            candidate(0, 5, 6, 5),
         ];
 
         let key_collections = vec![KeyCollectionWithKeys {
@@ -933,7 +1247,7 @@ mod tests {
             &[],
             Timestamp::now(),
             &AgentPubKey::from_raw_36(vec![0; 36]),
-            test_fetcher,
+            &mut TestFetcher,
         );
         assert_eq!(0, picked.len());
     }
@@ -947,19 +1261,13 @@ mod tests {
 
         let possible_signatures = std::iter::repeat_with(|| {
             time += 5; // +5 seconds
-            action_at_time(time, 0)
+            candidate(time, 0, 0, 0)
         })
         .take(100)
         .enumerate()
-        .map(|(idx, a)| {
-            (
-                a,
-                AssetSignature {
-                    fetch_url: "http://example.com".to_string(),
-                    signature: String::from_utf8(vec![idx as u8]).unwrap(),
-                    key_dist_address: ActionHash::from_raw_36(vec![0; 36]),
-                },
-            )
+        .map(|(idx, c)| SignatureCandidate {
+            signature_address: ActionHash::from_raw_36(vec![idx as u8; 36]),
+            ..c
         })
         .collect::<Vec<_>>();
 
@@ -967,7 +1275,7 @@ mod tests {
             &possible_signatures,
             Timestamp::now(),
             &AgentPubKey::from_raw_36(vec![130; 36]),
-            test_fetcher,
+            &mut TestFetcher,
         );
 
         // Should not return anything, leave these for recent selection
@@ -983,19 +1291,13 @@ mod tests {
 
         let possible_signatures = std::iter::repeat_with(|| {
             time += 60 * 60 * 24; // +1 day
-            action_at_time(time, 0)
+            candidate(time, 0, 0, 0)
         })
         .take(100)
         .enumerate()
-        .map(|(idx, a)| {
-            (
-                a,
-                AssetSignature {
-                    fetch_url: "http://example.com".to_string(),
-                    signature: String::from_utf8(vec![idx as u8]).unwrap(),
-                    key_dist_address: ActionHash::from_raw_36(vec![0; 36]),
-                },
-            )
+        .map(|(idx, c)| SignatureCandidate {
+            signature_address: ActionHash::from_raw_36(vec![idx as u8; 36]),
+            ..c
         })
         .collect::<Vec<_>>();
 
@@ -1003,7 +1305,7 @@ mod tests {
             &possible_signatures,
             Timestamp::now(),
             &AgentPubKey::from_raw_36(vec![130; 36]),
-            test_fetcher,
+            &mut TestFetcher,
         );
 
         // Picked 5
@@ -1012,7 +1314,7 @@ mod tests {
         // All from the first 30
         assert!(picked
             .iter()
-            .all(|sig| { sig.signature.as_bytes()[0] <= 30 }));
+            .all(|sig| { sig.candidate.signature_address.get_raw_36()[0] <= 30 }));
     }
 
     #[test]
@@ -1026,19 +1328,13 @@ mod tests {
         let possible_signatures = std::iter::repeat_with(|| {
             time += 5; // 5s
             author += 1;
-            action_at_time(time, author)
+            candidate(time, author, 0, 0)
         })
         .take(5)
         .enumerate()
-        .map(|(idx, a)| {
-            (
-                a,
-                AssetSignature {
-                    fetch_url: "http://example.com".to_string(),
-                    signature: String::from_utf8(vec![idx as u8]).unwrap(),
-                    key_dist_address: ActionHash::from_raw_36(vec![0; 36]),
-                },
-            )
+        .map(|(idx, c)| SignatureCandidate {
+            signature_address: ActionHash::from_raw_36(vec![idx as u8; 36]),
+            ..c
         })
         .collect::<Vec<_>>();
 
@@ -1046,12 +1342,14 @@ mod tests {
             &possible_signatures,
             Timestamp::now(),
             &AgentPubKey::from_raw_36(vec![3; 36]),
-            test_fetcher,
+            &mut TestFetcher,
         );
 
         // Picked 4 of the possible 5 valid options
         assert_eq!(4, picked.len());
-        assert!(!picked.iter().any(|p| p.author.get_raw_36()[0] == 3))
+        assert!(!picked
+            .iter()
+            .any(|p| p.candidate.author.get_raw_36()[0] == 3))
     }
 
     #[test]
@@ -1060,7 +1358,7 @@ mod tests {
             &[],
             current_time(),
             &AgentPubKey::from_raw_36(vec![0; 36]),
-            test_fetcher,
+            &mut TestFetcher,
         );
         assert_eq!(0, picked.len());
     }
@@ -1074,19 +1372,13 @@ mod tests {
 
         let possible_signatures = std::iter::repeat_with(|| {
             time += 5; // +5 seconds
-            action_at_time(time, 0)
+            candidate(time, 0, 0, 0)
         })
         .take(100)
         .enumerate()
-        .map(|(idx, a)| {
-            (
-                a,
-                AssetSignature {
-                    fetch_url: "http://example.com".to_string(),
-                    signature: String::from_utf8(vec![idx as u8]).unwrap(),
-                    key_dist_address: ActionHash::from_raw_36(vec![0; 36]),
-                },
-            )
+        .map(|(idx, c)| SignatureCandidate {
+            signature_address: ActionHash::from_raw_36(vec![idx as u8; 36]),
+            ..c
         })
         .collect::<Vec<_>>();
 
@@ -1094,7 +1386,7 @@ mod tests {
             &possible_signatures,
             current_time(),
             &AgentPubKey::from_raw_36(vec![130; 36]),
-            test_fetcher,
+            &mut TestFetcher,
         );
 
         // Picked 5
@@ -1112,19 +1404,13 @@ mod tests {
 
         let possible_signatures = std::iter::repeat_with(|| {
             time += 60 * 60 * 24; // +1 day
-            action_at_time(time, 0)
+            candidate(time, 0, 0, 0)
         })
         .take(100)
         .enumerate()
-        .map(|(idx, a)| {
-            (
-                a,
-                AssetSignature {
-                    fetch_url: "http://example.com".to_string(),
-                    signature: String::from_utf8(vec![idx as u8]).unwrap(),
-                    key_dist_address: ActionHash::from_raw_36(vec![0; 36]),
-                },
-            )
+        .map(|(idx, c)| SignatureCandidate {
+            signature_address: ActionHash::from_raw_36(vec![idx as u8; 36]),
+            ..c
         })
         .collect::<Vec<_>>();
 
@@ -1132,7 +1418,7 @@ mod tests {
             &possible_signatures,
             current_time(),
             &AgentPubKey::from_raw_36(vec![130; 36]),
-            test_fetcher,
+            &mut TestFetcher,
         );
 
         // Picked 5
@@ -1141,7 +1427,7 @@ mod tests {
         // All from the last 30
         assert!(picked
             .iter()
-            .all(|sig| { sig.signature.as_bytes()[0] >= 70 }));
+            .all(|sig| { sig.candidate.signature_address.get_raw_36()[0] >= 70 }));
     }
 
     #[test]
@@ -1151,18 +1437,12 @@ mod tests {
             .sub(Duration::from_secs(60 * 60 * 24 * 100))
             .timestamp(); // 100 days ago
 
-        let possible_signatures = std::iter::repeat_with(|| action_at_time(time, 0))
+        let possible_signatures = std::iter::repeat_with(|| candidate(time, 0, 0, 0))
             .take(100)
             .enumerate()
-            .map(|(idx, a)| {
-                (
-                    a,
-                    AssetSignature {
-                        fetch_url: "http://example.com".to_string(),
-                        signature: String::from_utf8(vec![idx as u8]).unwrap(),
-                        key_dist_address: ActionHash::from_raw_36(vec![0; 36]),
-                    },
-                )
+            .map(|(idx, c)| SignatureCandidate {
+                signature_address: ActionHash::from_raw_36(vec![idx as u8; 36]),
+                ..c
             })
             .collect::<Vec<_>>();
 
@@ -1170,7 +1450,7 @@ mod tests {
             &possible_signatures,
             current_time(),
             &AgentPubKey::from_raw_36(vec![130; 36]),
-            test_fetcher,
+            &mut TestFetcher,
         );
 
         // Picked 5
@@ -1179,7 +1459,7 @@ mod tests {
         // All from the last 30
         assert!(picked
             .iter()
-            .all(|sig| { sig.signature.as_bytes()[0] >= 70 }));
+            .all(|sig| { sig.candidate.signature_address.get_raw_36()[0] >= 70 }));
     }
 
     #[test]
@@ -1193,19 +1473,13 @@ mod tests {
         let possible_signatures = std::iter::repeat_with(|| {
             time += 5; // +5 seconds
             author += 1;
-            action_at_time(time, author)
+            candidate(time, author, 0, 0)
         })
         .take(5)
         .enumerate()
-        .map(|(idx, a)| {
-            (
-                a,
-                AssetSignature {
-                    fetch_url: "http://example.com".to_string(),
-                    signature: String::from_utf8(vec![idx as u8]).unwrap(),
-                    key_dist_address: ActionHash::from_raw_36(vec![0; 36]),
-                },
-            )
+        .map(|(idx, c)| SignatureCandidate {
+            signature_address: ActionHash::from_raw_36(vec![idx as u8; 36]),
+            ..c
         })
         .collect::<Vec<_>>();
 
@@ -1213,12 +1487,48 @@ mod tests {
             &possible_signatures,
             current_time(),
             &AgentPubKey::from_raw_36(vec![3; 36]),
-            test_fetcher,
+            &mut TestFetcher,
         );
 
         // Picked 4 of the possible 5 valid options
         assert_eq!(4, picked.len());
-        assert!(!picked.iter().any(|p| p.author.get_raw_36()[0] == 3));
+        assert!(!picked
+            .iter()
+            .any(|p| p.candidate.author.get_raw_36()[0] == 3));
+    }
+
+    #[test]
+    fn select_recent_skips_unresolved_key_dists() {
+        // Time in seconds
+        let mut time = chrono::prelude::Utc::now()
+            .sub(Duration::from_secs(60 * 60 * 24))
+            .timestamp(); // 1 day ago
+
+        let possible_signatures = std::iter::repeat_with(|| {
+            time += 5; // +5 seconds
+            candidate(time, 0, 0, 0)
+        })
+        .take(10)
+        .enumerate()
+        .map(|(idx, c)| SignatureCandidate {
+            signature_address: ActionHash::from_raw_36(vec![idx as u8; 36]),
+            key_dist_address: ActionHash::from_raw_36(vec![idx as u8; 36]),
+            ..c
+        })
+        .collect::<Vec<_>>();
+
+        let picked = select_recent_signatures(
+            &possible_signatures,
+            current_time(),
+            &AgentPubKey::from_raw_36(vec![130; 36]),
+            &mut EvenOnlyFetcher,
+        );
+
+        // Only the 5 signatures with an even key dist address can be resolved
+        assert_eq!(5, picked.len());
+        assert!(picked
+            .iter()
+            .all(|sig| sig.candidate.key_dist_address.get_raw_36()[0] % 2 == 0));
     }
 
     #[test]
@@ -1234,16 +1544,7 @@ mod tests {
         })
         .take(13)
         .enumerate()
-        .map(|(idx, time)| {
-            (
-                action_at_time(time, idx as u8),
-                AssetSignature {
-                    fetch_url: "http://example.com".to_string(),
-                    signature: format!("{idx}"),
-                    key_dist_address: ActionHash::from_raw_36(vec![idx as u8; 36]),
-                },
-            )
-        })
+        .map(|(idx, time)| candidate(time, idx as u8, idx as u8, idx as u8))
         .collect::<Vec<_>>();
 
         let key_responses = std::iter::repeat(3)
@@ -1258,12 +1559,9 @@ mod tests {
                 name: "test".to_string(),
                 verification_keys: key_responses,
             }],
-            Some(AssetSignature {
-                fetch_url: "http://example.com".to_string(),
-                signature: "4".to_string(),
-                key_dist_address: ActionHash::from_raw_36(vec![4; 36]),
-            }),
-            test_fetcher,
+            Some(candidate(0, 4, 4, 4)),
+            &HashSet::new(),
+            &mut TestFetcher,
             Timestamp::now()
                 .add(Duration::from_secs(60 * 60 * 24 * 15))
                 .unwrap(),
@@ -1303,7 +1601,7 @@ mod tests {
 
         let selected_sigs_unique = selected
             .iter()
-            .map(|s| s.signature.as_str())
+            .map(|s| s.candidate.signature_address.get_raw_36()[0])
             .collect::<HashSet<_>>();
         assert_eq!(13, selected_sigs_unique.len());
     }
@@ -1328,24 +1626,57 @@ mod tests {
         }
     }
 
-    fn action_at_time(time: i64, author_id: u8) -> Action {
-        Action::Create(Create {
+    fn candidate(
+        time: i64,
+        author_id: u8,
+        signature_id: u8,
+        key_dist_id: u8,
+    ) -> SignatureCandidate {
+        SignatureCandidate {
+            signature_address: ActionHash::from_raw_36(vec![signature_id; 36]),
             author: AgentPubKey::from_raw_36(vec![author_id; 36]),
             timestamp: Timestamp(time * 1_000_000), // Time in seconds to microseconds
-            action_seq: 1,
-            prev_action: ActionHash::from_raw_36(vec![0; 36]),
-            entry_type: EntryType::App(AppEntryDef {
-                entry_index: 0.into(),
-                zome_index: 0.into(),
-                visibility: EntryVisibility::Public,
-            }),
-            entry_hash: EntryHash::from_raw_36(vec![0; 36]),
-            weight: EntryRateWeight::default(),
-        })
+            key_dist_address: ActionHash::from_raw_36(vec![key_dist_id; 36]),
+        }
+    }
+
+    struct TestFetcher;
+
+    impl VfKeyDistFetcher for TestFetcher {
+        fn fetch(&mut self, _: &ActionHash) -> crate::ExternResult<Option<VfKeyResponse>> {
+            Ok(Some(test_fetched_vf_key_response()))
+        }
     }
 
-    fn test_fetcher(_: &ActionHash) -> crate::ExternResult<Option<VfKeyResponse>> {
-        Ok(Some(VfKeyResponse {
+    /// Finds key distributions that have all been marked as compromised.
+    struct CompromisedFetcher;
+
+    impl VfKeyDistFetcher for CompromisedFetcher {
+        fn fetch(&mut self, _: &ActionHash) -> crate::ExternResult<Option<VfKeyResponse>> {
+            Ok(Some(vf_key_response_add_compromised_mark(
+                test_fetched_vf_key_response(),
+            )))
+        }
+    }
+
+    /// Only finds key distributions with an even first byte in their address.
+    struct EvenOnlyFetcher;
+
+    impl VfKeyDistFetcher for EvenOnlyFetcher {
+        fn fetch(
+            &mut self,
+            vf_key_dist_address: &ActionHash,
+        ) -> crate::ExternResult<Option<VfKeyResponse>> {
+            if vf_key_dist_address.get_raw_36()[0] % 2 == 0 {
+                Ok(Some(test_fetched_vf_key_response()))
+            } else {
+                Ok(None)
+            }
+        }
+    }
+
+    fn test_fetched_vf_key_response() -> VfKeyResponse {
+        VfKeyResponse {
             verification_key_dist: VerificationKeyDistResponse {
                 verification_key: "test key".to_string(),
                 key_type: VerificationKeyType::MiniSignEd25519,
@@ -1357,7 +1688,7 @@ mod tests {
             reference_count: 0,
             author: AgentPubKey::from_raw_36(vec![0; 36]),
             created_at: Timestamp(0),
-        }))
+        }
     }
 
     fn vf_key_response_add_compromised_mark(mut response: VfKeyResponse) -> VfKeyResponse {
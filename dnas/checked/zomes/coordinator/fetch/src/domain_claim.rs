@@ -0,0 +1,104 @@
+use hdk::prelude::*;
+
+use checked_types::canonical_url::canonicalize_domain;
+use checked_types::*;
+use fetch_integrity::prelude::*;
+use signing_keys_types::MarkVfKeyDistOpt;
+
+use crate::asset_signature::get_my_key_dist_address;
+use crate::vf_key_dist_fetcher::{CachingVfKeyDistFetcher, VfKeyDistFetcher, ZomeVfKeyDistFetcher};
+
+/// Claim that one of your keys belongs to the owner of a domain.
+///
+/// For the claim to be trusted, you must also publish the key and your agent at
+/// `https://<domain>/.well-known/checked.json`.
+#[hdk_extern]
+pub fn create_domain_claim(request: CreateDomainClaim) -> ExternResult<ActionHash> {
+    let domain =
+        canonicalize_domain(&request.domain).map_err(|e| wasm_error!(WasmErrorInner::Guest(e)))?;
+
+    let key_dist_address = get_my_key_dist_address(request.key_type, &request.verification_key)?;
+
+    let domain_claim_address = create_entry(EntryTypes::DomainClaim(DomainClaim {
+        domain: domain.clone(),
+        key_dist_address,
+    }))?;
+
+    create_link(
+        make_domain_address(&domain),
+        domain_claim_address.clone(),
+        LinkTypes::DomainToDomainClaim,
+        (),
+    )?;
+
+    Ok(domain_claim_address)
+}
+
+/// Get the claims that have been made for a domain, along with the keys that they were made for.
+///
+/// Claims for keys that have been marked as compromised are ignored.
+#[hdk_extern]
+pub fn get_domain_claims(domain: String) -> ExternResult<Vec<DomainClaimResponse>> {
+    let domain = canonicalize_domain(&domain).map_err(|e| wasm_error!(WasmErrorInner::Guest(e)))?;
+
+    let links = get_links(
+        GetLinksInputBuilder::try_new(
+            make_domain_address(&domain),
+            LinkTypes::DomainToDomainClaim,
+        )?
+        .build(),
+    )?;
+
+    let get_inputs = links
+        .into_iter()
+        .filter_map(|link| link.target.into_action_hash())
+        .map(|address| GetInput::new(address.into(), GetOptions::network()))
+        .collect::<Vec<_>>();
+
+    let records = HDK.with(|h| h.borrow().get(get_inputs))?;
+
+    let mut fetcher =
+        CachingVfKeyDistFetcher::new(ZomeVfKeyDistFetcher::new(&GetOptions::network()));
+
+    let mut claims = Vec::new();
+    for record in records.into_iter().flatten() {
+        let domain_claim: DomainClaim = match record.entry.to_app_option() {
+            Ok(Some(domain_claim)) => domain_claim,
+            _ => {
+                warn!("Ignoring domain link to an invalid domain claim");
+                continue;
+            }
+        };
+
+        let vf_key_dist = match fetcher.fetch(&domain_claim.key_dist_address)? {
+            Some(vf_key_dist) => vf_key_dist,
+            None => {
+                warn!(
+                    "Discarding domain claim because the key distribution could not be fetched: {:?}",
+                    domain_claim.key_dist_address
+                );
+                continue;
+            }
+        };
+
+        if vf_key_dist
+            .verification_key_dist
+            .marks
+            .iter()
+            .any(|mark| matches!(mark, MarkVfKeyDistOpt::Compromised { .. }))
+        {
+            continue;
+        }
+
+        claims.push(DomainClaimResponse {
+            domain: domain_claim.domain,
+            author: record.signed_action.hashed.author().clone(),
+            key_dist_address: domain_claim.key_dist_address,
+            key_type: vf_key_dist.verification_key_dist.key_type,
+            verification_key: vf_key_dist.verification_key_dist.verification_key,
+            created_at: record.signed_action.hashed.timestamp(),
+        });
+    }
+
+    Ok(claims)
+}
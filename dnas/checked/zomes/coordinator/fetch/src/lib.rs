@@ -1,4 +1,8 @@
+mod asset_alias;
 mod asset_signature;
+mod domain_claim;
+mod publisher_claim;
+mod vf_key_dist_fetcher;
 
 use fetch_integrity::prelude::*;
 use hdk::prelude::*;
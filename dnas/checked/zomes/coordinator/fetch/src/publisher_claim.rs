@@ -0,0 +1,81 @@
+use hdk::prelude::*;
+
+use checked_types::canonical_url::{canonicalize_url, canonicalize_url_prefix, url_prefixes};
+use checked_types::*;
+use fetch_integrity::prelude::*;
+
+use crate::asset_signature::get_my_key_dist_address;
+
+/// Declare yourself the publisher of the assets under a URL prefix.
+///
+/// Signatures that you make with the given key, for URLs under the prefix, will be reported as
+/// [FetchCheckSignatureReason::Publisher] to anyone fetching those URLs.
+///
+/// Nothing checks that you control the prefix, so clients should only treat the claim as coming
+/// from the publisher when the key has also been verified for the domain with a domain claim.
+#[hdk_extern]
+pub fn create_publisher_claim(request: CreatePublisherClaim) -> ExternResult<ActionHash> {
+    let url_prefix = canonicalize_url_prefix(&request.url_prefix)
+        .map_err(|e| wasm_error!(WasmErrorInner::Guest(e)))?
+        .to_string();
+
+    let key_dist_address = get_my_key_dist_address(request.key_type, &request.verification_key)?;
+
+    let publisher_claim_address = create_entry(EntryTypes::PublisherClaim(PublisherClaim {
+        url_prefix: url_prefix.clone(),
+        key_dist_address,
+    }))?;
+
+    create_link(
+        make_url_prefix_address(&url_prefix),
+        publisher_claim_address.clone(),
+        LinkTypes::UrlPrefixToPublisherClaim,
+        (),
+    )?;
+
+    Ok(publisher_claim_address)
+}
+
+/// Find the addresses of the keys that have been claimed as publisher keys for any prefix of
+/// `fetch_url`.
+pub(crate) fn get_publisher_key_dist_addresses(
+    fetch_url: &str,
+    get_options: GetOptions,
+) -> ExternResult<HashSet<ActionHash>> {
+    let fetch_url = canonicalize_url(fetch_url)
+        .map_err(|e| wasm_error!(WasmErrorInner::Guest(e.to_string())))?;
+
+    let mut links = Vec::new();
+    for url_prefix in url_prefixes(&fetch_url) {
+        links.extend(get_links(
+            GetLinksInputBuilder::try_new(
+                make_url_prefix_address(&url_prefix),
+                LinkTypes::UrlPrefixToPublisherClaim,
+            )?
+            .get_options(get_options.strategy)
+            .build(),
+        )?);
+    }
+
+    let get_inputs = links
+        .into_iter()
+        .filter_map(|link| link.target.into_action_hash())
+        .map(|address| GetInput::new(address.into(), get_options.clone()))
+        .collect::<Vec<_>>();
+
+    let records = HDK.with(|h| h.borrow().get(get_inputs))?;
+
+    Ok(records
+        .into_iter()
+        .flatten()
+        .filter_map(
+            |record| match record.entry.to_app_option::<PublisherClaim>() {
+                Ok(Some(publisher_claim)) => Some(publisher_claim.key_dist_address),
+                _ => {
+                    warn!("Ignoring publisher link to an invalid publisher claim");
+                    None
+                }
+            },
+        )
+        .collect())
+}
@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use hdk::prelude::*;
+
+use signing_keys_types::VfKeyResponse;
+
+/// Looks up verification key distributions by their address while signatures are being selected.
+pub(crate) trait VfKeyDistFetcher {
+    /// Fetch the verification key distribution at `vf_key_dist_address`, if it can be found.
+    fn fetch(&mut self, vf_key_dist_address: &ActionHash) -> ExternResult<Option<VfKeyResponse>>;
+}
+
+/// Fetches verification key distributions from the `signing_keys` zome.
+pub(crate) struct ZomeVfKeyDistFetcher {
+    fn_name: &'static str,
+}
+
+impl ZomeVfKeyDistFetcher {
+    /// Create a fetcher that looks up key distributions using the strategy from `get_options`.
+    pub(crate) fn new(get_options: &GetOptions) -> Self {
+        Self {
+            fn_name: match get_options.strategy {
+                GetStrategy::Network => "get_verification_key_dist",
+                GetStrategy::Local => "get_verification_key_dist_local",
+            },
+        }
+    }
+}
+
+impl VfKeyDistFetcher for ZomeVfKeyDistFetcher {
+    fn fetch(&mut self, vf_key_dist_address: &ActionHash) -> ExternResult<Option<VfKeyResponse>> {
+        let response = call(
+            CallTargetCell::Local,
+            "signing_keys".to_string(),
+            self.fn_name.into(),
+            None,
+            vf_key_dist_address.clone(),
+        )?;
+
+        match response {
+            ZomeCallResponse::Ok(response) => {
+                let response: Option<VfKeyResponse> = response.decode().map_err(|e| {
+                    wasm_error!(WasmErrorInner::Guest(format!(
+                        "Failed to decode {} response: {:?}",
+                        self.fn_name, e
+                    )))
+                })?;
+                Ok(response)
+            }
+            _ => Err(wasm_error!(WasmErrorInner::Guest(format!(
+                "Unexpected response from {}",
+                self.fn_name
+            )))),
+        }
+    }
+}
+
+/// Remembers the result of each lookup made through the wrapped fetcher.
+///
+/// A single key is commonly used to sign many assets, and the same key distribution can be
+/// considered by more than one selection strategy. Each lookup is a cross-zome call which goes to
+/// the network, so this is intended to live for the duration of a single request.
+///
+/// Errors are not cached, so a failed lookup will be retried if the same address is requested again.
+pub(crate) struct CachingVfKeyDistFetcher<F> {
+    inner: F,
+    cache: HashMap<ActionHash, Option<VfKeyResponse>>,
+}
+
+impl<F: VfKeyDistFetcher> CachingVfKeyDistFetcher<F> {
+    pub(crate) fn new(inner: F) -> Self {
+        Self {
+            inner,
+            cache: HashMap::new(),
+        }
+    }
+}
+
+impl<F: VfKeyDistFetcher> VfKeyDistFetcher for CachingVfKeyDistFetcher<F> {
+    fn fetch(&mut self, vf_key_dist_address: &ActionHash) -> ExternResult<Option<VfKeyResponse>> {
+        if let Some(cached) = self.cache.get(vf_key_dist_address) {
+            return Ok(cached.clone());
+        }
+
+        let response = self.inner.fetch(vf_key_dist_address)?;
+        self.cache
+            .insert(vf_key_dist_address.clone(), response.clone());
+
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hdk::prelude::{ActionHash, AgentPubKey, Timestamp};
+
+    use checked_types::VerificationKeyType;
+    use signing_keys_types::{VerificationKeyDistResponse, VfKeyResponse};
+
+    use super::{CachingVfKeyDistFetcher, VfKeyDistFetcher};
+
+    struct CountingFetcher {
+        calls: usize,
+    }
+
+    impl VfKeyDistFetcher for CountingFetcher {
+        fn fetch(
+            &mut self,
+            vf_key_dist_address: &ActionHash,
+        ) -> crate::ExternResult<Option<VfKeyResponse>> {
+            self.calls += 1;
+
+            // Pretend that only even addresses exist
+            if vf_key_dist_address.get_raw_36()[0] % 2 == 1 {
+                return Ok(None);
+            }
+
+            Ok(Some(VfKeyResponse {
+                verification_key_dist: VerificationKeyDistResponse {
+                    verification_key: "test key".to_string(),
+                    key_type: VerificationKeyType::MiniSignEd25519,
+                    name: "test".to_string(),
+                    expires_at: None,
+                    marks: vec![],
+                },
+                key_dist_address: vf_key_dist_address.clone(),
+                reference_count: 0,
+                author: AgentPubKey::from_raw_36(vec![0; 36]),
+                created_at: Timestamp(0),
+            }))
+        }
+    }
+
+    #[test]
+    fn repeated_lookups_are_cached() {
+        let mut fetcher = CachingVfKeyDistFetcher::new(CountingFetcher { calls: 0 });
+
+        for _ in 0..5 {
+            let found = fetcher
+                .fetch(&ActionHash::from_raw_36(vec![2; 36]))
+                .unwrap();
+            assert!(found.is_some());
+        }
+
+        assert_eq!(1, fetcher.inner.calls);
+    }
+
+    #[test]
+    fn missing_key_dists_are_cached() {
+        let mut fetcher = CachingVfKeyDistFetcher::new(CountingFetcher { calls: 0 });
+
+        for _ in 0..5 {
+            let found = fetcher
+                .fetch(&ActionHash::from_raw_36(vec![1; 36]))
+                .unwrap();
+            assert!(found.is_none());
+        }
+
+        assert_eq!(1, fetcher.inner.calls);
+    }
+
+    #[test]
+    fn different_addresses_are_fetched() {
+        let mut fetcher = CachingVfKeyDistFetcher::new(CountingFetcher { calls: 0 });
+
+        fetcher
+            .fetch(&ActionHash::from_raw_36(vec![2; 36]))
+            .unwrap();
+        fetcher
+            .fetch(&ActionHash::from_raw_36(vec![4; 36]))
+            .unwrap();
+        fetcher
+            .fetch(&ActionHash::from_raw_36(vec![2; 36]))
+            .unwrap();
+
+        assert_eq!(2, fetcher.inner.calls);
+    }
+}
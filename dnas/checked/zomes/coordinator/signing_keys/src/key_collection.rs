@@ -22,6 +22,19 @@ pub fn create_key_collection(key_collection: KeyCollection) -> ExternResult<Reco
 
 #[hdk_extern]
 pub fn get_my_key_collections() -> ExternResult<Vec<KeyCollectionWithKeys>> {
+    get_my_key_collections_with_get_options(GetOptions::network())
+}
+
+/// Like [get_my_key_collections], but key marks and reference counts are only looked up in data
+/// that is already held locally.
+#[hdk_extern]
+pub fn get_my_key_collections_local() -> ExternResult<Vec<KeyCollectionWithKeys>> {
+    get_my_key_collections_with_get_options(GetOptions::local())
+}
+
+fn get_my_key_collections_with_get_options(
+    get_options: GetOptions,
+) -> ExternResult<Vec<KeyCollectionWithKeys>> {
     let mut key_collections = Vec::new();
     for record in inner_get_my_key_collections()? {
         let collection_action_hash = record.action_hashed().as_hash().clone();
@@ -63,12 +76,12 @@ pub fn get_my_key_collections() -> ExternResult<Vec<KeyCollectionWithKeys>> {
                     continue;
                 };
 
-            // Must be network because we are looking for marks on the key dist created by *other* agents.
-            let marks = get_key_marks(key_dist_address.clone(), GetOptions::network())?;
+            // Prefer network because we are looking for marks on the key dist created by *other* agents.
+            let marks = get_key_marks(key_dist_address.clone(), get_options.clone())?;
             let reference_count = get_key_collections_reference_count(
                 key_dist_address.clone(),
                 // This is collective across the network, so prefer network.
-                &GetOptions::network(),
+                &get_options,
             )?;
             key_collection.verification_keys.push(VfKeyResponse {
                 verification_key_dist: (vf_key_dist, marks).into(),
@@ -35,7 +35,21 @@ pub fn distribute_verification_key(request: DistributeVfKeyRequest) -> ExternRes
 pub fn get_verification_key_dist(
     vf_key_dist_address: ActionHash,
 ) -> ExternResult<Option<VfKeyResponse>> {
-    let record = get(vf_key_dist_address, GetOptions::network())?;
+    get_verification_key_dist_with_get_options(vf_key_dist_address, GetOptions::network())
+}
+
+#[hdk_extern]
+pub fn get_verification_key_dist_local(
+    vf_key_dist_address: ActionHash,
+) -> ExternResult<Option<VfKeyResponse>> {
+    get_verification_key_dist_with_get_options(vf_key_dist_address, GetOptions::local())
+}
+
+fn get_verification_key_dist_with_get_options(
+    vf_key_dist_address: ActionHash,
+    get_options: GetOptions,
+) -> ExternResult<Option<VfKeyResponse>> {
+    let record = get(vf_key_dist_address, get_options)?;
     match record {
         None => Ok(None),
         Some(r) => {
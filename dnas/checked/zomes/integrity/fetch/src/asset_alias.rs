@@ -0,0 +1,205 @@
+use super::UnitEntryTypes;
+use crate::prelude::{make_asset_url_address, make_asset_url_addresses};
+use fetch_types::{AssetAlias, AssetSignature};
+use hdi::prelude::*;
+
+pub(crate) fn validate_create_asset_alias(
+    create_action: EntryCreationAction,
+    asset_alias: AssetAlias,
+) -> ExternResult<ValidateCallbackResult> {
+    if make_asset_url_address(&asset_alias.fetch_url)?
+        == make_asset_url_address(&asset_alias.alias_url)?
+    {
+        return Ok(ValidateCallbackResult::Invalid(
+            "An asset alias must join two different URLs".to_string(),
+        ));
+    }
+
+    let agent_activity = must_get_agent_activity(
+        create_action.author().clone(),
+        ChainFilter::new(create_action.prev_action().clone()),
+    )?;
+
+    let fetch_url_signature = match get_live_asset_signature(
+        &agent_activity,
+        create_action.author(),
+        &asset_alias.fetch_url_signature_address,
+    )? {
+        Some(signature) => signature,
+        None => {
+            return Ok(ValidateCallbackResult::Invalid(
+                "The fetch URL signature is not a live asset signature on the author's chain"
+                    .to_string(),
+            ));
+        }
+    };
+
+    let alias_url_signature = match get_live_asset_signature(
+        &agent_activity,
+        create_action.author(),
+        &asset_alias.alias_url_signature_address,
+    )? {
+        Some(signature) => signature,
+        None => {
+            return Ok(ValidateCallbackResult::Invalid(
+                "The alias URL signature is not a live asset signature on the author's chain"
+                    .to_string(),
+            ));
+        }
+    };
+
+    if make_asset_url_address(&fetch_url_signature.fetch_url)?
+        != make_asset_url_address(&asset_alias.fetch_url)?
+        || make_asset_url_address(&alias_url_signature.fetch_url)?
+            != make_asset_url_address(&asset_alias.alias_url)?
+    {
+        return Ok(ValidateCallbackResult::Invalid(
+            "The asset signatures are not for the URLs in the alias".to_string(),
+        ));
+    }
+
+    if fetch_url_signature.key_dist_address != alias_url_signature.key_dist_address {
+        return Ok(ValidateCallbackResult::Invalid(
+            "The asset signatures must be made with the same key".to_string(),
+        ));
+    }
+
+    // Signatures are deterministic, so the same key signing the same content gives the same
+    // signature. The trusted comment is signed separately and is expected to differ.
+    match (
+        signature_line(&fetch_url_signature.signature),
+        signature_line(&alias_url_signature.signature),
+    ) {
+        (Some(fetch_url_line), Some(alias_url_line)) if fetch_url_line == alias_url_line => {}
+        _ => {
+            return Ok(ValidateCallbackResult::Invalid(
+                "The asset signatures are not for the same content".to_string(),
+            ));
+        }
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+pub(crate) fn validate_delete_asset_alias(
+    delete: Delete,
+    prev_action: SignedActionHashed,
+) -> ExternResult<ValidateCallbackResult> {
+    if &delete.author != prev_action.action().author() {
+        return Ok(ValidateCallbackResult::Invalid(
+            "The delete author does not match the previous action author".to_string(),
+        ));
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+pub(crate) fn validate_create_asset_url_to_alias_link(
+    action: CreateLink,
+    base_address: AnyLinkableHash,
+    target_address: AnyLinkableHash,
+) -> ExternResult<ValidateCallbackResult> {
+    let target = must_get_valid_record(target_address.try_into().map_err(|_| {
+        wasm_error!(WasmErrorInner::Guest(
+            "Target address is not an action hash".to_string()
+        ))
+    })?)?;
+
+    if target.signed_action.hashed.author() != &action.author {
+        return Ok(ValidateCallbackResult::Invalid(
+            "The target entry is not authored by the link author".to_string(),
+        ));
+    }
+
+    let entry_type: EntryType = UnitEntryTypes::AssetAlias.try_into()?;
+
+    if target
+        .signed_action
+        .hashed
+        .content
+        .entry_type()
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("No entry type".to_string())))?
+        != &entry_type
+    {
+        return Ok(ValidateCallbackResult::Invalid(
+            "The target entry is not an AssetAlias".to_string(),
+        ));
+    }
+
+    let asset_alias: AssetAlias = target
+        .entry
+        .into_option()
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("No entry".to_string())))?
+        .try_into()?;
+
+    let base_address: ExternalHash = base_address.try_into().map_err(|_| {
+        wasm_error!(WasmErrorInner::Guest(
+            "Base address is not an ExternalHash".to_string()
+        ))
+    })?;
+
+    if !make_asset_url_addresses(&asset_alias.fetch_url)?.contains(&base_address)
+        && !make_asset_url_addresses(&asset_alias.alias_url)?.contains(&base_address)
+    {
+        return Ok(ValidateCallbackResult::Invalid(
+            "The base address does not match either of the URLs in the alias".to_string(),
+        ));
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+pub(crate) fn validate_delete_asset_url_to_alias_link(
+    original_action: CreateLink,
+    delete: DeleteLink,
+) -> ExternResult<ValidateCallbackResult> {
+    if original_action.author != delete.author {
+        return Ok(ValidateCallbackResult::Invalid(
+            "The delete author does not match the original author".to_string(),
+        ));
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// Find the [AssetSignature] created at `address` by `author`, as long as it has not been deleted.
+fn get_live_asset_signature(
+    agent_activity: &[RegisterAgentActivity],
+    author: &AgentPubKey,
+    address: &ActionHash,
+) -> ExternResult<Option<AssetSignature>> {
+    let is_deleted = agent_activity.iter().any(|activity| {
+        matches!(activity.action.action(), Action::Delete(delete) if &delete.deletes_address == address)
+    });
+    if is_deleted {
+        return Ok(None);
+    }
+
+    let entry_type: EntryType = UnitEntryTypes::AssetSignature.try_into()?;
+
+    let create = agent_activity
+        .iter()
+        .find_map(|activity| match activity.action.action() {
+            Action::Create(create)
+                if activity.action.action_address() == address
+                    && &create.author == author
+                    && create.entry_type == entry_type =>
+            {
+                Some(create)
+            }
+            _ => None,
+        });
+
+    match create {
+        Some(create) => {
+            let entry = must_get_entry(create.entry_hash.clone())?;
+            Ok(Some(entry.try_into()?))
+        }
+        None => Ok(None),
+    }
+}
+
+/// The line of a MiniSign signature that signs the content, as opposed to the trusted comment.
+fn signature_line(signature: &str) -> Option<&str> {
+    signature.lines().nth(1).map(str::trim)
+}
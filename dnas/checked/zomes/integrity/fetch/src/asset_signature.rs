@@ -1,8 +1,16 @@
 use super::UnitEntryTypes;
-use crate::prelude::make_asset_url_address;
+use crate::common::get_key_dist_from_chain;
+use crate::prelude::{make_asset_url_address, make_asset_url_addresses};
 use crate::LinkTypes;
-use fetch_types::AssetSignature;
+use checked_types::properties::get_rate_limits;
+use checked_types::VerificationKeyType;
+use fetch_types::{AssetSignature, AssetUrlToSignatureTag};
 use hdi::prelude::*;
+use signing_keys_types::VerificationKeyDist;
+use std::collections::HashSet;
+
+/// The maximum length of the fetch URL for an [AssetSignature].
+pub const MAX_FETCH_URL_LENGTH: usize = 2048;
 
 pub(crate) fn validate_create_asset_signature(
     create_action: EntryCreationAction,
@@ -13,50 +21,72 @@ pub(crate) fn validate_create_asset_signature(
         ChainFilter::new(create_action.prev_action().clone()),
     )?;
 
-    // Not a perfect match because it's a type defined in another zome. We just check that the key
-    // dist address points to a real action on this agent's chain.
-    let maybe_found_key_dist = agent_activity.iter().find(|activity| {
-        activity.action.action_address() == &asset_signature.key_dist_address
-            && match activity.action.action() {
-                Action::Create(create) => create_action.author() == &create.author,
-                _ => false,
-            }
-    });
+    if let Err(reason) = check_fetch_url(&asset_signature.fetch_url) {
+        return Ok(ValidateCallbackResult::Invalid(reason));
+    }
 
-    if maybe_found_key_dist.is_none() {
-        return Ok(ValidateCallbackResult::Invalid(
-            "The key dist address does not point to a valid action on the author's chain"
-                .to_string(),
-        ));
+    let key_dist = match get_key_dist_from_chain(
+        &agent_activity,
+        create_action.author(),
+        &asset_signature.key_dist_address,
+    )? {
+        Ok(key_dist) => key_dist,
+        Err(reason) => return Ok(ValidateCallbackResult::Invalid(reason)),
+    };
+
+    if let Err(reason) = check_signature_matches_key(&asset_signature.signature, &key_dist) {
+        return Ok(ValidateCallbackResult::Invalid(reason));
     }
 
     let entry_type: EntryType = UnitEntryTypes::AssetSignature.try_into()?;
 
-    let previous_asset_signatures =
-        agent_activity
-            .iter()
-            .filter(|activity| match activity.action.action() {
+    // Signatures that have since been deleted don't prevent the same fetch URL being signed again.
+    let deleted = agent_activity
+        .iter()
+        .filter_map(|activity| match activity.action.action() {
+            Action::Delete(delete) => Some(&delete.deletes_address),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+
+    let previous_asset_signatures = agent_activity.iter().filter(|activity| {
+        !deleted.contains(activity.action.action_address())
+            && match activity.action.action() {
                 Action::Create(create) => {
                     create_action.author() == &create.author && entry_type == create.entry_type
                 }
                 _ => false,
+            }
+    });
+
+    let rate_limit = get_rate_limits()?.asset_signature;
+    let previous_create_timestamps =
+        agent_activity
+            .iter()
+            .filter_map(|activity| match activity.action.action() {
+                Action::Create(create) if entry_type == create.entry_type => Some(create.timestamp),
+                _ => None,
             });
+    if rate_limit.is_exceeded_by(*create_action.timestamp(), previous_create_timestamps) {
+        return Ok(ValidateCallbackResult::Invalid(format!(
+            "Too many asset signatures created, at most {} are allowed every {} seconds",
+            rate_limit.max_count, rate_limit.window_seconds
+        )));
+    }
+
+    // Compare canonical URLs, so that the same asset can't be signed twice by writing its URL
+    // differently.
+    let asset_url_address = make_asset_url_address(&asset_signature.fetch_url)?;
 
     for activity in previous_asset_signatures {
-        match activity.action.action() {
-            Action::Create(create) => {
-                // TODO any loss of entry data would prevent future validation, is that possible?
-                let entry = must_get_entry(create.entry_hash.clone())?;
-                let entry: AssetSignature = entry.try_into()?;
-                if entry.fetch_url == asset_signature.fetch_url {
-                    return Ok(ValidateCallbackResult::Invalid(
-                        "An asset signature with the same fetch URL already exists".to_string(),
-                    ));
-                }
-            }
-            _ => {
-                // Only care about other creates. Note that ignoring deletes means that you can
-                // delete a signature but not re-add it.
+        if let Action::Create(create) = activity.action.action() {
+            // TODO any loss of entry data would prevent future validation, is that possible?
+            let entry = must_get_entry(create.entry_hash.clone())?;
+            let entry: AssetSignature = entry.try_into()?;
+            if make_asset_url_address(&entry.fetch_url)? == asset_url_address {
+                return Ok(ValidateCallbackResult::Invalid(
+                    "An asset signature with the same fetch URL already exists".to_string(),
+                ));
             }
         }
     }
@@ -64,6 +94,55 @@ pub(crate) fn validate_create_asset_signature(
     Ok(ValidateCallbackResult::Valid)
 }
 
+/// Checks that `fetch_url` is an http(s) URL that is no longer than [MAX_FETCH_URL_LENGTH].
+fn check_fetch_url(fetch_url: &str) -> Result<(), String> {
+    if fetch_url.len() > MAX_FETCH_URL_LENGTH {
+        return Err(format!(
+            "The fetch URL is longer than the maximum of {} characters",
+            MAX_FETCH_URL_LENGTH
+        ));
+    }
+
+    let url = url::Url::parse(fetch_url).map_err(|e| format!("Invalid fetch URL: {}", e))?;
+
+    match url.scheme() {
+        "http" | "https" => Ok(()),
+        scheme => Err(format!(
+            "The fetch URL must use http or https, but found: {}",
+            scheme
+        )),
+    }
+}
+
+/// Checks that `signature` is well-formed for the type of `key_dist` and was produced by that key.
+///
+/// The asset itself isn't stored on Holochain, so the signature can't be fully verified here.
+fn check_signature_matches_key(
+    signature: &str,
+    key_dist: &VerificationKeyDist,
+) -> Result<(), String> {
+    match key_dist.key_type {
+        VerificationKeyType::MiniSignEd25519 => {
+            let vf_key = minisign_verify::PublicKey::decode(key_dist.verification_key.trim())
+                .map_err(|e| format!("The verification key could not be decoded: {}", e))?;
+            let signature = minisign_verify::Signature::decode(signature)
+                .map_err(|e| format!("The signature is not a valid MiniSign signature: {}", e))?;
+
+            // Checks that the key IDs match and that the signature is over a pre-hashed file,
+            // without needing the content that was signed.
+            vf_key
+                .verify_stream(&signature)
+                .map(|_| ())
+                .map_err(|e| match e {
+                    minisign_verify::Error::UnexpectedKeyId => {
+                        "The signature was not created by the referenced key".to_string()
+                    }
+                    e => format!("The signature is not supported: {}", e),
+                })
+        }
+    }
+}
+
 pub(crate) fn validate_delete_asset_signature(
     delete: Delete,
     prev_action: SignedActionHashed,
@@ -121,7 +200,7 @@ pub(crate) fn validate_create_asset_url_to_signature_link(
         .into_option()
         .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("No entry".to_string())))?
         .try_into()?;
-    let expected_base_address = make_asset_url_address(&asset_signature.fetch_url)?;
+    let expected_base_addresses = make_asset_url_addresses(&asset_signature.fetch_url)?;
 
     let base_address: ExternalHash = base_address.try_into().map_err(|_| {
         wasm_error!(WasmErrorInner::Guest(
@@ -129,12 +208,84 @@ pub(crate) fn validate_create_asset_url_to_signature_link(
         ))
     })?;
 
-    if base_address != expected_base_address {
+    if !expected_base_addresses.contains(&base_address) {
         return Ok(ValidateCallbackResult::Invalid(
             "The base address does not match the expected address for the asset URL".to_string(),
         ));
     }
 
+    let tag = match AssetUrlToSignatureTag::try_from_link_tag(&action.tag) {
+        Ok(tag) => tag,
+        Err(_) => {
+            return Ok(ValidateCallbackResult::Invalid(
+                "The link tag is not a valid AssetUrlToSignatureTag".to_string(),
+            ));
+        }
+    };
+
+    if tag.key_dist_address != asset_signature.key_dist_address {
+        return Ok(ValidateCallbackResult::Invalid(
+            "The key dist address in the link tag does not match the target asset signature"
+                .to_string(),
+        ));
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// The digest that the link is created from is chosen by the signer and can't be checked against
+/// the signature here, so signatures found through this index must be verified against the content.
+pub(crate) fn validate_create_asset_digest_to_signature_link(
+    action: CreateLink,
+    base_address: AnyLinkableHash,
+    target_address: AnyLinkableHash,
+) -> ExternResult<ValidateCallbackResult> {
+    if ExternalHash::try_from(base_address).is_err() {
+        return Ok(ValidateCallbackResult::Invalid(
+            "Base address is not an ExternalHash".to_string(),
+        ));
+    }
+
+    let target = must_get_valid_record(target_address.try_into().map_err(|_| {
+        wasm_error!(WasmErrorInner::Guest(
+            "Target address is not an action hash".to_string()
+        ))
+    })?)?;
+
+    if target.signed_action.hashed.author() != &action.author {
+        return Ok(ValidateCallbackResult::Invalid(
+            "The target entry is not authored by the link author".to_string(),
+        ));
+    }
+
+    let entry_type: EntryType = UnitEntryTypes::AssetSignature.try_into()?;
+
+    if target
+        .signed_action
+        .hashed
+        .content
+        .entry_type()
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("No entry type".to_string())))?
+        != &entry_type
+    {
+        return Ok(ValidateCallbackResult::Invalid(
+            "The target entry is not an AssetSignature".to_string(),
+        ));
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+pub(crate) fn validate_delete_asset_digest_to_signature_link(
+    original_action: CreateLink,
+    delete: DeleteLink,
+) -> ExternResult<ValidateCallbackResult> {
+    if original_action.author != delete.author {
+        return Ok(ValidateCallbackResult::Invalid(
+            "The delete author does not match the original author".to_string(),
+        ));
+    }
+
     Ok(ValidateCallbackResult::Valid)
 }
 
@@ -1,13 +1,128 @@
+use checked_types::canonical_url::{
+    UrlCanonicalization, CURRENT_URL_CANONICALIZATION, SUPPORTED_URL_CANONICALIZATIONS,
+};
 use hdi::prelude::*;
+use signing_keys_types::{
+    VerificationKeyDist, SIGNING_KEYS_INTEGRITY_ZOME_INDEX, VERIFICATION_KEY_DIST_ENTRY_INDEX,
+};
 
+/// The anchor that signatures for `asset_url` are linked from, using the
+/// [CURRENT_URL_CANONICALIZATION].
 pub fn make_asset_url_address(asset_url: &str) -> ExternResult<ExternalHash> {
-    let mut url = url::Url::parse(asset_url)
+    make_asset_url_address_for(asset_url, CURRENT_URL_CANONICALIZATION)
+}
+
+/// The anchors that signatures for `asset_url` may be linked from, one for each of the
+/// [SUPPORTED_URL_CANONICALIZATIONS].
+pub fn make_asset_url_addresses(asset_url: &str) -> ExternResult<Vec<ExternalHash>> {
+    SUPPORTED_URL_CANONICALIZATIONS
+        .iter()
+        .map(|canonicalization| make_asset_url_address_for(asset_url, *canonicalization))
+        .collect()
+}
+
+fn make_asset_url_address_for(
+    asset_url: &str,
+    canonicalization: UrlCanonicalization,
+) -> ExternResult<ExternalHash> {
+    let url = canonicalization
+        .canonicalize(asset_url)
         .map_err(|e| wasm_error!(WasmErrorInner::Guest(e.to_string())))?;
 
-    url.set_password(None).ok();
-    url.set_username("").ok();
+    // Include the canonicalization version so that each version produces distinct anchors.
+    let mut hash =
+        holo_hash::blake2b_256(format!("{}:{}", canonicalization.label(), url.as_str()).as_bytes());
+    hash.extend_from_slice(&[0, 0, 0, 0]);
+    Ok(ExternalHash::from_raw_36(hash))
+}
+
+/// The anchor that signatures are linked from by the SHA-256 digest of the content that they sign.
+///
+/// Uses the same hashing scheme as the URL anchors, with its own prefix so that the two can never
+/// collide.
+pub fn make_asset_digest_address(content_sha256: &[u8]) -> ExternResult<ExternalHash> {
+    if content_sha256.len() != 32 {
+        return Err(wasm_error!(WasmErrorInner::Guest(format!(
+            "Expected a 32 byte SHA-256 digest but got {} bytes",
+            content_sha256.len()
+        ))));
+    }
 
-    let mut hash = holo_hash::blake2b_256(url.as_str().as_bytes());
+    let mut preimage = b"sha256:".to_vec();
+    preimage.extend_from_slice(content_sha256);
+    let mut hash = holo_hash::blake2b_256(&preimage);
     hash.extend_from_slice(&[0, 0, 0, 0]);
     Ok(ExternalHash::from_raw_36(hash))
 }
+
+/// Find the [VerificationKeyDist] at `key_dist_address` in the `agent_activity` of its `author`.
+///
+/// The key dist is a type defined in another zome, so it is found on the author's chain and its
+/// entry type checked against the `signing_keys_integrity` zome's `VerificationKeyDist` def. If it can't be found then the
+/// reason is returned as the inner error, to be used as the reason that validation failed.
+pub(crate) fn get_key_dist_from_chain(
+    agent_activity: &[RegisterAgentActivity],
+    author: &AgentPubKey,
+    key_dist_address: &ActionHash,
+) -> ExternResult<Result<VerificationKeyDist, String>> {
+    let maybe_found_key_dist = agent_activity.iter().find_map(|activity| {
+        if activity.action.action_address() != key_dist_address {
+            return None;
+        }
+
+        match activity.action.action() {
+            Action::Create(create) if author == &create.author => Some(create),
+            _ => None,
+        }
+    });
+
+    let key_dist_create = match maybe_found_key_dist {
+        Some(create) => create,
+        None => {
+            return Ok(Err(
+                "The key dist address does not point to a valid action on the author's chain"
+                    .to_string(),
+            ));
+        }
+    };
+
+    match &key_dist_create.entry_type {
+        EntryType::App(AppEntryDef {
+            entry_index,
+            zome_index,
+            visibility: EntryVisibility::Public,
+        }) if *zome_index == SIGNING_KEYS_INTEGRITY_ZOME_INDEX
+            && *entry_index == VERIFICATION_KEY_DIST_ENTRY_INDEX =>
+        {
+            match must_get_entry(key_dist_create.entry_hash.clone())?.try_into() {
+                Ok(key_dist) => Ok(Ok(key_dist)),
+                Err(_) => Ok(Err(
+                    "The key dist address does not point to a VerificationKeyDist".to_string(),
+                )),
+            }
+        }
+        _ => Ok(Err(
+            "The key dist address does not point to a VerificationKeyDist".to_string(),
+        )),
+    }
+}
+
+/// The anchor that [PublisherClaim](fetch_types::PublisherClaim)s for `url_prefix` are linked from.
+///
+/// The `url_prefix` must already be canonical, see
+/// [canonicalize_url_prefix](checked_types::canonical_url::canonicalize_url_prefix).
+pub fn make_url_prefix_address(url_prefix: &str) -> ExternalHash {
+    let mut hash = holo_hash::blake2b_256(format!("publisher:{}", url_prefix).as_bytes());
+    hash.extend_from_slice(&[0, 0, 0, 0]);
+    ExternalHash::from_raw_36(hash)
+}
+
+/// The anchor that [DomainClaim](fetch_types::DomainClaim)s for `domain` are linked from.
+///
+/// The `domain` must already be canonical, see
+/// [canonicalize_domain](checked_types::canonical_url::canonicalize_domain).
+pub fn make_domain_address(domain: &str) -> ExternalHash {
+    let mut hash = holo_hash::blake2b_256(format!("domain:{}", domain).as_bytes());
+    hash.extend_from_slice(&[0, 0, 0, 0]);
+    ExternalHash::from_raw_36(hash)
+}
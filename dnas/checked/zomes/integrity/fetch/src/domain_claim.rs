@@ -0,0 +1,114 @@
+use super::UnitEntryTypes;
+use crate::common::{get_key_dist_from_chain, make_domain_address};
+use checked_types::canonical_url::canonicalize_domain;
+use fetch_types::DomainClaim;
+use hdi::prelude::*;
+
+pub(crate) fn validate_create_domain_claim(
+    create_action: EntryCreationAction,
+    domain_claim: DomainClaim,
+) -> ExternResult<ValidateCallbackResult> {
+    match canonicalize_domain(&domain_claim.domain) {
+        Ok(domain) if domain == domain_claim.domain => {}
+        Ok(_) => {
+            return Ok(ValidateCallbackResult::Invalid(
+                "The domain is not in its canonical form".to_string(),
+            ));
+        }
+        Err(reason) => return Ok(ValidateCallbackResult::Invalid(reason)),
+    }
+
+    let agent_activity = must_get_agent_activity(
+        create_action.author().clone(),
+        ChainFilter::new(create_action.prev_action().clone()),
+    )?;
+
+    if let Err(reason) = get_key_dist_from_chain(
+        &agent_activity,
+        create_action.author(),
+        &domain_claim.key_dist_address,
+    )? {
+        return Ok(ValidateCallbackResult::Invalid(reason));
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+pub(crate) fn validate_delete_domain_claim(
+    delete: Delete,
+    prev_action: SignedActionHashed,
+) -> ExternResult<ValidateCallbackResult> {
+    if &delete.author != prev_action.action().author() {
+        return Ok(ValidateCallbackResult::Invalid(
+            "The delete author does not match the previous action author".to_string(),
+        ));
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+pub(crate) fn validate_create_domain_to_domain_claim_link(
+    action: CreateLink,
+    base_address: AnyLinkableHash,
+    target_address: AnyLinkableHash,
+) -> ExternResult<ValidateCallbackResult> {
+    let target = must_get_valid_record(target_address.try_into().map_err(|_| {
+        wasm_error!(WasmErrorInner::Guest(
+            "Target address is not an action hash".to_string()
+        ))
+    })?)?;
+
+    if target.signed_action.hashed.author() != &action.author {
+        return Ok(ValidateCallbackResult::Invalid(
+            "The target entry is not authored by the link author".to_string(),
+        ));
+    }
+
+    let entry_type: EntryType = UnitEntryTypes::DomainClaim.try_into()?;
+
+    if target
+        .signed_action
+        .hashed
+        .content
+        .entry_type()
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("No entry type".to_string())))?
+        != &entry_type
+    {
+        return Ok(ValidateCallbackResult::Invalid(
+            "The target entry is not a DomainClaim".to_string(),
+        ));
+    }
+
+    let domain_claim: DomainClaim = target
+        .entry
+        .into_option()
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("No entry".to_string())))?
+        .try_into()?;
+
+    let base_address: ExternalHash = base_address.try_into().map_err(|_| {
+        wasm_error!(WasmErrorInner::Guest(
+            "Base address is not an ExternalHash".to_string()
+        ))
+    })?;
+
+    if base_address != make_domain_address(&domain_claim.domain) {
+        return Ok(ValidateCallbackResult::Invalid(
+            "The base address does not match the expected address for the domain".to_string(),
+        ));
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+pub(crate) fn validate_delete_domain_to_domain_claim_link(
+    original_action: CreateLink,
+    delete: DeleteLink,
+) -> ExternResult<ValidateCallbackResult> {
+    if original_action.author != delete.author {
+        return Ok(ValidateCallbackResult::Invalid(
+            "The delete author does not match the original author".to_string(),
+        ));
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
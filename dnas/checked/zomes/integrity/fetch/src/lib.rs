@@ -1,12 +1,20 @@
+mod asset_alias;
 mod asset_signature;
 mod common;
+mod domain_claim;
+mod publisher_claim;
 
 use hdi::prelude::*;
 
 pub mod prelude {
-    pub use crate::common::make_asset_url_address;
+    pub use crate::asset_signature::MAX_FETCH_URL_LENGTH;
+    pub use crate::common::{
+        make_asset_digest_address, make_asset_url_address, make_asset_url_addresses,
+        make_domain_address, make_url_prefix_address,
+    };
     pub use crate::LinkTypes;
     pub use crate::{EntryTypes, UnitEntryTypes};
+    pub use checked_types::properties::get_rate_limits;
     pub use fetch_types::*;
 }
 
@@ -16,11 +24,18 @@ pub mod prelude {
 #[unit_enum(UnitEntryTypes)]
 pub enum EntryTypes {
     AssetSignature(fetch_types::AssetSignature),
+    AssetAlias(fetch_types::AssetAlias),
+    PublisherClaim(fetch_types::PublisherClaim),
+    DomainClaim(fetch_types::DomainClaim),
 }
 
 #[hdk_link_types]
 pub enum LinkTypes {
     AssetUrlToSignature,
+    AssetUrlToAlias,
+    AssetDigestToSignature,
+    UrlPrefixToPublisherClaim,
+    DomainToDomainClaim,
 }
 
 // Validation you perform during the genesis process. Nobody else on the network performs it, only you.
@@ -70,11 +85,36 @@ pub fn validate(op: Op) -> ExternResult<ValidateCallbackResult> {
                         asset_signature,
                     )
                 }
+                EntryTypes::AssetAlias(asset_alias) => asset_alias::validate_create_asset_alias(
+                    EntryCreationAction::Create(action),
+                    asset_alias,
+                ),
+                EntryTypes::PublisherClaim(publisher_claim) => {
+                    publisher_claim::validate_create_publisher_claim(
+                        EntryCreationAction::Create(action),
+                        publisher_claim,
+                    )
+                }
+                EntryTypes::DomainClaim(domain_claim) => {
+                    domain_claim::validate_create_domain_claim(
+                        EntryCreationAction::Create(action),
+                        domain_claim,
+                    )
+                }
             },
             OpEntry::UpdateEntry { app_entry, .. } => match app_entry {
                 EntryTypes::AssetSignature(_) => Ok(ValidateCallbackResult::Invalid(
                     "Asset signatures cannot be updated".to_string(),
                 )),
+                EntryTypes::AssetAlias(_) => Ok(ValidateCallbackResult::Invalid(
+                    "Asset aliases cannot be updated".to_string(),
+                )),
+                EntryTypes::PublisherClaim(_) => Ok(ValidateCallbackResult::Invalid(
+                    "Publisher claims cannot be updated".to_string(),
+                )),
+                EntryTypes::DomainClaim(_) => Ok(ValidateCallbackResult::Invalid(
+                    "Domain claims cannot be updated".to_string(),
+                )),
             },
             _ => Ok(ValidateCallbackResult::Valid),
         },
@@ -83,6 +123,15 @@ pub fn validate(op: Op) -> ExternResult<ValidateCallbackResult> {
                 EntryTypes::AssetSignature(_) => Ok(ValidateCallbackResult::Invalid(
                     "Asset signatures cannot be updated".to_string(),
                 )),
+                EntryTypes::AssetAlias(_) => Ok(ValidateCallbackResult::Invalid(
+                    "Asset aliases cannot be updated".to_string(),
+                )),
+                EntryTypes::PublisherClaim(_) => Ok(ValidateCallbackResult::Invalid(
+                    "Publisher claims cannot be updated".to_string(),
+                )),
+                EntryTypes::DomainClaim(_) => Ok(ValidateCallbackResult::Invalid(
+                    "Domain claims cannot be updated".to_string(),
+                )),
             },
             _ => Ok(ValidateCallbackResult::Valid),
         },
@@ -96,9 +145,20 @@ pub fn validate(op: Op) -> ExternResult<ValidateCallbackResult> {
                         _ => return Ok(ValidateCallbackResult::Valid),
                     };
 
-                    let entry_def: AppEntryDef = UnitEntryTypes::AssetSignature.try_into()?;
-                    if prev_entry_def == &entry_def {
+                    let asset_signature_def: AppEntryDef =
+                        UnitEntryTypes::AssetSignature.try_into()?;
+                    let asset_alias_def: AppEntryDef = UnitEntryTypes::AssetAlias.try_into()?;
+                    let publisher_claim_def: AppEntryDef =
+                        UnitEntryTypes::PublisherClaim.try_into()?;
+                    let domain_claim_def: AppEntryDef = UnitEntryTypes::DomainClaim.try_into()?;
+                    if prev_entry_def == &asset_signature_def {
                         asset_signature::validate_delete_asset_signature(action, deleted_action)
+                    } else if prev_entry_def == &asset_alias_def {
+                        asset_alias::validate_delete_asset_alias(action, deleted_action)
+                    } else if prev_entry_def == &publisher_claim_def {
+                        publisher_claim::validate_delete_publisher_claim(action, deleted_action)
+                    } else if prev_entry_def == &domain_claim_def {
+                        domain_claim::validate_delete_domain_claim(action, deleted_action)
                     } else {
                         Ok(ValidateCallbackResult::Valid)
                     }
@@ -123,6 +183,32 @@ pub fn validate(op: Op) -> ExternResult<ValidateCallbackResult> {
                     link_type,
                 )
             }
+            LinkTypes::AssetUrlToAlias => asset_alias::validate_create_asset_url_to_alias_link(
+                action,
+                base_address,
+                target_address,
+            ),
+            LinkTypes::AssetDigestToSignature => {
+                asset_signature::validate_create_asset_digest_to_signature_link(
+                    action,
+                    base_address,
+                    target_address,
+                )
+            }
+            LinkTypes::UrlPrefixToPublisherClaim => {
+                publisher_claim::validate_create_url_prefix_to_publisher_claim_link(
+                    action,
+                    base_address,
+                    target_address,
+                )
+            }
+            LinkTypes::DomainToDomainClaim => {
+                domain_claim::validate_create_domain_to_domain_claim_link(
+                    action,
+                    base_address,
+                    target_address,
+                )
+            }
         },
         FlatOp::RegisterDeleteLink {
             link_type,
@@ -136,6 +222,24 @@ pub fn validate(op: Op) -> ExternResult<ValidateCallbackResult> {
                     action,
                 )
             }
+            LinkTypes::AssetUrlToAlias => {
+                asset_alias::validate_delete_asset_url_to_alias_link(original_action, action)
+            }
+            LinkTypes::AssetDigestToSignature => {
+                asset_signature::validate_delete_asset_digest_to_signature_link(
+                    original_action,
+                    action,
+                )
+            }
+            LinkTypes::UrlPrefixToPublisherClaim => {
+                publisher_claim::validate_delete_url_prefix_to_publisher_claim_link(
+                    original_action,
+                    action,
+                )
+            }
+            LinkTypes::DomainToDomainClaim => {
+                domain_claim::validate_delete_domain_to_domain_claim_link(original_action, action)
+            }
         },
         FlatOp::StoreRecord(store_record) => {
             match store_record {
@@ -149,6 +253,24 @@ pub fn validate(op: Op) -> ExternResult<ValidateCallbackResult> {
                             asset_signature,
                         )
                     }
+                    EntryTypes::AssetAlias(asset_alias) => {
+                        asset_alias::validate_create_asset_alias(
+                            EntryCreationAction::Create(action),
+                            asset_alias,
+                        )
+                    }
+                    EntryTypes::PublisherClaim(publisher_claim) => {
+                        publisher_claim::validate_create_publisher_claim(
+                            EntryCreationAction::Create(action),
+                            publisher_claim,
+                        )
+                    }
+                    EntryTypes::DomainClaim(domain_claim) => {
+                        domain_claim::validate_create_domain_claim(
+                            EntryCreationAction::Create(action),
+                            domain_claim,
+                        )
+                    }
                 },
                 // Complementary validation to the `RegisterUpdate` Op, in which the record itself is validated
                 // If you want to optimize performance, you can remove the validation for an entry type here and keep it in `StoreEntry` and in `RegisterUpdate`
@@ -174,6 +296,15 @@ pub fn validate(op: Op) -> ExternResult<ValidateCallbackResult> {
                         EntryTypes::AssetSignature(_) => Ok(ValidateCallbackResult::Invalid(
                             "Asset signatures cannot be updated".to_string(),
                         )),
+                        EntryTypes::AssetAlias(_) => Ok(ValidateCallbackResult::Invalid(
+                            "Asset aliases cannot be updated".to_string(),
+                        )),
+                        EntryTypes::PublisherClaim(_) => Ok(ValidateCallbackResult::Invalid(
+                            "Publisher claims cannot be updated".to_string(),
+                        )),
+                        EntryTypes::DomainClaim(_) => Ok(ValidateCallbackResult::Invalid(
+                            "Domain claims cannot be updated".to_string(),
+                        )),
                     }
                 }
                 // Complementary validation to the `RegisterDelete` Op, in which the record itself is validated
@@ -239,6 +370,20 @@ pub fn validate(op: Op) -> ExternResult<ValidateCallbackResult> {
                                 original_record.signed_action,
                             )
                         }
+                        EntryTypes::AssetAlias(_) => asset_alias::validate_delete_asset_alias(
+                            action,
+                            original_record.signed_action,
+                        ),
+                        EntryTypes::PublisherClaim(_) => {
+                            publisher_claim::validate_delete_publisher_claim(
+                                action,
+                                original_record.signed_action,
+                            )
+                        }
+                        EntryTypes::DomainClaim(_) => domain_claim::validate_delete_domain_claim(
+                            action,
+                            original_record.signed_action,
+                        ),
                     }
                 }
                 // Complementary validation to the `RegisterCreateLink` Op, in which the record itself is validated
@@ -259,6 +404,34 @@ pub fn validate(op: Op) -> ExternResult<ValidateCallbackResult> {
                             link_type,
                         )
                     }
+                    LinkTypes::AssetUrlToAlias => {
+                        asset_alias::validate_create_asset_url_to_alias_link(
+                            action,
+                            base_address,
+                            target_address,
+                        )
+                    }
+                    LinkTypes::AssetDigestToSignature => {
+                        asset_signature::validate_create_asset_digest_to_signature_link(
+                            action,
+                            base_address,
+                            target_address,
+                        )
+                    }
+                    LinkTypes::UrlPrefixToPublisherClaim => {
+                        publisher_claim::validate_create_url_prefix_to_publisher_claim_link(
+                            action,
+                            base_address,
+                            target_address,
+                        )
+                    }
+                    LinkTypes::DomainToDomainClaim => {
+                        domain_claim::validate_create_domain_to_domain_claim_link(
+                            action,
+                            base_address,
+                            target_address,
+                        )
+                    }
                 },
                 // Complementary validation to the `RegisterDeleteLink` Op, in which the record itself is validated
                 // If you want to optimize performance, you can remove the validation for an entry type here and keep it in `RegisterDeleteLink`
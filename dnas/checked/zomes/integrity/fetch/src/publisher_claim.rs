@@ -0,0 +1,122 @@
+use super::UnitEntryTypes;
+use crate::common::{get_key_dist_from_chain, make_url_prefix_address};
+use crate::prelude::MAX_FETCH_URL_LENGTH;
+use checked_types::canonical_url::canonicalize_url_prefix;
+use fetch_types::PublisherClaim;
+use hdi::prelude::*;
+
+pub(crate) fn validate_create_publisher_claim(
+    create_action: EntryCreationAction,
+    publisher_claim: PublisherClaim,
+) -> ExternResult<ValidateCallbackResult> {
+    if publisher_claim.url_prefix.len() > MAX_FETCH_URL_LENGTH {
+        return Ok(ValidateCallbackResult::Invalid(format!(
+            "The URL prefix is longer than the maximum of {} characters",
+            MAX_FETCH_URL_LENGTH
+        )));
+    }
+
+    match canonicalize_url_prefix(&publisher_claim.url_prefix) {
+        Ok(url_prefix) if url_prefix.as_str() == publisher_claim.url_prefix => {}
+        Ok(_) => {
+            return Ok(ValidateCallbackResult::Invalid(
+                "The URL prefix is not in its canonical form".to_string(),
+            ));
+        }
+        Err(reason) => return Ok(ValidateCallbackResult::Invalid(reason)),
+    }
+
+    let agent_activity = must_get_agent_activity(
+        create_action.author().clone(),
+        ChainFilter::new(create_action.prev_action().clone()),
+    )?;
+
+    if let Err(reason) = get_key_dist_from_chain(
+        &agent_activity,
+        create_action.author(),
+        &publisher_claim.key_dist_address,
+    )? {
+        return Ok(ValidateCallbackResult::Invalid(reason));
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+pub(crate) fn validate_delete_publisher_claim(
+    delete: Delete,
+    prev_action: SignedActionHashed,
+) -> ExternResult<ValidateCallbackResult> {
+    if &delete.author != prev_action.action().author() {
+        return Ok(ValidateCallbackResult::Invalid(
+            "The delete author does not match the previous action author".to_string(),
+        ));
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+pub(crate) fn validate_create_url_prefix_to_publisher_claim_link(
+    action: CreateLink,
+    base_address: AnyLinkableHash,
+    target_address: AnyLinkableHash,
+) -> ExternResult<ValidateCallbackResult> {
+    let target = must_get_valid_record(target_address.try_into().map_err(|_| {
+        wasm_error!(WasmErrorInner::Guest(
+            "Target address is not an action hash".to_string()
+        ))
+    })?)?;
+
+    if target.signed_action.hashed.author() != &action.author {
+        return Ok(ValidateCallbackResult::Invalid(
+            "The target entry is not authored by the link author".to_string(),
+        ));
+    }
+
+    let entry_type: EntryType = UnitEntryTypes::PublisherClaim.try_into()?;
+
+    if target
+        .signed_action
+        .hashed
+        .content
+        .entry_type()
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("No entry type".to_string())))?
+        != &entry_type
+    {
+        return Ok(ValidateCallbackResult::Invalid(
+            "The target entry is not a PublisherClaim".to_string(),
+        ));
+    }
+
+    let publisher_claim: PublisherClaim = target
+        .entry
+        .into_option()
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("No entry".to_string())))?
+        .try_into()?;
+
+    let base_address: ExternalHash = base_address.try_into().map_err(|_| {
+        wasm_error!(WasmErrorInner::Guest(
+            "Base address is not an ExternalHash".to_string()
+        ))
+    })?;
+
+    if base_address != make_url_prefix_address(&publisher_claim.url_prefix) {
+        return Ok(ValidateCallbackResult::Invalid(
+            "The base address does not match the expected address for the URL prefix".to_string(),
+        ));
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+pub(crate) fn validate_delete_url_prefix_to_publisher_claim_link(
+    original_action: CreateLink,
+    delete: DeleteLink,
+) -> ExternResult<ValidateCallbackResult> {
+    if original_action.author != delete.author {
+        return Ok(ValidateCallbackResult::Invalid(
+            "The delete author does not match the original author".to_string(),
+        ));
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
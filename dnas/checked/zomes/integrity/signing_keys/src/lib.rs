@@ -419,3 +419,16 @@ pub fn validate(op: Op) -> ExternResult<ValidateCallbackResult> {
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verification_key_dist_entry_index() {
+        assert_eq!(
+            VERIFICATION_KEY_DIST_ENTRY_INDEX,
+            ZomeEntryTypesKey::from(UnitEntryTypes::VerificationKeyDist).type_index
+        );
+    }
+}
@@ -2,6 +2,7 @@
 
 use crate::convert::try_extract_entry_to_app_type;
 use crate::prelude::*;
+use checked_types::properties::get_rate_limits;
 use checked_types::VerificationKeyType;
 use hdi::prelude::*;
 use signing_keys_types::{MarkVfKeyDistOpt, VerificationKeyDist, VerificationKeyDistMark};
@@ -9,7 +10,6 @@ use signing_keys_types::{MarkVfKeyDistOpt, VerificationKeyDist, VerificationKeyD
 pub const VERIFICATION_KEY_NAME_MIN_LENGTH: usize = 3;
 pub const MAX_VF_KEY_DIST_COMPROMISED_NOTE_LENGTH: usize = 120;
 
-// TODO validate creation rate?
 pub fn validate_create_vf_key_dist(
     create_action: EntryCreationAction,
     vf_key: VerificationKeyDist,
@@ -64,19 +64,31 @@ pub fn validate_create_vf_key_dist(
     };
 
     let entry_def: AppEntryDef = UnitEntryTypes::VerificationKeyDist.try_into()?;
-    let entry_hashes = activity
+    let previous_creates = activity
         .into_iter()
         .filter_map(|activity| match activity.action.action() {
             Action::Create(Create {
                 entry_type: EntryType::App(entry_type),
                 entry_hash,
+                timestamp,
                 ..
-            }) if entry_type == &entry_def => Some(entry_hash.clone()),
+            }) if entry_type == &entry_def => Some((entry_hash.clone(), *timestamp)),
             _ => None,
         })
         .collect::<Vec<_>>();
 
-    for entry_hash in entry_hashes {
+    let rate_limit = get_rate_limits()?.verification_key_dist;
+    if rate_limit.is_exceeded_by(
+        *create_action.timestamp(),
+        previous_creates.iter().map(|(_, timestamp)| *timestamp),
+    ) {
+        return Ok(ValidateCallbackResult::Invalid(format!(
+            "Too many verification keys distributed, at most {} are allowed every {} seconds",
+            rate_limit.max_count, rate_limit.window_seconds
+        )));
+    }
+
+    for (entry_hash, _) in previous_creates {
         let entry: VerificationKeyDist =
             try_extract_entry_to_app_type(must_get_entry(entry_hash)?)?;
         if entry.verification_key == vf_key.verification_key {
@@ -324,13 +336,17 @@ pub(crate) fn validate_create_vf_key_dist_mark(
     // Usually expect only 1 mark for a key, so don't allocate memory here until we need it.
     let entry_def: AppEntryDef = UnitEntryTypes::VerificationKeyDistMark.try_into()?;
     let mut other_marks_for_vf_key_dist = Vec::with_capacity(0);
+    let mut previous_mark_timestamps = Vec::new();
     for activity in activity.into_iter() {
         match activity.action.action() {
             Action::Create(Create {
                 entry_type: EntryType::App(entry_type),
                 entry_hash,
+                timestamp,
                 ..
             }) if entry_type == &entry_def => {
+                previous_mark_timestamps.push(*timestamp);
+
                 let existing_mark: VerificationKeyDistMark =
                     try_extract_entry_to_app_type(must_get_entry(entry_hash.clone())?)?;
 
@@ -344,6 +360,14 @@ pub(crate) fn validate_create_vf_key_dist_mark(
         };
     }
 
+    let rate_limit = get_rate_limits()?.verification_key_dist_mark;
+    if rate_limit.is_exceeded_by(*create_action.timestamp(), previous_mark_timestamps) {
+        return Ok(ValidateCallbackResult::Invalid(format!(
+            "Too many verification key marks created, at most {} are allowed every {} seconds",
+            rate_limit.max_count, rate_limit.window_seconds
+        )));
+    }
+
     match mark.mark {
         MarkVfKeyDistOpt::Rotated { .. } => {
             if other_marks_for_vf_key_dist
@@ -5,6 +5,7 @@ const utf8Encode = new TextEncoder();
 
 export interface PrepareFetchRequest {
   fetch_url: string;
+  include_aliases?: boolean;
 }
 
 export interface FetchCheckSignaturePinned {
@@ -14,6 +15,7 @@ export interface FetchCheckSignaturePinned {
 
 export type FetchCheckSignatureReason =
   | "Mine"
+  | "Publisher"
   | "RandomRecent"
   | "RandomHistorical"
   | { Pinned: FetchCheckSignaturePinned };
@@ -25,6 +27,7 @@ export interface FetchCheckSignature {
   author: AgentPubKey;
   key_dist_address: ActionHash;
   reason: FetchCheckSignatureReason;
+  alias_url: string | null;
 }
 
 export interface CreateAssetSignature {
@@ -32,6 +35,47 @@ export interface CreateAssetSignature {
   signature: string;
   key_type: { MiniSignEd25519: null };
   verification_key: string;
+  content_sha256?: number[];
+}
+
+export interface FindSignaturesByDigestRequest {
+  content_sha256: number[];
+}
+
+export interface DigestSignature {
+  fetch_url: string;
+  signature: string;
+  key_type: { MiniSignEd25519: null };
+  verification_key: string;
+  author: AgentPubKey;
+  key_dist_address: ActionHash;
+  created_at: number;
+}
+
+export interface CreateAssetAlias {
+  fetch_url: string;
+  alias_url: string;
+}
+
+export interface CreatePublisherClaim {
+  url_prefix: string;
+  key_type: { MiniSignEd25519: null };
+  verification_key: string;
+}
+
+export interface CreateDomainClaim {
+  domain: string;
+  key_type: { MiniSignEd25519: null };
+  verification_key: string;
+}
+
+export interface DomainClaimResponse {
+  domain: string;
+  author: AgentPubKey;
+  key_dist_address: ActionHash;
+  key_type: { MiniSignEd25519: null };
+  verification_key: string;
+  created_at: number;
 }
 
 export interface DeleteAssetSignatureRequest {
@@ -56,6 +100,17 @@ export const prepareFetch = async (
   });
 };
 
+export const prepareFetchLocal = async (
+  cell: CallableCell,
+  request: PrepareFetchRequest,
+): Promise<FetchCheckSignature[]> => {
+  return cell.callZome({
+    zome_name: "fetch",
+    fn_name: "prepare_fetch_local",
+    payload: request,
+  });
+};
+
 export const createAssetSignature = async (
   cell: CallableCell,
   request: CreateAssetSignature,
@@ -67,6 +122,17 @@ export const createAssetSignature = async (
   });
 };
 
+export const createAssetSignatures = async (
+  cell: CallableCell,
+  requests: CreateAssetSignature[],
+): Promise<ActionHash[]> => {
+  return cell.callZome({
+    zome_name: "fetch",
+    fn_name: "create_asset_signatures",
+    payload: requests,
+  });
+};
+
 export const getMyAssetSignatures = async (
   cell: CallableCell,
 ): Promise<AssetSignatureResponse[]> => {
@@ -88,6 +154,61 @@ export const deleteAssetSignature = async (
   });
 };
 
+export const findSignaturesByDigest = async (
+  cell: CallableCell,
+  request: FindSignaturesByDigestRequest,
+): Promise<DigestSignature[]> => {
+  return cell.callZome({
+    zome_name: "fetch",
+    fn_name: "find_signatures_by_digest",
+    payload: request,
+  });
+};
+
+export const createAssetAlias = async (
+  cell: CallableCell,
+  request: CreateAssetAlias,
+): Promise<ActionHash> => {
+  return cell.callZome({
+    zome_name: "fetch",
+    fn_name: "create_asset_alias",
+    payload: request,
+  });
+};
+
+export const createPublisherClaim = async (
+  cell: CallableCell,
+  request: CreatePublisherClaim,
+): Promise<ActionHash> => {
+  return cell.callZome({
+    zome_name: "fetch",
+    fn_name: "create_publisher_claim",
+    payload: request,
+  });
+};
+
+export const createDomainClaim = async (
+  cell: CallableCell,
+  request: CreateDomainClaim,
+): Promise<ActionHash> => {
+  return cell.callZome({
+    zome_name: "fetch",
+    fn_name: "create_domain_claim",
+    payload: request,
+  });
+};
+
+export const getDomainClaims = async (
+  cell: CallableCell,
+  domain: string,
+): Promise<DomainClaimResponse[]> => {
+  return cell.callZome({
+    zome_name: "fetch",
+    fn_name: "get_domain_claims",
+    payload: domain,
+  });
+};
+
 export const sampleFetchKey = () => {
   return `
 untrusted comment: minisign public key: B7ED5BB003A859F1
@@ -4,10 +4,17 @@ import { dhtSync, runScenario } from "@holochain/tryorama";
 
 import { testAppPath } from "../common";
 import {
+  createAssetAlias,
   createAssetSignature,
+  createAssetSignatures,
+  createDomainClaim,
+  createPublisherClaim,
   deleteAssetSignature,
+  findSignaturesByDigest,
+  getDomainClaims,
   getMyAssetSignatures,
   prepareFetch,
+  prepareFetchLocal,
   sampleFetchAssetSignature,
   sampleFetchKey,
   sampleFetchKeyOther,
@@ -36,6 +43,215 @@ test("Prepare fetch with no existing signatures", async () => {
   });
 });
 
+test("Prepare fetch local finds my existing signature", async () => {
+  await runScenario(async (scenario) => {
+    const appSource = { appBundleSource: { type: "path", value: testAppPath } };
+
+    const [alice] = await scenario.addPlayersWithApps([appSource]);
+
+    const record = await distributeVerificationKey(
+      alice.cells[0],
+      sampleFetchKey(),
+      sampleFetchKeyProof(),
+      sampleFetchKeyProofSignature(),
+    );
+    const vf_key_dist_address = record.signed_action.hashed.hash;
+
+    await createAssetSignature(alice.cells[0], {
+      fetch_url: "https://example.com/sample.csv",
+      signature: sampleFetchAssetSignature(),
+      key_type: { MiniSignEd25519: null },
+      verification_key: sampleFetchKey(),
+    });
+
+    const check_signatures = await prepareFetchLocal(alice.cells[0], {
+      fetch_url: "https://example.com/sample.csv",
+    });
+
+    assert.equal(check_signatures.length, 1);
+    assert.equal(check_signatures[0].reason, "Mine");
+    assert.deepEqual(
+      check_signatures[0].key_dist_address,
+      vf_key_dist_address,
+    );
+  });
+});
+
+test("Signatures are found for equivalent URLs", async () => {
+  await runScenario(async (scenario) => {
+    const appSource = { appBundleSource: { type: "path", value: testAppPath } };
+
+    const [alice] = await scenario.addPlayersWithApps([appSource]);
+
+    await distributeVerificationKey(
+      alice.cells[0],
+      sampleFetchKey(),
+      sampleFetchKeyProof(),
+      sampleFetchKeyProofSignature(),
+    );
+
+    await createAssetSignature(alice.cells[0], {
+      fetch_url: "https://Example.com:443//sample.csv#download",
+      signature: sampleFetchAssetSignature(),
+      key_type: { MiniSignEd25519: null },
+      verification_key: sampleFetchKey(),
+    });
+
+    const check_signatures = await prepareFetchLocal(alice.cells[0], {
+      fetch_url: "https://example.com/sample.csv?utm_source=test",
+    });
+
+    assert.equal(check_signatures.length, 1);
+    assert.equal(check_signatures[0].reason, "Mine");
+  });
+});
+
+test("Signatures are found through asset aliases", async () => {
+  await runScenario(async (scenario) => {
+    const appSource = { appBundleSource: { type: "path", value: testAppPath } };
+
+    const [alice, bob] = await scenario.addPlayersWithApps([
+      appSource,
+      appSource,
+    ]);
+
+    await distributeVerificationKey(
+      alice.cells[0],
+      sampleFetchKey(),
+      sampleFetchKeyProof(),
+      sampleFetchKeyProofSignature(),
+    );
+
+    // Alice has signed the same asset at two locations
+    for (const fetch_url of [
+      "https://example.com/sample.csv",
+      "https://mirror.example.com/sample.csv",
+    ]) {
+      await createAssetSignature(alice.cells[0], {
+        fetch_url,
+        signature: sampleFetchAssetSignature(),
+        key_type: { MiniSignEd25519: null },
+        verification_key: sampleFetchKey(),
+      });
+    }
+
+    await createAssetAlias(alice.cells[0], {
+      fetch_url: "https://example.com/sample.csv",
+      alias_url: "https://mirror.example.com/sample.csv",
+    });
+
+    await dhtSync([alice, bob], alice.cells[0].cell_id[0]);
+
+    const without_aliases = await prepareFetch(bob.cells[0], {
+      fetch_url: "https://example.com/sample.csv",
+    });
+
+    assert.equal(without_aliases.length, 1);
+    assert.equal(without_aliases[0].alias_url, null);
+
+    const with_aliases = await prepareFetch(bob.cells[0], {
+      fetch_url: "https://example.com/sample.csv",
+      include_aliases: true,
+    });
+
+    assert.equal(with_aliases.length, 2);
+    assert.sameMembers(
+      with_aliases.map((s) => s.alias_url),
+      [null, "https://mirror.example.com/sample.csv"],
+    );
+  });
+});
+
+test("Cannot create an asset alias for signatures over different content", async () => {
+  await runScenario(async (scenario) => {
+    const appSource = { appBundleSource: { type: "path", value: testAppPath } };
+
+    const [alice] = await scenario.addPlayersWithApps([appSource]);
+
+    await distributeVerificationKey(
+      alice.cells[0],
+      sampleFetchKey(),
+      sampleFetchKeyProof(),
+      sampleFetchKeyProofSignature(),
+    );
+
+    await createAssetSignature(alice.cells[0], {
+      fetch_url: "https://example.com/sample.csv",
+      signature: sampleFetchAssetSignature(),
+      key_type: { MiniSignEd25519: null },
+      verification_key: sampleFetchKey(),
+    });
+
+    // A valid signature from the same key, but over the key proof rather than the asset
+    await createAssetSignature(alice.cells[0], {
+      fetch_url: "https://example.com/proof.txt",
+      signature: new TextDecoder().decode(
+        new Uint8Array(sampleFetchKeyProofSignature()),
+      ),
+      key_type: { MiniSignEd25519: null },
+      verification_key: sampleFetchKey(),
+    });
+
+    let err_msg = "";
+    try {
+      await createAssetAlias(alice.cells[0], {
+        fetch_url: "https://example.com/sample.csv",
+        alias_url: "https://example.com/proof.txt",
+      });
+    } catch (e) {
+      err_msg = e.message;
+    }
+    assert.isTrue(
+      err_msg.includes("The asset signatures are not for the same content"),
+    );
+  });
+});
+
+test("Find signatures by content digest", async () => {
+  await runScenario(async (scenario) => {
+    const appSource = { appBundleSource: { type: "path", value: testAppPath } };
+
+    const [alice, bob] = await scenario.addPlayersWithApps([
+      appSource,
+      appSource,
+    ]);
+
+    await distributeVerificationKey(
+      alice.cells[0],
+      sampleFetchKey(),
+      sampleFetchKeyProof(),
+      sampleFetchKeyProofSignature(),
+    );
+
+    // The digest isn't checked on Holochain, so any 32 bytes will do here.
+    const content_sha256 = Array(32).fill(7);
+
+    await createAssetSignature(alice.cells[0], {
+      fetch_url: "https://example.com/sample.csv",
+      signature: sampleFetchAssetSignature(),
+      key_type: { MiniSignEd25519: null },
+      verification_key: sampleFetchKey(),
+      content_sha256,
+    });
+
+    await dhtSync([alice, bob], alice.cells[0].cell_id[0]);
+
+    const found = await findSignaturesByDigest(bob.cells[0], {
+      content_sha256,
+    });
+
+    assert.equal(found.length, 1);
+    assert.equal(found[0].fetch_url, "https://example.com/sample.csv");
+    assert.deepEqual(found[0].author, alice.agentPubKey);
+
+    const not_found = await findSignaturesByDigest(bob.cells[0], {
+      content_sha256: Array(32).fill(8),
+    });
+
+    assert.equal(not_found.length, 0);
+  });
+});
+
 test("Create asset signature", async () => {
   await runScenario(async (scenario) => {
     const appSource = { appBundleSource: { type: "path", value: testAppPath } };
@@ -67,6 +283,82 @@ test("Create asset signature", async () => {
   });
 });
 
+test("Create asset signatures in a batch", async () => {
+  await runScenario(async (scenario) => {
+    const appSource = { appBundleSource: { type: "path", value: testAppPath } };
+
+    const [alice] = await scenario.addPlayersWithApps([appSource]);
+
+    const record = await distributeVerificationKey(
+      alice.cells[0],
+      sampleFetchKey(),
+      sampleFetchKeyProof(),
+      sampleFetchKeyProofSignature(),
+    );
+    const vf_key_dist_address = record.signed_action.hashed.hash;
+
+    const addresses = await createAssetSignatures(alice.cells[0], [
+      {
+        fetch_url: "https://example.com/v1/sample.csv",
+        signature: sampleFetchAssetSignature(),
+        key_type: { MiniSignEd25519: null },
+        verification_key: sampleFetchKey(),
+      },
+      {
+        fetch_url: "https://example.com/v1/other.csv",
+        signature: sampleFetchAssetSignature(),
+        key_type: { MiniSignEd25519: null },
+        verification_key: sampleFetchKey(),
+      },
+    ]);
+    assert.equal(addresses.length, 2);
+
+    const my_asset_signatures = await getMyAssetSignatures(alice.cells[0]);
+
+    assert.equal(my_asset_signatures.length, 2);
+    assert.sameMembers(
+      my_asset_signatures.map((s) => s.fetch_url),
+      ["https://example.com/v1/sample.csv", "https://example.com/v1/other.csv"],
+    );
+    for (const signature of my_asset_signatures) {
+      assert.deepEqual(signature.key_dist_address, vf_key_dist_address);
+    }
+  });
+});
+
+test("Cannot create a batch of asset signatures larger than the rate limit", async () => {
+  await runScenario(async (scenario) => {
+    const appSource = { appBundleSource: { type: "path", value: testAppPath } };
+
+    const [alice] = await scenario.addPlayersWithApps([appSource]);
+
+    await distributeVerificationKey(
+      alice.cells[0],
+      sampleFetchKey(),
+      sampleFetchKeyProof(),
+      sampleFetchKeyProofSignature(),
+    );
+
+    const requests = Array.from({ length: 101 }, (_, i) => ({
+      fetch_url: `https://example.com/v1/sample-${i}.csv`,
+      signature: sampleFetchAssetSignature(),
+      key_type: { MiniSignEd25519: null } as const,
+      verification_key: sampleFetchKey(),
+    }));
+
+    let err_msg = "";
+    try {
+      await createAssetSignatures(alice.cells[0], requests);
+    } catch (e) {
+      err_msg = e.message;
+    }
+    assert.isTrue(err_msg.includes("Too many asset signatures in one batch"));
+
+    const my_asset_signatures = await getMyAssetSignatures(alice.cells[0]);
+    assert.equal(my_asset_signatures.length, 0);
+  });
+});
+
 test("Get my asset signatures", async () => {
   await runScenario(async (scenario) => {
     const appSource = { appBundleSource: { type: "path", value: testAppPath } };
@@ -199,7 +491,7 @@ test("Cannot resign an asset", async () => {
   });
 });
 
-test("Cannot resign an asset after deleting the original signature", async () => {
+test("Can resign an asset after deleting the original signature", async () => {
   await runScenario(async (scenario) => {
     const appSource = { appBundleSource: { type: "path", value: testAppPath } };
 
@@ -212,7 +504,7 @@ test("Cannot resign an asset after deleting the original signature", async () =>
       sampleFetchKeyProofSignature(),
     );
 
-    await createAssetSignature(alice.cells[0], {
+    const original_address = await createAssetSignature(alice.cells[0], {
       fetch_url: "https://example.com/sample.csv",
       signature: sampleFetchAssetSignature(),
       key_type: { MiniSignEd25519: null },
@@ -226,6 +518,64 @@ test("Cannot resign an asset after deleting the original signature", async () =>
       fetch_url: "https://example.com/sample.csv",
     });
 
+    const check_signatures_after_delete = await prepareFetchLocal(
+      alice.cells[0],
+      {
+        fetch_url: "https://example.com/sample.csv",
+      },
+    );
+    assert.equal(check_signatures_after_delete.length, 0);
+
+    const new_address = await createAssetSignature(alice.cells[0], {
+      fetch_url: "https://example.com/sample.csv",
+      signature: sampleFetchAssetSignature(),
+      key_type: { MiniSignEd25519: null },
+      verification_key: sampleFetchKey(),
+    });
+    assert.notDeepEqual(new_address, original_address);
+
+    const mySignaturesAfter = await getMyAssetSignatures(alice.cells[0]);
+    assert.equal(mySignaturesAfter.length, 1);
+
+    const check_signatures = await prepareFetchLocal(alice.cells[0], {
+      fetch_url: "https://example.com/sample.csv",
+    });
+    assert.equal(check_signatures.length, 1);
+    assert.equal(check_signatures[0].reason, "Mine");
+  });
+});
+
+test("Cannot resign an asset twice after deleting the original signature", async () => {
+  await runScenario(async (scenario) => {
+    const appSource = { appBundleSource: { type: "path", value: testAppPath } };
+
+    const [alice] = await scenario.addPlayersWithApps([appSource]);
+
+    await distributeVerificationKey(
+      alice.cells[0],
+      sampleFetchKey(),
+      sampleFetchKeyProof(),
+      sampleFetchKeyProofSignature(),
+    );
+
+    await createAssetSignature(alice.cells[0], {
+      fetch_url: "https://example.com/sample.csv",
+      signature: sampleFetchAssetSignature(),
+      key_type: { MiniSignEd25519: null },
+      verification_key: sampleFetchKey(),
+    });
+
+    await deleteAssetSignature(alice.cells[0], {
+      fetch_url: "https://example.com/sample.csv",
+    });
+
+    await createAssetSignature(alice.cells[0], {
+      fetch_url: "https://example.com/sample.csv",
+      signature: sampleFetchAssetSignature(),
+      key_type: { MiniSignEd25519: null },
+      verification_key: sampleFetchKey(),
+    });
+
     let err_msg = "";
     try {
       await createAssetSignature(alice.cells[0], {
@@ -244,7 +594,68 @@ test("Cannot resign an asset after deleting the original signature", async () =>
     );
 
     const mySignaturesAfter = await getMyAssetSignatures(alice.cells[0]);
-    assert.equal(mySignaturesAfter.length, 0);
+    assert.equal(mySignaturesAfter.length, 1);
+  });
+});
+
+test("Cannot create an asset signature with a signature from a different key", async () => {
+  await runScenario(async (scenario) => {
+    const appSource = { appBundleSource: { type: "path", value: testAppPath } };
+
+    const [alice] = await scenario.addPlayersWithApps([appSource]);
+
+    await distributeVerificationKey(
+      alice.cells[0],
+      sampleFetchKey(),
+      sampleFetchKeyProof(),
+      sampleFetchKeyProofSignature(),
+    );
+
+    let err_msg = "";
+    try {
+      await createAssetSignature(alice.cells[0], {
+        fetch_url: "https://example.com/sample.csv",
+        signature: sampleFetchOtherAssetSignature(),
+        key_type: { MiniSignEd25519: null },
+        verification_key: sampleFetchKey(),
+      });
+    } catch (e) {
+      err_msg = e.message;
+    }
+    assert.isTrue(
+      err_msg.includes("The signature was not created by the referenced key"),
+    );
+
+    const mySignatures = await getMyAssetSignatures(alice.cells[0]);
+    assert.equal(mySignatures.length, 0);
+  });
+});
+
+test("Cannot create an asset signature for a non-http fetch URL", async () => {
+  await runScenario(async (scenario) => {
+    const appSource = { appBundleSource: { type: "path", value: testAppPath } };
+
+    const [alice] = await scenario.addPlayersWithApps([appSource]);
+
+    await distributeVerificationKey(
+      alice.cells[0],
+      sampleFetchKey(),
+      sampleFetchKeyProof(),
+      sampleFetchKeyProofSignature(),
+    );
+
+    let err_msg = "";
+    try {
+      await createAssetSignature(alice.cells[0], {
+        fetch_url: "ftp://example.com/sample.csv",
+        signature: sampleFetchAssetSignature(),
+        key_type: { MiniSignEd25519: null },
+        verification_key: sampleFetchKey(),
+      });
+    } catch (e) {
+      err_msg = e.message;
+    }
+    assert.isTrue(err_msg.includes("The fetch URL must use http or https"));
   });
 });
 
@@ -325,6 +736,186 @@ test("Signatures from multiple selection strategies", async () => {
   });
 });
 
+test("Signatures from a publisher are always included", async () => {
+  await runScenario(async (scenario) => {
+    const appSource = { appBundleSource: { type: "path", value: testAppPath } };
+
+    const [alice, bob] = await scenario.addPlayersWithApps([
+      appSource,
+      appSource,
+    ]);
+
+    await distributeVerificationKey(
+      alice.cells[0],
+      sampleFetchKey(),
+      sampleFetchKeyProof(),
+      sampleFetchKeyProofSignature(),
+    );
+
+    await createPublisherClaim(alice.cells[0], {
+      url_prefix: "https://example.com/tools/",
+      key_type: { MiniSignEd25519: null },
+      verification_key: sampleFetchKey(),
+    });
+
+    await createAssetSignature(alice.cells[0], {
+      fetch_url: "https://example.com/tools/v1/sample.csv",
+      signature: sampleFetchAssetSignature(),
+      key_type: { MiniSignEd25519: null },
+      verification_key: sampleFetchKey(),
+    });
+
+    await dhtSync([alice, bob], alice.cells[0].cell_id[0]);
+
+    const check_signatures = await prepareFetch(bob.cells[0], {
+      fetch_url: "https://example.com/tools/v1/sample.csv",
+    });
+
+    assert.equal(check_signatures.length, 1);
+    assert.deepEqual(check_signatures[0].author, alice.agentPubKey);
+    assert.equal(check_signatures[0].reason, "Publisher");
+
+    // The claim doesn't cover other paths
+    await createAssetSignature(alice.cells[0], {
+      fetch_url: "https://example.com/other/sample.csv",
+      signature: sampleFetchAssetSignature(),
+      key_type: { MiniSignEd25519: null },
+      verification_key: sampleFetchKey(),
+    });
+
+    await dhtSync([alice, bob], alice.cells[0].cell_id[0]);
+
+    const other_signatures = await prepareFetch(bob.cells[0], {
+      fetch_url: "https://example.com/other/sample.csv",
+    });
+
+    assert.equal(other_signatures.length, 1);
+    assert.equal(other_signatures[0].reason, "RandomRecent");
+  });
+});
+
+test("Cannot create a publisher claim for a URL prefix that is not a directory", async () => {
+  await runScenario(async (scenario) => {
+    const appSource = { appBundleSource: { type: "path", value: testAppPath } };
+
+    const [alice] = await scenario.addPlayersWithApps([appSource]);
+
+    await distributeVerificationKey(
+      alice.cells[0],
+      sampleFetchKey(),
+      sampleFetchKeyProof(),
+      sampleFetchKeyProofSignature(),
+    );
+
+    let err_msg = "";
+    try {
+      await createPublisherClaim(alice.cells[0], {
+        url_prefix: "https://example.com/tools",
+        key_type: { MiniSignEd25519: null },
+        verification_key: sampleFetchKey(),
+      });
+    } catch (e) {
+      err_msg = e.message;
+    }
+    assert.isTrue(err_msg.includes("The URL prefix must end with `/`"));
+  });
+});
+
+test("Cannot create a publisher claim for a whole origin", async () => {
+  await runScenario(async (scenario) => {
+    const appSource = { appBundleSource: { type: "path", value: testAppPath } };
+
+    const [alice] = await scenario.addPlayersWithApps([appSource]);
+
+    await distributeVerificationKey(
+      alice.cells[0],
+      sampleFetchKey(),
+      sampleFetchKeyProof(),
+      sampleFetchKeyProofSignature(),
+    );
+
+    let err_msg = "";
+    try {
+      await createPublisherClaim(alice.cells[0], {
+        url_prefix: "https://example.com/",
+        key_type: { MiniSignEd25519: null },
+        verification_key: sampleFetchKey(),
+      });
+    } catch (e) {
+      err_msg = e.message;
+    }
+    assert.isTrue(
+      err_msg.includes("The URL prefix must have a path below the origin"),
+    );
+  });
+});
+
+test("Domain claims are visible to other agents", async () => {
+  await runScenario(async (scenario) => {
+    const appSource = { appBundleSource: { type: "path", value: testAppPath } };
+
+    const [alice, bob] = await scenario.addPlayersWithApps([
+      appSource,
+      appSource,
+    ]);
+
+    const record = await distributeVerificationKey(
+      alice.cells[0],
+      sampleFetchKey(),
+      sampleFetchKeyProof(),
+      sampleFetchKeyProofSignature(),
+    );
+    const vf_key_dist_address = record.signed_action.hashed.hash;
+
+    await createDomainClaim(alice.cells[0], {
+      domain: "Downloads.Example.com",
+      key_type: { MiniSignEd25519: null },
+      verification_key: sampleFetchKey(),
+    });
+
+    await dhtSync([alice, bob], alice.cells[0].cell_id[0]);
+
+    const claims = await getDomainClaims(
+      bob.cells[0],
+      "downloads.example.com",
+    );
+    assert.equal(claims.length, 1);
+    assert.equal(claims[0].domain, "downloads.example.com");
+    assert.deepEqual(claims[0].author, alice.agentPubKey);
+    assert.deepEqual(claims[0].key_dist_address, vf_key_dist_address);
+
+    const otherClaims = await getDomainClaims(bob.cells[0], "example.com");
+    assert.equal(otherClaims.length, 0);
+  });
+});
+
+test("Cannot create a domain claim for something that is not a domain", async () => {
+  await runScenario(async (scenario) => {
+    const appSource = { appBundleSource: { type: "path", value: testAppPath } };
+
+    const [alice] = await scenario.addPlayersWithApps([appSource]);
+
+    await distributeVerificationKey(
+      alice.cells[0],
+      sampleFetchKey(),
+      sampleFetchKeyProof(),
+      sampleFetchKeyProofSignature(),
+    );
+
+    let err_msg = "";
+    try {
+      await createDomainClaim(alice.cells[0], {
+        domain: "https://example.com/tools",
+        key_type: { MiniSignEd25519: null },
+        verification_key: sampleFetchKey(),
+      });
+    } catch (e) {
+      err_msg = e.message;
+    }
+    assert.isTrue(err_msg.includes("Not a valid domain name"));
+  });
+});
+
 test("Remote validation", async () => {
   await runScenario(async (scenario) => {
     const appSource = { appBundleSource: { type: "path", value: testAppPath } };
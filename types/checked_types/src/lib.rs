@@ -1,4 +1,10 @@
-use holochain_zome_types::prelude::{ActionHash, AgentPubKey, Timestamp};
+pub mod canonical_url;
+#[cfg(feature = "hdi")]
+pub mod properties;
+
+use holochain_zome_types::prelude::{
+    decode, ActionHash, AgentPubKey, SerializedBytes, SerializedBytesError, Timestamp,
+};
 use serde::{Deserialize, Serialize};
 
 /// Supported key types for verification keys.
@@ -20,6 +26,17 @@ pub struct DistributeVfKeyRequest {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct PrepareFetchRequest {
     pub fetch_url: String,
+    /// Also consider signatures made for URLs that have been asserted to serve the same asset.
+    #[serde(default)]
+    pub include_aliases: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateAssetAlias {
+    /// A URL that you have already signed the asset for.
+    pub fetch_url: String,
+    /// Another URL that you have signed the same asset for, with the same key.
+    pub alias_url: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -35,6 +52,8 @@ pub struct FetchCheckSignaturePinned {
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub enum FetchCheckSignatureReason {
+    /// Made with a key whose owner has claimed to publish assets under the fetch URL.
+    Publisher,
     RandomRecent,
     RandomHistorical,
     Pinned(FetchCheckSignaturePinned),
@@ -49,6 +68,9 @@ pub struct FetchCheckSignature {
     pub author: AgentPubKey,
     pub key_dist_address: ActionHash,
     pub reason: FetchCheckSignatureReason,
+    /// Set when the signature was found through an alias, to the URL that it was made for.
+    #[serde(default)]
+    pub alias_url: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -57,6 +79,65 @@ pub struct CreateAssetSignature {
     pub signature: String,
     pub key_type: VerificationKeyType,
     pub verification_key: String,
+    /// The SHA-256 digest of the signed content. When provided, the signature is also indexed by
+    /// this digest so that it can be found without knowing the URL.
+    #[serde(default)]
+    pub content_sha256: Option<Vec<u8>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FindSignaturesByDigestRequest {
+    /// The SHA-256 digest of the content to find signatures for.
+    pub content_sha256: Vec<u8>,
+}
+
+/// A signature that was found by the digest of the content it claims to sign.
+///
+/// The digest is provided by the signer and can't be checked on Holochain, so the signature must be
+/// verified against the content before it is trusted.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DigestSignature {
+    /// The URL that the signer fetched the asset from.
+    pub fetch_url: String,
+    pub signature: String,
+    pub key_type: VerificationKeyType,
+    pub verification_key: String,
+    pub author: AgentPubKey,
+    pub key_dist_address: ActionHash,
+    /// When the signature was published on Holochain.
+    pub created_at: Timestamp,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreatePublisherClaim {
+    /// The URL prefix that you publish assets under, which must end with `/`.
+    pub url_prefix: String,
+    pub key_type: VerificationKeyType,
+    /// The key that you sign published assets with. It must already be distributed.
+    pub verification_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateDomainClaim {
+    /// The domain that you control, such as `downloads.example.org`.
+    pub domain: String,
+    pub key_type: VerificationKeyType,
+    /// The key to associate with the domain. It must already be distributed.
+    pub verification_key: String,
+}
+
+/// A claim that a key belongs to the owner of a domain.
+///
+/// The claim is only an assertion by its author. It should be checked against the `.well-known`
+/// file served by the domain before it is trusted.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DomainClaimResponse {
+    pub domain: String,
+    pub author: AgentPubKey,
+    pub key_dist_address: ActionHash,
+    pub key_type: VerificationKeyType,
+    pub verification_key: String,
+    pub created_at: Timestamp,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -73,3 +154,169 @@ pub struct AssetSignatureResponse {
     /// When the signature was published on Holochain.
     pub created_at: Timestamp,
 }
+
+/// Properties that can be set on the `checked` DNA, so that private deployments can tune the
+/// behaviour of the network.
+///
+/// Any property that is not provided takes its default value.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct CheckedDnaProperties {
+    pub rate_limits: RateLimits,
+}
+
+impl CheckedDnaProperties {
+    /// Decode the properties that the DNA was installed with. No properties, `~` in the DNA
+    /// manifest, gives the default properties.
+    pub fn try_from_dna_properties(
+        properties: &SerializedBytes,
+    ) -> Result<Self, SerializedBytesError> {
+        let properties: Option<Self> = decode(properties.bytes())?;
+        Ok(properties.unwrap_or_default())
+    }
+}
+
+/// Limits on how quickly a single agent can create each type of entry.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct RateLimits {
+    pub verification_key_dist: RateLimit,
+    pub verification_key_dist_mark: RateLimit,
+    pub asset_signature: RateLimit,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self {
+            verification_key_dist: RateLimit {
+                max_count: 10,
+                window_seconds: 60 * 60 * 24, // 1 day
+            },
+            verification_key_dist_mark: RateLimit {
+                max_count: 10,
+                window_seconds: 60 * 60 * 24, // 1 day
+            },
+            asset_signature: RateLimit {
+                max_count: 100,
+                window_seconds: 60 * 60, // 1 hour
+            },
+        }
+    }
+}
+
+/// Permit at most `max_count` creates within any window of `window_seconds`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub max_count: u32,
+    pub window_seconds: u64,
+}
+
+impl RateLimit {
+    /// Whether a create at `at` would exceed this limit, given the times of the author's previous
+    /// creates of the same type.
+    pub fn is_exceeded_by(
+        &self,
+        at: Timestamp,
+        previous: impl IntoIterator<Item = Timestamp>,
+    ) -> bool {
+        let window_start = at
+            .as_micros()
+            .saturating_sub((self.window_seconds as i64).saturating_mul(1_000_000));
+
+        let in_window = previous
+            .into_iter()
+            .filter(|t| t.as_micros() > window_start && t <= &at)
+            .count();
+
+        in_window >= self.max_count as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use holochain_zome_types::prelude::{encode, SerializedBytes, Timestamp, UnsafeBytes};
+    use serde::Serialize;
+
+    use super::{CheckedDnaProperties, RateLimit};
+
+    const SECOND: i64 = 1_000_000;
+
+    #[test]
+    fn rate_limit_not_exceeded_below_max() {
+        let limit = RateLimit {
+            max_count: 3,
+            window_seconds: 60,
+        };
+
+        let previous = vec![Timestamp(10 * SECOND), Timestamp(20 * SECOND)];
+        assert!(!limit.is_exceeded_by(Timestamp(30 * SECOND), previous));
+    }
+
+    #[test]
+    fn rate_limit_exceeded_at_max() {
+        let limit = RateLimit {
+            max_count: 3,
+            window_seconds: 60,
+        };
+
+        let previous = vec![
+            Timestamp(10 * SECOND),
+            Timestamp(20 * SECOND),
+            Timestamp(25 * SECOND),
+        ];
+        assert!(limit.is_exceeded_by(Timestamp(30 * SECOND), previous));
+    }
+
+    #[test]
+    fn rate_limit_ignores_creates_outside_window() {
+        let limit = RateLimit {
+            max_count: 2,
+            window_seconds: 60,
+        };
+
+        let previous = vec![Timestamp(0), Timestamp(10 * SECOND), Timestamp(80 * SECOND)];
+        assert!(!limit.is_exceeded_by(Timestamp(100 * SECOND), previous));
+    }
+
+    #[test]
+    fn missing_dna_properties_use_defaults() {
+        let properties = SerializedBytes::from(UnsafeBytes::from(encode(&()).unwrap()));
+        let properties = CheckedDnaProperties::try_from_dna_properties(&properties).unwrap();
+
+        assert_eq!(CheckedDnaProperties::default(), properties);
+    }
+
+    #[test]
+    fn partial_dna_properties_fill_defaults() {
+        #[derive(Serialize, Debug)]
+        struct PartialRateLimits {
+            asset_signature: RateLimit,
+        }
+
+        #[derive(Serialize, Debug)]
+        struct PartialProperties {
+            rate_limits: PartialRateLimits,
+        }
+
+        let properties = SerializedBytes::from(UnsafeBytes::from(
+            encode(&PartialProperties {
+                rate_limits: PartialRateLimits {
+                    asset_signature: RateLimit {
+                        max_count: 5,
+                        window_seconds: 10,
+                    },
+                },
+            })
+            .unwrap(),
+        ));
+        let properties = CheckedDnaProperties::try_from_dna_properties(&properties).unwrap();
+
+        assert_eq!(5, properties.rate_limits.asset_signature.max_count);
+        assert_eq!(
+            CheckedDnaProperties::default()
+                .rate_limits
+                .verification_key_dist,
+            properties.rate_limits.verification_key_dist
+        );
+    }
+}
@@ -0,0 +1,15 @@
+use hdi::prelude::*;
+
+use crate::{CheckedDnaProperties, RateLimits};
+
+/// Get the rate limits that this DNA was installed with.
+pub fn get_rate_limits() -> ExternResult<RateLimits> {
+    CheckedDnaProperties::try_from_dna_properties(&dna_info()?.modifiers.properties)
+        .map(|properties| properties.rate_limits)
+        .map_err(|e| {
+            wasm_error!(WasmErrorInner::Guest(format!(
+                "Invalid DNA properties: {}",
+                e
+            )))
+        })
+}
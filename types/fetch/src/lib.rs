@@ -18,3 +18,78 @@ pub struct AssetSignature {
     /// zome (signing_keys) but the same DHT.
     pub key_dist_address: ActionHash,
 }
+
+/// The tag on an `AssetUrlToSignature` link.
+///
+/// The link's own metadata already records who created the signature and when, so together with
+/// this tag a signature can be selected for checking without fetching the [AssetSignature] itself.
+#[derive(Serialize, Deserialize, SerializedBytes, Debug, Clone, PartialEq)]
+pub struct AssetUrlToSignatureTag {
+    /// The address of the public key that signed the linked asset signature.
+    ///
+    /// Must match [AssetSignature::key_dist_address] on the link target.
+    pub key_dist_address: ActionHash,
+}
+
+impl AssetUrlToSignatureTag {
+    /// Encode this tag so that it can be used as a [LinkTag].
+    pub fn to_link_tag(&self) -> ExternResult<LinkTag> {
+        let bytes = SerializedBytes::try_from(self.clone())
+            .map_err(|e| wasm_error!(WasmErrorInner::Guest(e.to_string())))?;
+
+        Ok(LinkTag::new(bytes.bytes().clone()))
+    }
+
+    /// Decode a tag that was created by [AssetUrlToSignatureTag::to_link_tag].
+    pub fn try_from_link_tag(tag: &LinkTag) -> ExternResult<Self> {
+        Self::try_from(SerializedBytes::from(UnsafeBytes::from(tag.0.clone())))
+            .map_err(|e| wasm_error!(WasmErrorInner::Guest(e.to_string())))
+    }
+}
+
+/// An assertion, by its author, that two URLs serve identical content.
+///
+/// The assertion is backed by the author's own [AssetSignature]s for both URLs. Both must have been
+/// made with the same key over the same content, so that the alias is only as trustworthy as the
+/// signatures that it joins together.
+#[hdk_entry_helper]
+pub struct AssetAlias {
+    /// One of the URLs that serves the asset.
+    pub fetch_url: String,
+
+    /// Another URL that serves the same asset.
+    pub alias_url: String,
+
+    /// The address of the author's [AssetSignature] for [AssetAlias::fetch_url].
+    pub fetch_url_signature_address: ActionHash,
+
+    /// The address of the author's [AssetSignature] for [AssetAlias::alias_url].
+    pub alias_url_signature_address: ActionHash,
+}
+
+/// A declaration, by the owner of a key, that they publish the assets found under a URL prefix.
+///
+/// Signatures made with the key for URLs under the prefix are reported separately from those made
+/// by other agents who have fetched the asset.
+#[hdk_entry_helper]
+pub struct PublisherClaim {
+    /// The canonical URL prefix, which must end with `/`. For example,
+    /// `https://downloads.example.org/tool/`.
+    pub url_prefix: String,
+
+    /// The address of the author's key that they sign published assets with.
+    pub key_dist_address: ActionHash,
+}
+
+/// A claim, by the owner of a key, that they control a domain.
+///
+/// Holochain can't check the claim. Instead, the claimant publishes their key and agent at
+/// `https://<domain>/.well-known/checked.json` and anyone relying on the claim checks that file.
+#[hdk_entry_helper]
+pub struct DomainClaim {
+    /// The canonical domain name, such as `downloads.example.org`.
+    pub domain: String,
+
+    /// The address of the author's key that is being associated with the domain.
+    pub key_dist_address: ActionHash,
+}
@@ -2,6 +2,17 @@ use checked_types::VerificationKeyType;
 use chrono::{DateTime, Utc};
 use hdk::prelude::*;
 
+/// The index of the `signing_keys_integrity` zome in the DNA's integrity zomes. It is listed first
+/// in `dna.yaml`, which must be kept in that order.
+pub const SIGNING_KEYS_INTEGRITY_ZOME_INDEX: ZomeIndex = ZomeIndex(0);
+
+/// The entry def index of [VerificationKeyDist] in the `signing_keys_integrity` zome.
+///
+/// Together with [SIGNING_KEYS_INTEGRITY_ZOME_INDEX], this lets other zomes check that an entry
+/// on an agent's chain really is a [VerificationKeyDist], rather than any public entry that
+/// happens to deserialize as one.
+pub const VERIFICATION_KEY_DIST_ENTRY_INDEX: EntryDefIndex = EntryDefIndex(0);
+
 #[hdk_entry_helper]
 #[derive(Clone, PartialEq)]
 pub struct VerificationKeyDist {